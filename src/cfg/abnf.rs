@@ -0,0 +1,454 @@
+//! Importing ABNF (RFC 5234) grammars.
+//!
+//! `parse` reads a rulelist such as
+//!
+//! ```text
+//! ; A comma-separated list of tokens.
+//! list  = token *( "," token )
+//! token = 1*( ALPHA / DIGIT / %x2D-2E )
+//! ```
+//!
+//! Rules start in the first column and continue on indented lines. Rule names are case-insensitive,
+//! and `=/` adds alternatives to a rule. The repetitions `*e`, `n*e`, `*me`, `n*me` and `ne` are
+//! desugared by `cfg::ebnf`, as are groups and options.
+//!
+//! ABNF describes strings down to the character, so character-level constructs become
+//! scannerless terminals (see `cfg::scannerless`): a numeric value `%x41` or range `%x41-5A` is a
+//! terminal of its characters, `%x0D.0A` is a sequence of them, and a string `"ab"` is a
+//! sequence of terminals for each character, in either case unless it is written `%s"ab"`.
+//! Terminals are shared between equal character sets. The core rules of Appendix B are provided
+//! when they are used but not defined: those that are sets of characters, such as `ALPHA`, are
+//! terminals, and `CRLF` and `LWSP` are nonterminals.
+//!
+//! Prose values `<...>` have no grammar to import and are an error.
+
+use std::collections::HashMap;
+use std::fmt;
+use cfg::{Cfg, Mutable, Symbol, EPSILON};
+use cfg::bnf::Position;
+use cfg::ebnf::{self, Construct, Expr};
+use cfg::scannerless::class_terminal;
+use lex::CharSet;
+
+/// An error in an ABNF rulelist.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Error {
+    /// A character that can't start an element or operator.
+    UnexpectedChar(Position, char),
+    /// Something other than what was expected, which is described.
+    Expected(Position, &'static str),
+    /// A string or prose value without its closing delimiter.
+    Unterminated(Position),
+    /// A numeric value that isn't a valid character, or a repetition with its bounds reversed.
+    InvalidNumber(Position),
+    /// A prose value, which can't be imported.
+    Prose(Position),
+    /// A rule defined with `=` more than once.
+    Redefined(Position, String),
+    /// A rule that is used but never defined, and isn't a core rule.
+    Undefined(Position, String),
+    /// The input has no rules.
+    NoRules,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::UnexpectedChar(pos, c) => write!(f, "{}: unexpected character {:?}", pos, c),
+            Error::Expected(pos, what) => write!(f, "{}: expected {}", pos, what),
+            Error::Unterminated(pos) => write!(f, "{}: unterminated string or prose value", pos),
+            Error::InvalidNumber(pos) => write!(f, "{}: invalid numeric value or repetition", pos),
+            Error::Prose(pos) => write!(f, "{}: prose values can't be imported", pos),
+            Error::Redefined(pos, ref name) => write!(f, "{}: rule {} is already defined", pos, name),
+            Error::Undefined(pos, ref name) => write!(f, "{}: rule {} is not defined", pos, name),
+            Error::NoRules => write!(f, "the rulelist has no rules"),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Tok {
+    Name(String),
+    /// `=`, or `=/` if incremental.
+    Define(bool),
+    Slash,
+    /// `(` or `[`.
+    Open(char),
+    /// `)` or `]`.
+    Close(char),
+    /// A repetition's minimum and maximum.
+    Repeat(u32, Option<u32>),
+    /// A sequence of sets of characters, from a string or numeric value.
+    Chars(Vec<CharSet>),
+}
+
+fn ascii_insensitive(c: char) -> CharSet {
+    if c.is_ascii_alphabetic() {
+        let (lower, upper) = (c.to_ascii_lowercase(), c.to_ascii_uppercase());
+        CharSet::from_ranges(&[(upper, upper), (lower, lower)])
+    } else {
+        CharSet::single(c)
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<(Position, Tok)>, Error> {
+    let mut toks = Vec::new();
+    let chars = input.chars().collect::<Vec<_>>();
+    let (mut i, mut line, mut col) = (0, 1, 1);
+
+    fn number(chars: &[char], i: &mut usize, radix: u32) -> Option<u32> {
+        let start = *i;
+        while *i < chars.len() && chars[*i].is_digit(radix) {
+            *i += 1;
+        }
+        let digits = chars[start..*i].iter().cloned().collect::<String>();
+        u32::from_str_radix(&digits, radix).ok()
+    }
+
+    while i < chars.len() {
+        let c = chars[i];
+        let pos = Position { line: line, column: col };
+        let start = i;
+        if c == '\n' {
+            i += 1;
+            line += 1;
+            col = 1;
+            continue;
+        } else if c.is_whitespace() {
+            i += 1;
+        } else if c == ';' {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c.is_ascii_alphabetic() {
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '-') {
+                i += 1;
+            }
+            toks.push((pos, Tok::Name(chars[start..i].iter().cloned().collect())));
+        } else if c == '=' {
+            i += 1;
+            let incremental = chars.get(i) == Some(&'/');
+            if incremental {
+                i += 1;
+            }
+            toks.push((pos, Tok::Define(incremental)));
+        } else if c == '/' {
+            i += 1;
+            toks.push((pos, Tok::Slash));
+        } else if c == '(' || c == '[' {
+            i += 1;
+            toks.push((pos, Tok::Open(c)));
+        } else if c == ')' || c == ']' {
+            i += 1;
+            toks.push((pos, Tok::Close(c)));
+        } else if c.is_digit(10) || c == '*' {
+            let min = if c == '*' { 0 } else { try!(number(&chars, &mut i, 10).ok_or(Error::InvalidNumber(pos))) };
+            let rep = if chars.get(i) == Some(&'*') {
+                i += 1;
+                let max = if chars.get(i).map_or(false, |c| c.is_digit(10)) {
+                    Some(try!(number(&chars, &mut i, 10).ok_or(Error::InvalidNumber(pos))))
+                } else {
+                    None
+                };
+                Tok::Repeat(min, max)
+            } else {
+                Tok::Repeat(min, Some(min))
+            };
+            if let Tok::Repeat(min, Some(max)) = rep {
+                if max < min {
+                    return Err(Error::InvalidNumber(pos));
+                }
+            }
+            toks.push((pos, rep));
+        } else if c == '"' || (c == '%' && (chars.get(i + 1) == Some(&'s') || chars.get(i + 1) == Some(&'i'))) {
+            let sensitive = c == '%' && chars[i + 1] == 's';
+            if c == '%' {
+                i += 2;
+                if chars.get(i) != Some(&'"') {
+                    return Err(Error::Expected(pos, "a string after %s or %i"));
+                }
+            }
+            i += 1;
+            let mut sets = Vec::new();
+            loop {
+                match chars.get(i) {
+                    Some(&'"') => break,
+                    Some(&'\n') | None => return Err(Error::Unterminated(pos)),
+                    Some(&c) => sets.push(if sensitive { CharSet::single(c) } else { ascii_insensitive(c) }),
+                }
+                i += 1;
+            }
+            i += 1;
+            toks.push((pos, Tok::Chars(sets)));
+        } else if c == '%' {
+            i += 1;
+            let radix = match chars.get(i) {
+                Some(&'x') | Some(&'X') => 16,
+                Some(&'d') | Some(&'D') => 10,
+                Some(&'b') | Some(&'B') => 2,
+                _ => return Err(Error::Expected(pos, "`x`, `d` or `b` after %")),
+            };
+            i += 1;
+            let value = |n: Option<u32>| n.and_then(::std::char::from_u32).ok_or(Error::InvalidNumber(pos));
+            let first = try!(value(number(&chars, &mut i, radix)));
+            let mut sets = vec![CharSet::single(first)];
+            if chars.get(i) == Some(&'-') {
+                i += 1;
+                let last = try!(value(number(&chars, &mut i, radix)));
+                if last < first {
+                    return Err(Error::InvalidNumber(pos));
+                }
+                sets = vec![CharSet::range(first, last)];
+            } else {
+                while chars.get(i) == Some(&'.') {
+                    i += 1;
+                    sets.push(CharSet::single(try!(value(number(&chars, &mut i, radix)))));
+                }
+            }
+            toks.push((pos, Tok::Chars(sets)));
+        } else if c == '<' {
+            return Err(if chars[i..].contains(&'>') { Error::Prose(pos) } else { Error::Unterminated(pos) });
+        } else {
+            return Err(Error::UnexpectedChar(pos, c));
+        }
+        col += i - start;
+    }
+    Ok(toks)
+}
+
+// The core rules that are sets of characters.
+fn core_class(name: &str) -> Option<CharSet> {
+    let r = |lo: u32, hi: u32| (::std::char::from_u32(lo).unwrap(), ::std::char::from_u32(hi).unwrap());
+    let ranges = match name {
+        "alpha" => vec![r(0x41, 0x5A), r(0x61, 0x7A)],
+        "bit" => vec![r(0x30, 0x31)],
+        "char" => vec![r(0x01, 0x7F)],
+        "cr" => vec![r(0x0D, 0x0D)],
+        "ctl" => vec![r(0x00, 0x1F), r(0x7F, 0x7F)],
+        "digit" => vec![r(0x30, 0x39)],
+        "dquote" => vec![r(0x22, 0x22)],
+        "hexdig" => vec![r(0x30, 0x39), r(0x41, 0x46), r(0x61, 0x66)],
+        "htab" => vec![r(0x09, 0x09)],
+        "lf" => vec![r(0x0A, 0x0A)],
+        "octet" => vec![r(0x00, 0xFF)],
+        "sp" => vec![r(0x20, 0x20)],
+        "vchar" => vec![r(0x21, 0x7E)],
+        "wsp" => vec![r(0x09, 0x09), r(0x20, 0x20)],
+        _ => return None,
+    };
+    Some(CharSet::from_ranges(&ranges))
+}
+
+// A name for a terminal of `set`, in ABNF notation.
+fn class_name(set: &CharSet) -> String {
+    let ranges = set.ranges();
+    let printable = |c: char| c > ' ' && c <= '~' && c != '"';
+    match ranges.len() {
+        1 if ranges[0].0 == ranges[0].1 && printable(ranges[0].0) && !ranges[0].0.is_ascii_alphabetic() =>
+            format!("\"{}\"", ranges[0].0),
+        1 if ranges[0].0 == ranges[0].1 => format!("%x{:02X}", ranges[0].0 as u32),
+        2 if ranges[0].0 == ranges[0].1 && ranges[1].0 == ranges[1].1 && ranges[0].0.is_ascii_uppercase()
+             && ranges[1].0 == ranges[0].0.to_ascii_lowercase() => format!("\"{}\"", ranges[1].0),
+        _ => ranges.iter().map(|&(lo, hi)| if lo == hi {
+            format!("%x{:02X}", lo as u32)
+        } else {
+            format!("%x{:02X}-{:02X}", lo as u32, hi as u32)
+        }).collect::<Vec<_>>().join(" / "),
+    }
+}
+
+struct Parser<'a> {
+    toks: &'a [(Position, Tok)],
+    i: usize,
+    end: Position,
+    cfg: Cfg<Mutable>,
+    /// The nonterminal of each rule defined in the input, by lower case name.
+    rules: HashMap<String, Symbol>,
+    /// Core rules used but not defined, by lower case name.
+    core: HashMap<String, Symbol>,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a Tok> {
+        self.toks.get(self.i).map(|&(_, ref tok)| tok)
+    }
+
+    fn position(&self) -> Position {
+        self.toks.get(self.i).map_or(self.end, |&(pos, _)| pos)
+    }
+
+    // Whether the next rule starts here.
+    fn at_rule(&self) -> bool {
+        match self.toks.get(self.i) {
+            Some(&(pos, Tok::Name(_))) => pos.column == 1,
+            _ => false,
+        }
+    }
+
+    fn reference(&mut self, name: &str, pos: Position) -> Result<Symbol, Error> {
+        let key = name.to_lowercase();
+        if let Some(&nt) = self.rules.get(&key).or(self.core.get(&key)) {
+            return Ok(nt);
+        }
+        let sym = match core_class(&key) {
+            Some(set) => class_terminal(&mut self.cfg, set, &name.to_uppercase()),
+            None if key == "crlf" || key == "lwsp" => self.cfg.add_named_nonterminal(&name.to_uppercase()),
+            None => return Err(Error::Undefined(pos, name.to_string())),
+        };
+        self.core.insert(key, sym);
+        Ok(sym)
+    }
+
+    fn alternation(&mut self) -> Result<Expr, Error> {
+        let mut alts = vec![try!(self.concatenation())];
+        while let Some(&Tok::Slash) = self.peek() {
+            self.i += 1;
+            alts.push(try!(self.concatenation()));
+        }
+        Ok(if alts.len() == 1 { alts.pop().unwrap() } else { Expr::Alt(alts) })
+    }
+
+    fn concatenation(&mut self) -> Result<Expr, Error> {
+        let mut items = Vec::new();
+        while !self.at_rule() {
+            match self.peek() {
+                Some(&Tok::Slash) | Some(&Tok::Close(_)) | None => break,
+                _ => items.push(try!(self.repetition())),
+            }
+        }
+        if items.is_empty() {
+            return Err(Error::Expected(self.position(), "an element"));
+        }
+        Ok(if items.len() == 1 { items.pop().unwrap() } else { Expr::Seq(items) })
+    }
+
+    fn repetition(&mut self) -> Result<Expr, Error> {
+        let pos = self.position();
+        let (min, max) = match self.peek() {
+            Some(&Tok::Repeat(min, max)) => { self.i += 1; (min, max) },
+            _ => (1, Some(1)),
+        };
+        let e = try!(self.element());
+        let mut items = vec![e.clone(); min as usize];
+        match max {
+            None if min == 0 => items.push(Expr::Op(Construct::Star, pos, Box::new(e))),
+            // `e e*` as `e+`, so that the first occurrence isn't lowered twice.
+            None => {
+                items.pop();
+                items.push(Expr::Op(Construct::Plus, pos, Box::new(e)));
+            },
+            // Nested options, `e [e [e]]`, rather than a choice between lengths.
+            Some(max) => {
+                let mut rest = None;
+                for _ in min..max {
+                    let inner = match rest {
+                        Some(rest) => Expr::Seq(vec![e.clone(), rest]),
+                        None => e.clone(),
+                    };
+                    rest = Some(Expr::Op(Construct::Optional, pos, Box::new(inner)));
+                }
+                items.extend(rest);
+            },
+        }
+        Ok(if items.len() == 1 { items.pop().unwrap() } else { Expr::Seq(items) })
+    }
+
+    fn element(&mut self) -> Result<Expr, Error> {
+        let pos = self.position();
+        let e = match self.peek() {
+            Some(&Tok::Name(ref name)) => Expr::Symbol(try!(self.reference(name, pos))),
+            Some(&Tok::Chars(ref sets)) => Expr::Seq(sets.iter().map(|set| {
+                Expr::Symbol(class_terminal(&mut self.cfg, set.clone(), &class_name(set)))
+            }).collect()),
+            Some(&Tok::Open(open)) => {
+                self.i += 1;
+                let inner = try!(self.alternation());
+                let close = if open == '(' { ')' } else { ']' };
+                if self.peek() != Some(&Tok::Close(close)) {
+                    return Err(Error::Expected(self.position(), if open == '(' { "`)`" } else { "`]`" }));
+                }
+                let construct = if open == '(' { Construct::Group } else { Construct::Optional };
+                Expr::Op(construct, pos, Box::new(inner))
+            },
+            _ => return Err(Error::Expected(pos, "an element")),
+        };
+        self.i += 1;
+        Ok(e)
+    }
+}
+
+/// Parse an ABNF rulelist. The first rule is the start rule.
+pub fn parse(input: &str) -> Result<Cfg<Mutable>, Error> {
+    let toks = try!(tokenize(input));
+    let end = toks.last().map_or(Position { line: 1, column: 1 }, |&(pos, _)| pos);
+    let mut p = Parser {
+        toks: &toks, i: 0, end: end, cfg: Cfg::new(),
+        rules: HashMap::new(), core: HashMap::new(),
+    };
+
+    // Every rule is a nonterminal, in order of definition.
+    for w in toks.windows(2) {
+        if let (&(pos, Tok::Name(ref name)), &(_, Tok::Define(_))) = (&w[0], &w[1]) {
+            if pos.column == 1 && !p.rules.contains_key(&name.to_lowercase()) {
+                let nt = p.cfg.add_named_nonterminal(name);
+                p.rules.insert(name.to_lowercase(), nt);
+            }
+        }
+    }
+
+    // The alternatives of each rule, gathered over its incremental definitions.
+    let mut defs: Vec<(Symbol, Vec<Expr>)> = Vec::new();
+    while p.i < toks.len() {
+        let (pos, name) = match toks[p.i] {
+            (pos, Tok::Name(ref name)) if pos.column == 1 => (pos, name),
+            (pos, _) => return Err(Error::Expected(pos, "a rule name in the first column")),
+        };
+        let next = toks.get(p.i + 1).map_or(end, |&(pos, _)| pos);
+        // Only names followed by `=` or `=/` were made rules.
+        let nt = match p.rules.get(&name.to_lowercase()) {
+            Some(&nt) => nt,
+            None => return Err(Error::Expected(next, "`=` or `=/`")),
+        };
+        let incremental = match toks.get(p.i + 1) {
+            Some(&(_, Tok::Define(incremental))) => incremental,
+            _ => return Err(Error::Expected(next, "`=` or `=/`")),
+        };
+        p.i += 2;
+        let e = try!(p.alternation());
+        if !p.at_rule() && p.i < toks.len() {
+            return Err(Error::Expected(p.position(), "an element"));
+        }
+        match defs.iter().position(|&(lhs, _)| lhs == nt) {
+            Some(_) if !incremental => return Err(Error::Redefined(pos, name.clone())),
+            Some(i) => match e {
+                Expr::Alt(alts) => defs[i].1.extend(alts),
+                e => defs[i].1.push(e),
+            },
+            None => defs.push((nt, match e { Expr::Alt(alts) => alts, e => vec![e] })),
+        }
+    }
+    if defs.is_empty() {
+        return Err(Error::NoRules);
+    }
+
+    for &(lhs, ref alts) in &defs {
+        let rules = ebnf::add_rules(&mut p.cfg, lhs, &Expr::Alt(alts.clone()));
+        if lhs == defs[0].0 {
+            p.cfg.set_start(rules[0]);
+        }
+    }
+
+    // LWSP = *(WSP / CRLF WSP), as LWSP -> WSP LWSP | CRLF WSP LWSP | ε.
+    if let Some(&lwsp) = p.core.get("lwsp") {
+        let (wsp, crlf) = (try!(p.reference("WSP", end)), try!(p.reference("CRLF", end)));
+        p.cfg.add_rule(lwsp, &[wsp, lwsp]);
+        p.cfg.add_rule(lwsp, &[crlf, wsp, lwsp]);
+        p.cfg.add_rule(lwsp, &[EPSILON]);
+    }
+    // CRLF = CR LF
+    if let Some(&crlf) = p.core.get("crlf") {
+        let (cr, lf) = (try!(p.reference("CR", end)), try!(p.reference("LF", end)));
+        p.cfg.add_rule(crlf, &[cr, lf]);
+    }
+    Ok(p.cfg)
+}
@@ -0,0 +1,189 @@
+//! Detecting ambiguous grammars.
+//!
+//! Whether a grammar is ambiguous is undecidable, so there are two partial answers:
+//!
+//! - `find` searches for a terminal string with two distinct leftmost derivations, within bounds
+//!   on the length of the string and the number of steps of the derivations. It finds the
+//!   ambiguity with the fewest steps first.
+//! - `prove_unambiguous` is sound but incomplete: an LL(1) grammar, or one with an SLR(1) table
+//!   without conflicts, is unambiguous. Precedence declarations are ignored, since they resolve
+//!   conflicts by choosing between the parses of an ambiguous grammar.
+//!
+//! `check` tries both.
+
+use std::collections::HashMap;
+use cfg::{Cfg, Frozen, Rule, Symbol, PackedSymbol, SymbolTable, EPSILON};
+use cfg::{ll1, lr};
+use cfg::prec::Precedence;
+use cfg::util::compute_min_yield;
+
+/// A terminal string with two distinct leftmost derivations.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Ambiguity {
+    pub sentence: Vec<Symbol>,
+    pub first: Vec<Rule>,
+    pub second: Vec<Rule>,
+    /// The sentential form both derivations reach before deriving `sentence` the same way.
+    pub form: Vec<Symbol>,
+}
+
+impl Ambiguity {
+    /// Describe the ambiguity, naming symbols by `names`.
+    pub fn describe(&self, names: &SymbolTable) -> String {
+        let sentence: Vec<String> = self.sentence.iter().map(|&t| names.display(t).to_string()).collect();
+        let rules = |d: &[Rule]| d.iter().map(|r| r.0.to_string()).collect::<Vec<_>>().join(", ");
+        format!("`{}` has two leftmost derivations: rules {} and rules {}", sentence.join(" "), rules(&self.first), rules(&self.second))
+    }
+}
+
+/// Why a grammar is unambiguous.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Proof {
+    /// The grammar is LL(1).
+    Ll1,
+    /// The grammar's SLR(1) table has no conflicts.
+    Slr1,
+}
+
+/// What is known about the ambiguity of a grammar.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Verdict {
+    Unambiguous(Proof),
+    Ambiguous(Ambiguity),
+    /// Neither proven unambiguous nor found ambiguous within the bounds.
+    Unknown,
+}
+
+/// A sentential form reached by the search, and the step that reached it.
+struct Node {
+    form: Vec<PackedSymbol>,
+    /// The node it was derived from and the rule applied, or None for the start symbol.
+    step: Option<(usize, Rule)>,
+}
+
+/// The rules applied to reach `nodes[i]`.
+fn derivation(nodes: &[Node], mut i: usize) -> Vec<Rule> {
+    let mut rules = Vec::new();
+    while let Some((parent, rule)) = nodes[i].step {
+        rules.push(rule);
+        i = parent;
+    }
+    rules.reverse();
+    rules
+}
+
+/// Search for a terminal string of at most `max_length` terminals with two distinct leftmost
+/// derivations of at most `max_steps` steps each, before completing them.
+///
+/// Two leftmost derivations that reach the same sentential form are completed the same way, with
+/// a shortest derivation of each nonterminal left, into derivations of the same string.
+pub fn find<T>(cfg: &Cfg<T>, max_length: usize, max_steps: usize) -> Option<Ambiguity> {
+    search(cfg, max_length, max_steps, |_| true)
+}
+
+/// Search like `find`, passing each ambiguity found to `accept` until it returns true, and
+/// returning that one.
+pub fn search<T, F>(cfg: &Cfg<T>, max_length: usize, max_steps: usize, mut accept: F) -> Option<Ambiguity>
+    where F: FnMut(&Ambiguity) -> bool
+{
+    let min = compute_min_yield(cfg);
+    let start = match cfg.rules().nth(cfg.start) {
+        Some(&(start, _)) => start,
+        None => return None,
+    };
+    // The least number of terminals a form derives, if it derives any string.
+    let min_length = |form: &[PackedSymbol]| form.iter().fold(Some(0), |len, &sym| len.and_then(|len| {
+        if sym.is_terminal() { Some(len + 1) } else { min[sym.to_index()].map(|(l, _)| len + l) }
+    }));
+    if min_length(&[start]).map_or(true, |len| len > max_length) {
+        return None;
+    }
+
+    let mut rules_of: HashMap<PackedSymbol, Vec<Rule>> = HashMap::new();
+    for (i, &(lhs, _)) in cfg.rules().enumerate() {
+        rules_of.entry(lhs).or_insert_with(Vec::new).push(Rule(i));
+    }
+
+    let mut nodes = vec![Node { form: vec![start], step: None }];
+    let mut seen: HashMap<Vec<PackedSymbol>, usize> = HashMap::new();
+    seen.insert(vec![start], 0);
+    let (mut level, mut next) = (0, 1);
+    for _ in 0..max_steps {
+        for i in level..next {
+            let at = match nodes[i].form.iter().position(|s| s.is_nonterminal()) {
+                Some(at) => at,
+                None => continue,
+            };
+            let nt = nodes[i].form[at];
+            for &rule in rules_of.get(&nt).map_or(&[][..], |r| &r[..]) {
+                let rhs = cfg.get_rule(rule).unwrap().1.iter().cloned().filter(|&s| s != EPSILON);
+                let form: Vec<PackedSymbol> = nodes[i].form[..at].iter().cloned()
+                    .chain(rhs)
+                    .chain(nodes[i].form[at + 1..].iter().cloned())
+                    .collect();
+                if min_length(&form).map_or(true, |len| len > max_length) {
+                    continue;
+                }
+                if let Some(&other) = seen.get(&form) {
+                    let mut first = derivation(&nodes, other);
+                    let mut second = derivation(&nodes, i);
+                    second.push(rule);
+                    let (sentence, rest) = complete(cfg, &min, form.clone());
+                    first.extend(rest.iter().cloned());
+                    second.extend(rest);
+                    let form = form.into_iter().map(Symbol::from).collect();
+                    let ambiguity = Ambiguity { sentence: sentence, first: first, second: second, form: form };
+                    if accept(&ambiguity) {
+                        return Some(ambiguity);
+                    }
+                    continue;
+                }
+                seen.insert(form.clone(), nodes.len());
+                nodes.push(Node { form: form, step: Some((i, rule)) });
+            }
+        }
+        level = next;
+        next = nodes.len();
+        if level == next {
+            break;
+        }
+    }
+    None
+}
+
+/// Derive a shortest terminal string from `form`, returning it and the rules applied.
+fn complete<T>(cfg: &Cfg<T>, min: &[Option<(usize, Rule)>], mut form: Vec<PackedSymbol>) -> (Vec<Symbol>, Vec<Rule>) {
+    let mut rules = Vec::new();
+    while let Some(at) = form.iter().position(|s| s.is_nonterminal()) {
+        let (_, rule) = min[form[at].to_index()].unwrap();
+        let rhs: Vec<PackedSymbol> = cfg.get_rule(rule).unwrap().1.iter().cloned().filter(|&s| s != EPSILON).collect();
+        form.splice(at..at + 1, rhs);
+        rules.push(rule);
+    }
+    (form.into_iter().map(Symbol::from).collect(), rules)
+}
+
+/// Prove `cfg` unambiguous by showing it is LL(1) or SLR(1), or return None if it is neither.
+pub fn prove_unambiguous(cfg: &mut Cfg<Frozen>) -> Option<Proof> {
+    let columns = |row: &Vec<Vec<Rule>>| row.iter().enumerate().all(|(t, cell)| t == EPSILON.to_index() || cell.len() <= 1);
+    if ll1::compute_cells(cfg).iter().all(columns) {
+        return Some(Proof::Ll1);
+    }
+    let prec = cfg.mut_extra().remove::<Precedence>();
+    let table = lr::generate_table(cfg);
+    if let Some(prec) = prec {
+        cfg.mut_extra().insert::<Precedence>(prec);
+    }
+    if table.conflicts.is_empty() { Some(Proof::Slr1) } else { None }
+}
+
+/// Prove `cfg` unambiguous, or else search for an ambiguity within the bounds of `find`.
+pub fn check(cfg: &mut Cfg<Frozen>, max_length: usize, max_steps: usize) -> Verdict {
+    if let Some(proof) = prove_unambiguous(cfg) {
+        return Verdict::Unambiguous(proof);
+    }
+    match find(cfg, max_length, max_steps) {
+        Some(ambiguity) => Verdict::Ambiguous(ambiguity),
+        None => Verdict::Unknown,
+    }
+}
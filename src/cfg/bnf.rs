@@ -1,18 +1,55 @@
+//! Reading and writing grammars in BNF.
+//!
+//! `parse` reads grammar files like:
+//!
+//! ```text
+//! # Sums of numbers.
+//! expr ::= term ('+' term)*
+//! term -> NUM
+//!       | '(' expr ')'
+//! opt  -> ε | "?"
+//! ```
+//!
+//! A rule is a nonterminal, `::=` or `->`, and alternatives separated by `|`; it may span several
+//! lines, and ends where the next rule starts. Symbols are identifiers (letters, digits and `_`,
+//! not starting with a digit), names in angle brackets such as `<if statement>`, or literals in
+//! single or double quotes, which may use the escapes `\\`, `\'`, `\"`, `\n`, `\r` and `\t`.
+//! Identifiers with rules are nonterminals, all other symbols are terminals, and each symbol is
+//! named by its identifier or the contents of its literal. `ε` is ɛ, as is an empty alternative.
+//! `#` starts a comment that runs to the end of the line. The first rule is the start rule.
+//!
+//! Angle-bracketed names take the same escapes as literals, and `\>` as well. Two directives may
+//! appear between rules: `%start A` makes the first rule of `A` the start rule, and `%nonterminal
+//! A B ...` makes the symbols listed nonterminals even though they have no rules.
+//!
+//! Alternatives may also be grouped with `( ... )`, made optional with `[ ... ]` or a trailing
+//! `?`, and repeated with `{ ... }` or `*` for zero or more times, or `+` for one or more. These
+//! are desugared into rules of fresh nonterminals by `cfg::ebnf`.
+//!
+//! `to_string` writes a grammar in the same format, so that `parse` reads it back unchanged, with
+//! the directives it needs. Only the nonterminal of the start rule is kept, so a start rule that
+//! isn't the first rule of its nonterminal is read back as that first rule.
+//!
+//! `from_str` reads the original format of one rule per line and single-character symbols,
+//! upper case for nonterminals.
+
 use cfg;
-use cfg::EPSILON;
+use cfg::{EPSILON, PackedSymbol, Symbol};
+use cfg::ebnf::{self, Construct, Expr};
 use std::collections::HashMap;
+use std::fmt;
 
+/// The character each symbol was written as by `from_str`.
+#[deprecated(note = "symbols are named in the grammar's `SymbolTable`; use `Cfg::symbol_name`")]
 pub struct BNFName;
+#[allow(deprecated)]
 impl ::typemap::Key for BNFName { type Value = HashMap<cfg::Symbol, char>; }
 
-
-
 pub fn from_str(bnf: &str) -> cfg::Cfg<cfg::Mutable> {
     let mut c = cfg::Cfg::new();
 
     let mut terms = HashMap::new();
     let mut nonterms = HashMap::new();
-    let mut names = HashMap::new();
     let mut first_line = true;
 
     for line in bnf.lines() {
@@ -20,8 +57,7 @@ pub fn from_str(bnf: &str) -> cfg::Cfg<cfg::Mutable> {
 
         let mut chars = line.chars();
         if let Some(fst) = chars.next() {
-            nonterms.entry(fst).or_insert_with(|| c.add_nonterminal());
-            names.insert(*nonterms.get(&fst).unwrap(), fst);
+            nonterms.entry(fst).or_insert_with(|| c.add_named_nonterminal(&fst.to_string()));
             let mut seen_any = false;
             let mut syms = Vec::new();
 
@@ -29,13 +65,11 @@ pub fn from_str(bnf: &str) -> cfg::Cfg<cfg::Mutable> {
                 seen_any = true;
                 match sym.is_uppercase() {
                     true => {
-                        nonterms.entry(sym).or_insert_with(||c.add_nonterminal());
-                        names.insert(*nonterms.get(&sym).unwrap(), sym);
+                        nonterms.entry(sym).or_insert_with(|| c.add_named_nonterminal(&sym.to_string()));
                         syms.push(*nonterms.get(&sym).unwrap());
                     },
                     false => {
-                        terms.entry(sym).or_insert_with(|| c.add_terminal());
-                        names.insert(*terms.get(&sym).unwrap(), sym);
+                        terms.entry(sym).or_insert_with(|| c.add_named_terminal(&sym.to_string()));
                         syms.push(*terms.get(&sym).unwrap());
                     }
                 }
@@ -53,21 +87,423 @@ pub fn from_str(bnf: &str) -> cfg::Cfg<cfg::Mutable> {
         first_line = false;
     }
 
+    let names = nonterms.into_iter().chain(terms).map(|(name, sym)| (sym, name)).collect();
+    #[allow(deprecated)]
     c.mut_extra().insert::<BNFName>(names);
-
     c
 }
 
+/// A line and column in the input, both counted from 1.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// An error in a grammar file.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Error {
+    /// A character that can't start a symbol or operator.
+    UnexpectedChar(Position, char),
+    /// Something other than what was expected, which is described.
+    Expected(Position, &'static str),
+    /// A literal or angle-bracketed name without its closing delimiter.
+    Unterminated(Position),
+    /// A literal containing an unknown escape sequence.
+    InvalidEscape(Position, char),
+    /// An empty literal or angle-bracketed name.
+    EmptyName(Position),
+    /// A terminal with the same name as a nonterminal.
+    NameClash(Position, String),
+    /// A directive other than `%start` and `%nonterminal`.
+    UnknownDirective(Position, String),
+    /// The input has no rules.
+    NoRules,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::UnexpectedChar(pos, c) => write!(f, "{}: unexpected character {:?}", pos, c),
+            Error::Expected(pos, what) => write!(f, "{}: expected {}", pos, what),
+            Error::Unterminated(pos) => write!(f, "{}: unterminated literal or name", pos),
+            Error::InvalidEscape(pos, c) => write!(f, "{}: invalid escape sequence \\{}", pos, c),
+            Error::EmptyName(pos) => write!(f, "{}: empty literal or name", pos),
+            Error::NameClash(pos, ref name) => write!(f, "{}: terminal {:?} is also the name of a nonterminal", pos, name),
+            Error::UnknownDirective(pos, ref name) => write!(f, "{}: unknown directive %{}", pos, name),
+            Error::NoRules => write!(f, "the grammar has no rules"),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Tok {
+    Ident(String),
+    Literal(String),
+    Epsilon,
+    Define,
+    Bar,
+    /// `(`, `[` or `{`.
+    Open(char),
+    /// `)`, `]` or `}`.
+    Close(char),
+    /// `*`, `+` or `?`.
+    Repeat(char),
+    /// `%` and the identifier after it.
+    Directive(String),
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_alphabetic() || c == '_'
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn tokenize(input: &str) -> Result<Vec<(Position, Tok)>, Error> {
+    let mut toks = Vec::new();
+    let mut chars = input.chars().peekable();
+    let mut pos = Position { line: 1, column: 1 };
+
+    fn advance<I: Iterator<Item=char>>(chars: &mut I, pos: &mut Position) -> Option<char> {
+        let c = chars.next();
+        match c {
+            Some('\n') => { pos.line += 1; pos.column = 1; },
+            Some(_) => pos.column += 1,
+            None => (),
+        }
+        c
+    }
+
+    while let Some(&c) = chars.peek() {
+        let start = pos;
+        if c.is_whitespace() {
+            advance(&mut chars, &mut pos);
+        } else if c == '#' {
+            while chars.peek().map_or(false, |&c| c != '\n') {
+                advance(&mut chars, &mut pos);
+            }
+        } else if c == 'ε' {
+            advance(&mut chars, &mut pos);
+            toks.push((start, Tok::Epsilon));
+        } else if is_ident_start(c) || c == '%' {
+            if c == '%' {
+                advance(&mut chars, &mut pos);
+            }
+            let mut name = String::new();
+            while let Some(&c) = chars.peek() {
+                if !is_ident_char(c) {
+                    break;
+                }
+                name.push(c);
+                advance(&mut chars, &mut pos);
+            }
+            toks.push((start, if c == '%' { Tok::Directive(name) } else { Tok::Ident(name) }));
+        } else if c == '<' || c == '\'' || c == '"' {
+            advance(&mut chars, &mut pos);
+            let close = if c == '<' { '>' } else { c };
+            let mut name = String::new();
+            loop {
+                let at = pos;
+                match advance(&mut chars, &mut pos) {
+                    Some(q) if q == close => break,
+                    Some('\\') => match advance(&mut chars, &mut pos) {
+                        Some('n') => name.push('\n'),
+                        Some('r') => name.push('\r'),
+                        Some('t') => name.push('\t'),
+                        Some(e @ '\\') | Some(e @ '\'') | Some(e @ '"') => name.push(e),
+                        Some('>') if close == '>' => name.push('>'),
+                        Some(e) => return Err(Error::InvalidEscape(at, e)),
+                        None => return Err(Error::Unterminated(start)),
+                    },
+                    Some('\n') | None => return Err(Error::Unterminated(start)),
+                    Some(c) => name.push(c),
+                }
+            }
+            if name.is_empty() {
+                return Err(Error::EmptyName(start));
+            }
+            toks.push((start, if c == '<' { Tok::Ident(name) } else { Tok::Literal(name) }));
+        } else if c == '|' {
+            advance(&mut chars, &mut pos);
+            toks.push((start, Tok::Bar));
+        } else if c == '(' || c == '[' || c == '{' {
+            advance(&mut chars, &mut pos);
+            toks.push((start, Tok::Open(c)));
+        } else if c == ')' || c == ']' || c == '}' {
+            advance(&mut chars, &mut pos);
+            toks.push((start, Tok::Close(c)));
+        } else if c == '*' || c == '+' || c == '?' {
+            advance(&mut chars, &mut pos);
+            toks.push((start, Tok::Repeat(c)));
+        } else if c == '-' {
+            advance(&mut chars, &mut pos);
+            if advance(&mut chars, &mut pos) != Some('>') {
+                return Err(Error::Expected(start, "`->`"));
+            }
+            toks.push((start, Tok::Define));
+        } else if c == ':' {
+            advance(&mut chars, &mut pos);
+            if advance(&mut chars, &mut pos) != Some(':') || advance(&mut chars, &mut pos) != Some('=') {
+                return Err(Error::Expected(start, "`::=`"));
+            }
+            toks.push((start, Tok::Define));
+        } else {
+            return Err(Error::UnexpectedChar(start, c));
+        }
+    }
+    Ok(toks)
+}
+
+struct Parser<'a> {
+    toks: &'a [(Position, Tok)],
+    i: usize,
+    end: Position,
+    cfg: cfg::Cfg<cfg::Mutable>,
+    symbols: HashMap<String, Symbol>,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a Tok> {
+        self.toks.get(self.i).map(|&(_, ref tok)| tok)
+    }
+
+    fn position(&self) -> Position {
+        self.toks.get(self.i).map_or(self.end, |&(pos, _)| pos)
+    }
+
+    // Whether the next rule starts here.
+    fn at_rule(&self) -> bool {
+        match (self.toks.get(self.i), self.toks.get(self.i + 1)) {
+            (Some(&(_, Tok::Ident(_))), Some(&(_, Tok::Define))) => true,
+            _ => false,
+        }
+    }
+
+    // The terminal named by a literal.
+    fn literal(&self, name: &str, pos: Position) -> Result<Symbol, Error> {
+        match self.symbols[name] {
+            sym if sym.is_terminal() => Ok(sym),
+            _ => Err(Error::NameClash(pos, name.to_string())),
+        }
+    }
+
+    fn alts(&mut self) -> Result<Expr, Error> {
+        let mut alts = vec![try!(self.seq())];
+        while let Some(&Tok::Bar) = self.peek() {
+            self.i += 1;
+            alts.push(try!(self.seq()));
+        }
+        Ok(if alts.len() == 1 { alts.pop().unwrap() } else { Expr::Alt(alts) })
+    }
+
+    fn seq(&mut self) -> Result<Expr, Error> {
+        let mut items = Vec::new();
+        while !self.at_rule() {
+            let pos = self.position();
+            let mut item = match self.peek() {
+                Some(&Tok::Ident(ref name)) => Expr::Symbol(self.symbols[name]),
+                Some(&Tok::Literal(ref name)) => Expr::Symbol(try!(self.literal(name, pos))),
+                Some(&Tok::Epsilon) => Expr::Symbol(EPSILON.into()),
+                Some(&Tok::Open(open)) => {
+                    self.i += 1;
+                    let inner = try!(self.alts());
+                    let close = match open { '(' => ')', '[' => ']', _ => '}' };
+                    if self.peek() != Some(&Tok::Close(close)) {
+                        return Err(Error::Expected(self.position(), match close { ')' => "`)`", ']' => "`]`", _ => "`}`" }));
+                    }
+                    let construct = match open { '(' => Construct::Group, '[' => Construct::Optional, _ => Construct::Star };
+                    Expr::Op(construct, pos, Box::new(inner))
+                },
+                Some(&Tok::Define) | Some(&Tok::Repeat(_)) => return Err(Error::Expected(pos, "a symbol")),
+                Some(&Tok::Bar) | Some(&Tok::Close(_)) | Some(&Tok::Directive(_)) | None => break,
+            };
+            self.i += 1;
+            while let Some(&Tok::Repeat(op)) = self.peek() {
+                let construct = match op { '*' => Construct::Star, '+' => Construct::Plus, _ => Construct::Optional };
+                item = Expr::Op(construct, self.position(), Box::new(item));
+                self.i += 1;
+            }
+            items.push(item);
+        }
+        Ok(if items.len() == 1 { items.pop().unwrap() } else { Expr::Seq(items) })
+    }
+}
+
+/// Parse a grammar in the format described in the module documentation.
+pub fn parse(input: &str) -> Result<cfg::Cfg<cfg::Mutable>, Error> {
+    let toks = try!(tokenize(input));
+    let end = toks.last().map_or(Position { line: 1, column: 1 }, |&(pos, _)| pos);
+    let mut p = Parser { toks: &toks, i: 0, end: end, cfg: cfg::Cfg::new(), symbols: HashMap::new() };
+
+    // Every identifier with a rule or listed by `%nonterminal` is a nonterminal, wherever it is used.
+    let mut declaring = false;
+    for (i, &(_, ref tok)) in toks.iter().enumerate() {
+        let defined = toks.get(i + 1).map_or(false, |&(_, ref next)| *next == Tok::Define);
+        match *tok {
+            Tok::Directive(ref name) => declaring = name == "nonterminal",
+            Tok::Ident(ref name) if defined || declaring => {
+                if !p.symbols.contains_key(name) {
+                    let nt = p.cfg.add_named_nonterminal(name);
+                    p.symbols.insert(name.clone(), nt);
+                }
+            },
+            _ => declaring = false,
+        }
+        if defined {
+            declaring = false;
+        }
+    }
+    // All other symbols are terminals, added up front so that the fresh nonterminals of EBNF
+    // operators are given names no terminal has.
+    for (i, &(_, ref tok)) in toks.iter().enumerate() {
+        let after_start = i > 0 && toks[i - 1].1 == Tok::Directive("start".to_string());
+        match *tok {
+            Tok::Ident(ref name) | Tok::Literal(ref name) if !after_start && !p.symbols.contains_key(name) => {
+                let t = p.cfg.add_named_terminal(name);
+                p.symbols.insert(name.clone(), t);
+            },
+            _ => (),
+        }
+    }
+
+    let mut first = true;
+    let mut start = None;
+    while p.i < toks.len() {
+        if let Tok::Directive(ref name) = toks[p.i].1 {
+            let pos = p.position();
+            p.i += 1;
+            if name == "start" {
+                match toks.get(p.i) {
+                    Some(&(_, Tok::Ident(ref name))) if !p.at_rule() => start = Some((p.position(), name.clone())),
+                    _ => return Err(Error::Expected(p.position(), "a nonterminal")),
+                }
+                p.i += 1;
+            } else if name == "nonterminal" {
+                while let Some(&(_, Tok::Ident(_))) = toks.get(p.i) {
+                    if p.at_rule() {
+                        break;
+                    }
+                    p.i += 1;
+                }
+            } else {
+                return Err(Error::UnknownDirective(pos, name.clone()));
+            }
+            continue;
+        }
+        if !p.at_rule() {
+            return Err(Error::Expected(p.position(), if first { "a rule" } else { "a symbol" }));
+        }
+        let lhs = match toks[p.i].1 { Tok::Ident(ref name) => p.symbols[name], _ => unreachable!() };
+        p.i += 2;
+        let e = try!(p.alts());
+        let rules = ebnf::add_rules(&mut p.cfg, lhs, &e);
+        if first {
+            p.cfg.set_start(rules[0]);
+            first = false;
+        }
+    }
+    if first {
+        return Err(Error::NoRules);
+    }
+    if let Some((pos, name)) = start {
+        let lhs = try!(p.symbols.get(&name).cloned().ok_or(Error::Expected(pos, "a nonterminal with rules")));
+        match p.cfg.rules().position(|&(l, _)| l == lhs.into()) {
+            Some(r) => p.cfg.set_start(cfg::Rule(r)),
+            None => return Err(Error::Expected(pos, "a nonterminal with rules")),
+        }
+    }
+    Ok(p.cfg)
+}
+
+fn is_ident(name: &str) -> bool {
+    // `ε` would be read back as ɛ.
+    name.chars().next().map_or(false, |c| is_ident_start(c) && c != 'ε') && name.chars().all(is_ident_char)
+}
+
+fn write_symbol<T>(c: &cfg::Cfg<T>, sym: PackedSymbol, out: &mut String) {
+    if sym == EPSILON && c.symbol_name(sym).is_none() {
+        out.push_str("ε");
+        return;
+    }
+    let name = c.display_symbol(sym).to_string();
+    if sym.is_nonterminal() && is_ident(&name) {
+        out.push_str(&name);
+        return;
+    }
+    let (open, close) = if sym.is_nonterminal() { ('<', '>') } else { ('\'', '\'') };
+    out.push(open);
+    for ch in name.chars() {
+        match ch {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            ch if ch == close => { out.push('\\'); out.push(ch); },
+            ch => out.push(ch),
+        }
+    }
+    out.push(close);
+}
+
+/// Write a grammar in the format read by `parse`, with consecutive rules of the same nonterminal
+/// joined by `|`. Terminals are always quoted.
 pub fn to_string<T>(c: &cfg::Cfg<T>) -> String {
-    let names = c.extra.get::<BNFName>().unwrap();
-
-    c.rules().map(|&(lhs, ref rhs)| {
-            let from = names.get(&lhs.into()).unwrap();
-            let to = rhs.iter()
-                .map(|rule| *names.get(&rule.into()).unwrap_or(&'ε'))
-                .collect::<String>();
-            format!("{} -> {}", from, to)
-    }).collect::<Vec<String>>().connect("\n")
+    let mut lines: Vec<String> = Vec::new();
+    let start = c.get_rule(cfg::Rule(c.start)).map(|(lhs, _)| lhs);
+    if start.is_some() && start != c.get_rule(cfg::Rule(0)).map(|(lhs, _)| lhs) {
+        let mut line = "%start ".to_string();
+        write_symbol(c, start.unwrap(), &mut line);
+        lines.push(line);
+    }
+    let mut has_rules = vec![false; c.num_nonterminals() as usize];
+    for &(lhs, _) in c.rules() {
+        has_rules[lhs.to_index()] = true;
+    }
+    let mut declared = Vec::new();
+    for &(_, ref rhs) in c.rules() {
+        for &sym in rhs {
+            if sym.is_nonterminal() && !has_rules[sym.to_index()] && !declared.contains(&sym) {
+                declared.push(sym);
+            }
+        }
+    }
+    if !declared.is_empty() {
+        let mut line = "%nonterminal".to_string();
+        for &sym in &declared {
+            line.push(' ');
+            write_symbol(c, sym, &mut line);
+        }
+        lines.push(line);
+    }
+    let mut last = None;
+    for &(lhs, ref rhs) in c.rules() {
+        let mut line = String::new();
+        if last == Some(lhs) {
+            line = lines.pop().unwrap();
+            line.push_str(" |");
+        } else {
+            write_symbol(c, lhs, &mut line);
+            line.push_str(" ->");
+        }
+        if rhs.is_empty() {
+            line.push_str(" ε");
+        }
+        for &sym in rhs {
+            line.push(' ');
+            write_symbol(c, sym, &mut line);
+        }
+        lines.push(line);
+        last = Some(lhs);
+    }
+    lines.connect("\n")
 }
 
 pub fn print_bnf<T>(c: &cfg::Cfg<T>) {
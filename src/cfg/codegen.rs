@@ -0,0 +1,127 @@
+//! Recursive-descent parser generation.
+//!
+//! Rather than building the LL(1) table at runtime, `generate_parser` writes out Rust source for a
+//! parser with one function per nonterminal, each of which `match`es on the lookahead over its row
+//! of the table. The generated module depends only on `pct::cfg` for `Rule`, `Symbol` and `Token`,
+//! and looks like:
+//!
+//! ```ignore
+//! pub trait Callbacks { fn rule(&mut self, rule: Rule); fn token(&mut self, token: &Token) { } }
+//! impl Callbacks for Vec<Rule> { ... }
+//! pub fn parse(s: Vec<&Token>) -> Vec<Rule> { ... }
+//! pub fn parse_with<C: Callbacks>(s: &[&Token], callbacks: &mut C) { ... }
+//! ```
+//!
+//! `parse` behaves exactly like `ll1::parse` on the table the module was generated from, including
+//! panicking with the same messages on parse errors, which name symbols by the grammar's symbol
+//! table. `parse_with` reports each rule as it is applied and each token as it is consumed, in the
+//! same order as the derivation.
+
+use std::io::{self, Write};
+use cfg::{Cfg, Frozen, Rule, EPSILON, Symbol, PackedSymbol};
+use cfg::ll1::{ParseTable, Table};
+
+/// Write the source of a recursive-descent parser for `cfg`, using its LL(1) table `tab`.
+pub fn generate_parser(cfg: &Cfg<Frozen>, tab: &Table, out: &mut Write) -> io::Result<()> {
+    try!(out.write_all(PRELUDE.as_bytes()));
+    let names = (0..tab.num_terminals())
+        .map(|t| format!("{:?}", tab.names.display(Symbol::Terminal(t)).to_string()))
+        .collect::<Vec<_>>();
+    try!(writeln!(out, "const TERMINAL_NAMES: &'static [&'static str] = &[{}];\n", names.join(", ")));
+    try!(writeln!(out, "/// Parse a string, returning the rules applied to derive the string."));
+    try!(writeln!(out, "pub fn parse(s: Vec<&Token>) -> Vec<Rule> {{"));
+    try!(writeln!(out, "    let mut derivation = Vec::new();"));
+    try!(writeln!(out, "    parse_with(&s, &mut derivation);"));
+    try!(writeln!(out, "    derivation"));
+    try!(writeln!(out, "}}\n"));
+    try!(writeln!(out, "/// Parse a string, reporting each rule and token to `callbacks` as it is recognized."));
+    try!(writeln!(out, "pub fn parse_with<C: Callbacks>(s: &[&Token], callbacks: &mut C) {{"));
+    try!(writeln!(out, "    let mut p = Parser {{ s: s, idx: 0, callbacks: callbacks }};"));
+    try!(writeln!(out, "    p.{}();", function_name(tab.start.into())));
+    try!(writeln!(out, "    p.expect(0);"));
+    try!(writeln!(out, "}}\n"));
+
+    try!(writeln!(out, "impl<'a, 'b, C: Callbacks> Parser<'a, 'b, C> {{"));
+    for nt in 0..cfg.num_nonterminals() as usize {
+        // The terminals predicting each rule, in order of rule.
+        let mut arms: Vec<(Rule, Vec<u32>)> = Vec::new();
+        for t in (0..tab.num_terminals()).filter(|&t| t as usize != EPSILON.to_index()) {
+            if let Some(rule) = tab.entry(Symbol::Nonterminal(nt as u32), Symbol::Terminal(t)) {
+                match arms.iter().position(|&(r, _)| r == rule) {
+                    Some(i) => arms[i].1.push(t),
+                    None => arms.push((rule, vec![t])),
+                }
+            }
+        }
+        arms.sort();
+
+        try!(writeln!(out, "    fn {}(&mut self) {{", function_name(Symbol::Nonterminal(nt as u32).into())));
+        try!(writeln!(out, "        match self.peek() {{"));
+        for (rule, terms) in arms {
+            let pattern = terms.iter().map(|t| t.to_string()).collect::<Vec<_>>().join(" | ");
+            try!(writeln!(out, "            // {}", cfg.display_rule(rule)));
+            try!(writeln!(out, "            {} => {{", pattern));
+            try!(writeln!(out, "                self.callbacks.rule(Rule({}));", rule.0));
+            for &sym in &tab.rules[rule.0].1 {
+                if sym == EPSILON {
+                    continue;
+                } else if sym.is_terminal() {
+                    try!(writeln!(out, "                self.expect({});", sym.to_index()));
+                } else {
+                    try!(writeln!(out, "                self.{}();", function_name(sym)));
+                }
+            }
+            try!(writeln!(out, "            }},"));
+        }
+        let name = format!("{:?}", cfg.display_symbol(Symbol::Nonterminal(nt as u32)).to_string());
+        try!(writeln!(out, "            t => panic!(\"Parse error! Found error entry in table for {{}} on {{}}.\", {}, TERMINAL_NAMES[t]),", name));
+        try!(writeln!(out, "        }}"));
+        try!(writeln!(out, "    }}\n"));
+    }
+    try!(writeln!(out, "}}"));
+    Ok(())
+}
+
+fn function_name(nonterminal: PackedSymbol) -> String {
+    format!("nonterminal_{}", nonterminal.to_index())
+}
+
+const PRELUDE: &'static str = r#"// Generated by pct. Do not edit.
+
+use pct::cfg::{Rule, Token};
+
+/// Receives the rules and tokens of a derivation as they are recognized.
+pub trait Callbacks {
+    fn rule(&mut self, rule: Rule);
+    fn token(&mut self, _token: &Token) { }
+}
+
+impl Callbacks for Vec<Rule> {
+    fn rule(&mut self, rule: Rule) {
+        self.push(rule);
+    }
+}
+
+struct Parser<'a, 'b: 'a, C: 'a> {
+    s: &'a [&'b Token],
+    idx: usize,
+    callbacks: &'a mut C,
+}
+
+impl<'a, 'b, C: Callbacks> Parser<'a, 'b, C> {
+    fn peek(&self) -> usize {
+        self.s.get(self.idx).map(|t| t.to_terminal().to_index()).unwrap_or(0)
+    }
+
+    fn expect(&mut self, t: usize) {
+        if self.peek() != t {
+            panic!("Parse error! Expected {}, found {}.", TERMINAL_NAMES[t], TERMINAL_NAMES[self.peek()]);
+        }
+        if let Some(token) = self.s.get(self.idx) {
+            self.callbacks.token(*token);
+        }
+        self.idx += 1;
+    }
+}
+
+"#;
@@ -0,0 +1,128 @@
+//! LL(1) table compression.
+//!
+//! A dense `ll1::Table` has a cell for every nonterminal and terminal, and most of them are errors.
+//! `compress` shrinks it in three steps:
+//!
+//! 1. Terminals whose columns are identical in every row are merged into one equivalence class.
+//! 2. Rows that are identical over the classes are stored once and shared.
+//! 3. The remaining rows are packed into a single comb vector by row displacement: each row is
+//!    given a base offset such that its non-error cells land on free slots, and every slot
+//!    records which row owns it.
+//!
+//! A `CompressedTable` implements `ll1::ParseTable`, so the parsers work on it unchanged.
+
+use cfg::{Rule, Symbol, PackedSymbol, SymbolTable};
+use cfg::ll1::{ParseTable, Table};
+
+pub struct CompressedTable {
+    pub start: Symbol,
+    pub rules: Vec<(PackedSymbol, Vec<PackedSymbol>)>,
+    pub names: SymbolTable,
+    /// The equivalence class of each terminal.
+    classes: Vec<usize>,
+    /// The shared row of each nonterminal.
+    rows: Vec<usize>,
+    /// The displacement of each shared row in `slots`.
+    base: Vec<usize>,
+    /// The packed cells: the shared row owning the slot, and its rule.
+    slots: Vec<Option<(usize, Rule)>>,
+}
+
+impl CompressedTable {
+    /// Number of equivalence classes of terminals.
+    pub fn num_classes(&self) -> usize {
+        self.classes.iter().max().map_or(0, |&c| c + 1)
+    }
+
+    /// Number of distinct rows.
+    pub fn num_rows(&self) -> usize {
+        self.base.len()
+    }
+
+    /// Length of the comb vector the rows are packed into.
+    pub fn packed_len(&self) -> usize {
+        self.slots.len()
+    }
+}
+
+impl ParseTable for CompressedTable {
+    fn start(&self) -> Symbol {
+        self.start
+    }
+
+    fn num_terminals(&self) -> u32 {
+        self.classes.len() as u32
+    }
+
+    fn num_nonterminals(&self) -> u32 {
+        self.rows.len() as u32
+    }
+
+    fn lookup(&self, nonterminal: usize, terminal: usize) -> Option<Rule> {
+        let row = self.rows[nonterminal];
+        match self.slots.get(self.base[row] + self.classes[terminal]) {
+            Some(&Some((owner, rule))) if owner == row => Some(rule),
+            _ => None,
+        }
+    }
+
+    fn rhs(&self, rule: Rule) -> &[PackedSymbol] {
+        &self.rules[rule.0].1
+    }
+
+    fn names(&self) -> &SymbolTable {
+        &self.names
+    }
+}
+
+/// Compress a dense table.
+pub fn compress(tab: &Table) -> CompressedTable {
+    let nonterms = tab.num_nonterminals() as usize;
+    let terms = tab.num_terminals() as usize;
+
+    // Merge identical columns.
+    let mut columns: Vec<Vec<Option<Rule>>> = Vec::new();
+    let mut classes = Vec::with_capacity(terms);
+    for t in 0..terms {
+        let column = (0..nonterms).map(|nt| tab.lookup(nt, t)).collect::<Vec<_>>();
+        let class = match columns.iter().position(|c| *c == column) {
+            Some(class) => class,
+            None => { columns.push(column); columns.len() - 1 },
+        };
+        classes.push(class);
+    }
+
+    // Share identical rows.
+    let mut shared: Vec<Vec<Option<Rule>>> = Vec::new();
+    let mut rows = Vec::with_capacity(nonterms);
+    for nt in 0..nonterms {
+        let row = columns.iter().map(|c| c[nt]).collect::<Vec<_>>();
+        let index = match shared.iter().position(|r| *r == row) {
+            Some(index) => index,
+            None => { shared.push(row); shared.len() - 1 },
+        };
+        rows.push(index);
+    }
+
+    // Pack the densest rows first, each at the first displacement where it fits.
+    let mut order = (0..shared.len()).collect::<Vec<_>>();
+    order.sort_by_key(|&r| (!0 - shared[r].iter().filter(|e| e.is_some()).count(), r));
+    let mut base = vec![0; shared.len()];
+    let mut slots: Vec<Option<(usize, Rule)>> = Vec::new();
+    for r in order {
+        let cells = shared[r].iter().enumerate().filter_map(|(c, e)| e.map(|rule| (c, rule))).collect::<Vec<_>>();
+        let mut offset = 0;
+        while cells.iter().any(|&(c, _)| slots.get(offset + c).map_or(false, |s| s.is_some())) {
+            offset += 1;
+        }
+        for &(c, rule) in &cells {
+            if slots.len() <= offset + c {
+                slots.resize(offset + c + 1, None);
+            }
+            slots[offset + c] = Some((r, rule));
+        }
+        base[r] = offset;
+    }
+
+    CompressedTable { start: tab.start, rules: tab.rules.clone(), names: tab.names.clone(), classes: classes, rows: rows, base: base, slots: slots }
+}
@@ -0,0 +1,829 @@
+//! Counterexamples explaining parse table conflicts.
+//!
+//! `ll1` explains each cell of the LL(1) table that predicts more than one rule. It derives a
+//! sentential form from the start symbol in which the cell's nonterminal is the leftmost
+//! nonterminal, so a parser reaches it, and which continues with the cell's terminal if a rule is
+//! predicted by FOLLOW. Then for each rule it derives why the rule is predicted there:
+//! either the rule derives a form starting with the terminal, by FIRST, or it derives ε and the
+//! terminal follows, by FOLLOW.
+//!
+//! `lr` explains each unresolved conflict of the SLR(1) table with two derivations from the start
+//! symbol, one for each action, with a dot where the conflict is. First it searches for a
+//! unifying counterexample: one sentential form with a derivation for each action, showing the
+//! grammar is ambiguous. Unlike Bison, which searches the product of the automaton with itself,
+//! it takes the pairs of leftmost derivations of one form that `ambiguity::search` enumerates,
+//! runs both through the automaton, and keeps a pair for the conflict where its two parses first
+//! take different actions. So a unifying counterexample is only found if a whole ambiguous
+//! sentential form is within the bounds of the search, and a pair whose parses diverge at an
+//! earlier conflict doesn't count for a later one. If there's none, it gives two forms instead,
+//! which agree up to the conflict. Either way they're shown aligned, in the style of Bison:
+//!
+//! ```text
+//! Example: E + E • + E
+//! Shift derivation:
+//!   E
+//!   ↳ 0: E + E
+//!            ↳ 0: E • + E
+//! Reduce derivation:
+//!   E
+//!   ↳ 0: E            + E
+//!        ↳ 0: E + E •
+//! ```
+
+use std::collections::{HashMap, VecDeque};
+use cfg::{Cfg, Frozen, Rule, Symbol, PackedSymbol, EPSILON, END_OF_INPUT};
+use cfg::{ambiguity, ll1, lr};
+use cfg::lr::{Automaton, Conflict, ConflictKind, Item};
+use cfg::util::{compute_first_of, compute_min_yield};
+
+/// Why a rule is predicted in a conflicting cell.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Continuation {
+    pub rule: Rule,
+    /// A leftmost derivation from the nonterminal, starting with `rule`.
+    pub derivation: Vec<Rule>,
+    /// The form `derivation` derives. It starts with the terminal, or is empty when the rule is
+    /// predicted because the terminal follows the nonterminal.
+    pub form: Vec<Symbol>,
+}
+
+/// A conflicting cell of an LL(1) table, explained.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Ll1Conflict {
+    pub nonterminal: Symbol,
+    pub terminal: Symbol,
+    /// A sentential form derived from the start symbol: a string of terminals, the nonterminal,
+    /// and the rest. If a rule is predicted by FOLLOW, the rest starts with the terminal, or is
+    /// empty if it's `END_OF_INPUT`.
+    pub context: Vec<Symbol>,
+    /// The index of the nonterminal in `context`.
+    pub position: usize,
+    /// The derivation of `context`. Each step replaces the symbol at an index of the previous
+    /// form by the right-hand side of a rule.
+    pub steps: Vec<(usize, Rule)>,
+    /// One for each rule in the cell.
+    pub continuations: Vec<Continuation>,
+}
+
+impl Ll1Conflict {
+    /// Describe the conflict, with every form of the derivations.
+    pub fn describe<T>(&self, cfg: &Cfg<T>) -> String {
+        let rules: Vec<String> = self.continuations.iter().map(|c| c.rule.0.to_string()).collect();
+        let mut out = format!("{} has rules {} for {}:\n", cfg.display_symbol(self.nonterminal),
+                              rules.join(", "), cfg.display_symbol(self.terminal));
+        let mut form: Vec<Symbol> = cfg.rules().nth(cfg.start).map(|&(start, _)| start.into()).into_iter().collect();
+        let mut forms = vec![show(cfg, &form)];
+        for &(at, rule) in &self.steps {
+            form.splice(at..at + 1, rhs(cfg, rule).into_iter().map(Symbol::from));
+            forms.push(show(cfg, &form));
+        }
+        out.push_str(&forms.join(" ⇒ "));
+        for c in &self.continuations {
+            let mut form = vec![self.nonterminal];
+            let mut forms = vec![show(cfg, &form)];
+            for &rule in &c.derivation {
+                let at = form.iter().position(|s| s.is_nonterminal()).unwrap();
+                form.splice(at..at + 1, rhs(cfg, rule).into_iter().map(Symbol::from));
+                forms.push(show(cfg, &form));
+            }
+            out.push_str(&format!("\nrule {}: {}", c.rule.0, forms.join(" ⇒ ")));
+            if c.form.is_empty() {
+                out.push_str(&format!(", followed by {}", cfg.display_symbol(self.terminal)));
+            }
+        }
+        out.push('\n');
+        out
+    }
+}
+
+fn show<T>(cfg: &Cfg<T>, form: &[Symbol]) -> String {
+    if form.is_empty() {
+        return "ε".to_string();
+    }
+    form.iter().map(|&s| cfg.display_symbol(s).to_string()).collect::<Vec<_>>().join(" ")
+}
+
+/// The right-hand side of `rule`, without `EPSILON`.
+fn rhs<T>(cfg: &Cfg<T>, rule: Rule) -> Vec<PackedSymbol> {
+    cfg.get_rule(rule).unwrap().1.iter().cloned().filter(|&s| s != EPSILON).collect()
+}
+
+/// Derives the forms of a counterexample for a lookahead terminal.
+struct Deriver<'a, T: 'a> {
+    cfg: &'a Cfg<T>,
+    terminal: PackedSymbol,
+    /// The shortest string each nonterminal derives, from `compute_min_yield`.
+    min: Vec<Option<(usize, Rule)>>,
+    /// For each nonterminal deriving a form that starts with `terminal`, the number of steps to
+    /// one, the rule to start with, and the index of the symbol in it to derive the terminal from.
+    /// The symbols before it derive ε.
+    starts: Vec<Option<(usize, Rule, usize)>>,
+}
+
+impl<'a, T> Deriver<'a, T> {
+    fn new(cfg: &'a Cfg<T>, terminal: PackedSymbol) -> Deriver<'a, T> {
+        let min = compute_min_yield(cfg);
+        let mut starts: Vec<Option<(usize, Rule, usize)>> = vec![None; cfg.num_nonterminals() as usize];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (i, &(lhs, ref rhs)) in cfg.rules().enumerate() {
+                let mut found = None;
+                for (k, &sym) in rhs.iter().enumerate().filter(|&(_, &s)| s != EPSILON) {
+                    if sym == terminal {
+                        found = Some((1, k));
+                    } else if sym.is_nonterminal() {
+                        found = starts[sym.to_index()].map(|(steps, _, _)| (steps + 1, k));
+                    }
+                    if found.is_some() || sym.is_terminal() || min[sym.to_index()].map(|(l, _)| l) != Some(0) {
+                        break;
+                    }
+                }
+                if let Some((steps, k)) = found {
+                    if starts[lhs.to_index()].map_or(true, |(s, _, _)| steps < s) {
+                        starts[lhs.to_index()] = Some((steps, Rule(i), k));
+                        changed = true;
+                    }
+                }
+            }
+        }
+        Deriver { cfg: cfg, terminal: terminal, min: min, starts: starts }
+    }
+
+    fn nullable(&self, sym: PackedSymbol) -> bool {
+        sym == EPSILON || (sym.is_nonterminal() && self.min[sym.to_index()].map(|(l, _)| l) == Some(0))
+    }
+
+    fn productive(&self, sym: PackedSymbol) -> bool {
+        sym.is_terminal() || self.min[sym.to_index()].is_some()
+    }
+
+    fn expand(&self, form: &mut Vec<PackedSymbol>, steps: &mut Vec<(usize, Rule)>, at: usize, rule: Rule) -> usize {
+        let rhs = rhs(self.cfg, rule);
+        let len = rhs.len();
+        form.splice(at..at + 1, rhs);
+        steps.push((at, rule));
+        len
+    }
+
+    /// Derive a shortest string from the symbol at `at`, returning its length.
+    fn derive_shortest(&self, form: &mut Vec<PackedSymbol>, steps: &mut Vec<(usize, Rule)>, at: usize) -> usize {
+        if form[at].is_terminal() {
+            return 1;
+        }
+        let (_, rule) = self.min[form[at].to_index()].unwrap();
+        let mut end = at;
+        for _ in 0..self.expand(form, steps, at, rule) {
+            end += self.derive_shortest(form, steps, end);
+        }
+        end - at
+    }
+
+    /// Derive ε from the nullable symbol at `at`.
+    fn derive_empty(&self, form: &mut Vec<PackedSymbol>, steps: &mut Vec<(usize, Rule)>, at: usize) {
+        let (_, rule) = self.min[form[at].to_index()].unwrap();
+        for _ in 0..self.expand(form, steps, at, rule) {
+            self.derive_empty(form, steps, at);
+        }
+    }
+
+    /// Derive the symbols from `at` on until the one at `at` is the terminal, deriving ε from
+    /// those that can't start with it.
+    fn derive_terminal(&self, form: &mut Vec<PackedSymbol>, steps: &mut Vec<(usize, Rule)>, at: usize) {
+        while form[at] != self.terminal {
+            let sym = form[at];
+            if sym.is_terminal() {
+                panic!("Derived {:?} where {:?} should start.", sym, self.terminal);
+            }
+            match self.starts[sym.to_index()] {
+                Some((_, rule, k)) => {
+                    self.expand(form, steps, at, rule);
+                    for _ in 0..k {
+                        self.derive_empty(form, steps, at);
+                    }
+                },
+                None => self.derive_empty(form, steps, at),
+            }
+        }
+    }
+
+    /// Derive a leftmost sentential form from the start symbol whose leftmost nonterminal is
+    /// `nonterminal`, followed by the terminal if `follows`.
+    fn context(&self, nonterminal: PackedSymbol, follows: bool) -> Option<(Vec<PackedSymbol>, usize, Vec<(usize, Rule)>)> {
+        let start = match self.cfg.rules().nth(self.cfg.start) {
+            Some(&(start, _)) => start,
+            None => return None,
+        };
+        // States are a nonterminal, and whether the symbols after it can start with the
+        // terminal. A path of them is a chain of rules deriving each from the one before.
+        let n = self.cfg.num_nonterminals() as usize;
+        let state = |sym: PackedSymbol, followed: bool| sym.to_index() * 2 + followed as usize;
+        let mut parent: Vec<Option<Option<(usize, Rule, usize)>>> = vec![None; 2 * n];
+        let first = state(start, self.terminal == END_OF_INPUT);
+        parent[first] = Some(None);
+        let mut queue = VecDeque::new();
+        queue.push_back((start, self.terminal == END_OF_INPUT));
+        let mut goal = None;
+        while let Some((lhs, followed)) = queue.pop_front() {
+            if lhs == nonterminal && (followed || !follows) {
+                goal = Some((state(lhs, followed), followed));
+                break;
+            }
+            for (i, &(_, ref rhs)) in self.cfg.rules().enumerate().filter(|&(_, &(l, _))| l == lhs) {
+                for (k, &sym) in rhs.iter().enumerate().filter(|&(_, s)| s.is_nonterminal()) {
+                    if !rhs[..k].iter().all(|&s| self.productive(s)) {
+                        break;
+                    }
+                    let rest = &rhs[k + 1..];
+                    let next = compute_first_of(self.cfg, rest).contains(&self.terminal)
+                        || (followed && rest.iter().all(|&s| self.nullable(s)));
+                    if parent[state(sym, next)].is_none() {
+                        parent[state(sym, next)] = Some(Some((state(lhs, followed), Rule(i), k)));
+                        queue.push_back((sym, next));
+                    }
+                }
+            }
+        }
+
+        let (mut at_state, followed) = match goal {
+            Some(goal) => goal,
+            None => return None,
+        };
+        let mut chain = Vec::new();
+        loop {
+            match parent[at_state] {
+                None => unreachable!(),
+                Some(None) => break,
+                Some(Some((prev, rule, k))) => {
+                    chain.push((rule, k));
+                    at_state = prev;
+                },
+            }
+        }
+        chain.reverse();
+
+        let mut form = vec![start];
+        let mut steps = Vec::new();
+        let mut at = 0;
+        for (rule, k) in chain {
+            self.expand(&mut form, &mut steps, at, rule);
+            for _ in 0..k {
+                at += self.derive_shortest(&mut form, &mut steps, at);
+            }
+        }
+        if followed && self.terminal == END_OF_INPUT {
+            while form.len() > at + 1 {
+                self.derive_empty(&mut form, &mut steps, at + 1);
+            }
+        } else if followed {
+            self.derive_terminal(&mut form, &mut steps, at + 1);
+        }
+        Some((form, at, steps))
+    }
+
+    /// Whether `seq` derives a form starting with the terminal.
+    fn can_start(&self, seq: &[PackedSymbol]) -> bool {
+        for &sym in seq {
+            if sym == self.terminal || (sym.is_nonterminal() && self.starts[sym.to_index()].is_some()) {
+                return true;
+            }
+            if !self.nullable(sym) {
+                return false;
+            }
+        }
+        false
+    }
+
+    /// The tree of a derivation from `sym` of a form starting with the terminal, with the symbols
+    /// after it left unexpanded.
+    fn starting_tree(&self, sym: PackedSymbol) -> Derivation {
+        if sym == self.terminal {
+            return Derivation::Symbol(sym.into());
+        }
+        let (_, rule, k) = self.starts[sym.to_index()].unwrap();
+        let children = rhs(self.cfg, rule).into_iter().enumerate().map(|(i, s)| {
+            if i < k {
+                shortest_tree(self.cfg, &self.min, s)
+            } else if i == k {
+                self.starting_tree(s)
+            } else {
+                Derivation::Symbol(s.into())
+            }
+        }).collect();
+        Derivation::Node(sym.into(), rule, children)
+    }
+
+    /// Derive from `rule` why it's predicted for the terminal.
+    fn continuation(&self, rule: Rule) -> Continuation {
+        let (lhs, rhs) = self.cfg.get_rule(rule).unwrap();
+        let mut form = vec![lhs];
+        let mut steps = Vec::new();
+        self.expand(&mut form, &mut steps, 0, rule);
+        if compute_first_of(self.cfg, rhs).contains(&self.terminal) {
+            self.derive_terminal(&mut form, &mut steps, 0);
+        } else {
+            while !form.is_empty() {
+                self.derive_empty(&mut form, &mut steps, 0);
+            }
+        }
+        Continuation {
+            rule: rule,
+            derivation: steps.into_iter().map(|(_, r)| r).collect(),
+            form: form.into_iter().map(Symbol::from).collect(),
+        }
+    }
+}
+
+/// Explain every conflicting cell of the LL(1) table of `cfg`, by nonterminal and then terminal.
+///
+/// Conflicts that can't arise from the start symbol, such as those of nonterminals it doesn't
+/// derive, are left out.
+pub fn ll1(cfg: &mut Cfg<Frozen>) -> Vec<Ll1Conflict> {
+    let cells = ll1::compute_cells(cfg);
+    let mut conflicts = Vec::new();
+    for (n, row) in cells.iter().enumerate() {
+        for (t, cell) in row.iter().enumerate().filter(|&(t, cell)| t != EPSILON.to_index() && cell.len() > 1) {
+            let nonterminal: PackedSymbol = Symbol::Nonterminal(n as u32).into();
+            let terminal: PackedSymbol = Symbol::Terminal(t as u32).into();
+            let deriver = Deriver::new(cfg, terminal);
+            let follows = cell.iter().any(|&r| !compute_first_of(cfg, cfg.get_rule(r).unwrap().1).contains(&terminal));
+            if let Some((context, position, steps)) = deriver.context(nonterminal, follows) {
+                conflicts.push(Ll1Conflict {
+                    nonterminal: nonterminal.into(),
+                    terminal: terminal.into(),
+                    context: context.into_iter().map(Symbol::from).collect(),
+                    position: position,
+                    steps: steps,
+                    continuations: cell.iter().map(|&r| deriver.continuation(r)).collect(),
+                });
+            }
+        }
+    }
+    conflicts
+}
+
+/// A derivation in an LR counterexample. Its leaves can be nonterminals left unexpanded, and the
+/// dot marks where the conflict is.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Derivation {
+    Symbol(Symbol),
+    Dot,
+    /// A nonterminal expanded by a rule.
+    Node(Symbol, Rule, Vec<Derivation>),
+}
+
+impl Derivation {
+    /// The sentential form derived, without the dot.
+    pub fn form(&self) -> Vec<Symbol> {
+        let mut form = Vec::new();
+        self.collect(&mut form, &mut |_| ());
+        form
+    }
+
+    fn collect<F: FnMut(usize)>(&self, form: &mut Vec<Symbol>, dot: &mut F) {
+        match *self {
+            Derivation::Symbol(sym) => form.push(sym),
+            Derivation::Dot => dot(form.len()),
+            Derivation::Node(_, _, ref children) => for child in children {
+                child.collect(form, dot);
+            },
+        }
+    }
+
+    /// The sentential form derived, with the dot.
+    fn example<T>(&self, cfg: &Cfg<T>) -> String {
+        let mut form = Vec::new();
+        let mut at = None;
+        self.collect(&mut form, &mut |i| at = Some(i));
+        let mut words: Vec<String> = form.iter().map(|&s| cfg.display_symbol(s).to_string()).collect();
+        if let Some(at) = at {
+            words.insert(at, "•".to_string());
+        }
+        words.join(" ")
+    }
+
+    /// Draw the derivation as lines of text, with each node's children in a row below it, and
+    /// return the lines and their width.
+    fn draw<T>(&self, cfg: &Cfg<T>) -> (Vec<String>, usize) {
+        let (sym, rule, children) = match *self {
+            Derivation::Symbol(sym) => {
+                let name = cfg.display_symbol(sym).to_string();
+                let width = name.chars().count();
+                return (vec![name], width);
+            },
+            Derivation::Dot => return (vec!["•".to_string()], 1),
+            Derivation::Node(sym, rule, ref children) => (sym, rule, children),
+        };
+        let blocks: Vec<(Vec<String>, usize)> = children.iter().map(|c| c.draw(cfg)).collect();
+        let height = blocks.iter().map(|&(ref lines, _)| lines.len()).max().unwrap_or(1);
+        let mut rows = vec![String::new(); height];
+        if blocks.is_empty() {
+            rows[0].push_str("ε");
+        }
+        for (i, &(ref lines, width)) in blocks.iter().enumerate() {
+            for (r, row) in rows.iter_mut().enumerate() {
+                if i > 0 {
+                    row.push(' ');
+                }
+                let line = lines.get(r).map_or("", |l| &l[..]);
+                row.push_str(line);
+                row.push_str(&" ".repeat(width - line.chars().count()));
+            }
+        }
+        let label = format!("↳ {}: ", rule.0);
+        let indent = " ".repeat(label.chars().count());
+        let mut lines = vec![cfg.display_symbol(sym).to_string()];
+        for (r, row) in rows.into_iter().enumerate() {
+            lines.push(format!("{}{}", if r == 0 { &label } else { &indent }, row));
+        }
+        let width = lines.iter().map(|l| l.chars().count()).max().unwrap();
+        (lines, width)
+    }
+}
+
+/// An unresolved conflict of an SLR(1) table, explained.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct LrConflict {
+    pub conflict: Conflict,
+    /// Whether `first` and `second` derive the same form, which shows the grammar is ambiguous.
+    pub unifying: bool,
+    /// A derivation from the start symbol that shifts at the dot, or reduces by the first rule of
+    /// a reduce/reduce conflict, or None if no derivation reaching the conflict was found.
+    pub first: Option<Derivation>,
+    /// A derivation from the start symbol that reduces by the other rule at the dot, or None if
+    /// none was found.
+    pub second: Option<Derivation>,
+}
+
+impl LrConflict {
+    /// Describe the conflict, with its derivations drawn aligned.
+    pub fn describe<T>(&self, cfg: &Cfg<T>) -> String {
+        let (first, second) = match self.conflict.kind {
+            ConflictKind::ShiftReduce { .. } => ("Shift derivation".to_string(), "Reduce derivation".to_string()),
+            ConflictKind::ReduceReduce(a, b) =>
+                (format!("Reduce by rule {} derivation", a.0), format!("Reduce by rule {} derivation", b.0)),
+        };
+        let mut out = self.conflict.describe(cfg.names());
+        out.push('\n');
+        if let (true, Some(ref derivation)) = (self.unifying, self.first.as_ref()) {
+            out.push_str(&format!("Example: {}\n", derivation.example(cfg)));
+        }
+        for &(ref label, derivation) in &[(first, &self.first), (second, &self.second)] {
+            let derivation = match *derivation {
+                Some(ref derivation) => derivation,
+                None => {
+                    out.push_str(&format!("{}: none found\n", label));
+                    continue;
+                },
+            };
+            if !self.unifying {
+                out.push_str(&format!("Example: {}\n", derivation.example(cfg)));
+            }
+            out.push_str(&format!("{}:\n", label));
+            for line in derivation.draw(cfg).0 {
+                out.push_str(&format!("  {}\n", line.trim_end()));
+            }
+        }
+        out
+    }
+}
+
+/// The tree of a shortest derivation from `sym`, by the rules from `compute_min_yield`.
+fn shortest_tree<T>(cfg: &Cfg<T>, min: &[Option<(usize, Rule)>], sym: PackedSymbol) -> Derivation {
+    if sym.is_terminal() {
+        return Derivation::Symbol(sym.into());
+    }
+    let (_, rule) = min[sym.to_index()].unwrap();
+    Derivation::Node(sym.into(), rule, rhs(cfg, rule).into_iter().map(|s| shortest_tree(cfg, min, s)).collect())
+}
+
+/// The tree of the first steps of a leftmost derivation, up to where it reaches `form`.
+fn partial_tree<T>(cfg: &Cfg<T>, form: &[PackedSymbol], derivation: &[Rule]) -> Derivation {
+    fn build<T, I: Iterator<Item=Rule>>(cfg: &Cfg<T>, sym: PackedSymbol, rules: &mut I) -> Derivation {
+        if sym.is_terminal() {
+            return Derivation::Symbol(sym.into());
+        }
+        match rules.next() {
+            Some(rule) => {
+                let mut children = Vec::new();
+                for s in rhs(cfg, rule) {
+                    children.push(build(cfg, s, rules));
+                }
+                Derivation::Node(sym.into(), rule, children)
+            },
+            None => Derivation::Symbol(sym.into()),
+        }
+    }
+    let start = cfg.get_rule(derivation[0]).unwrap().0;
+    let mut current = vec![start];
+    let mut steps = 0;
+    while &current[..] != form {
+        let at = current.iter().position(|s| s.is_nonterminal()).unwrap();
+        current.splice(at..at + 1, rhs(cfg, derivation[steps]));
+        steps += 1;
+    }
+    build(cfg, start, &mut derivation[..steps].iter().cloned())
+}
+
+/// An action of an LR parser.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum Action {
+    Shift(PackedSymbol),
+    Reduce(Rule),
+}
+
+/// Where to put the dot in a tree for an action: before the node at a path of child indices, or
+/// if true, at the end of its children.
+type Place = (Vec<usize>, bool);
+
+/// The actions of an LR parser parsing `tree`, with the nonterminals at its leaves derived by
+/// `shortest_tree`. The actions deriving a leaf are placed before it.
+fn actions<T>(cfg: &Cfg<T>, min: &[Option<(usize, Rule)>], tree: &Derivation, path: &mut Vec<usize>,
+              leaf: Option<&Place>, out: &mut Vec<(Action, Place)>) {
+    let place = |path: &Vec<usize>, end: bool| leaf.cloned().unwrap_or_else(|| (path.clone(), end));
+    match *tree {
+        Derivation::Symbol(sym) if sym.is_terminal() => out.push((Action::Shift(sym.into()), place(path, false))),
+        Derivation::Symbol(sym) => {
+            let here = place(path, false);
+            actions(cfg, min, &shortest_tree(cfg, min, sym.into()), path, Some(&here), out);
+        },
+        Derivation::Dot => (),
+        Derivation::Node(_, rule, ref children) => {
+            for (i, child) in children.iter().enumerate() {
+                path.push(i);
+                actions(cfg, min, child, path, leaf, out);
+                path.pop();
+            }
+            out.push((Action::Reduce(rule), place(path, true)));
+        },
+    }
+}
+
+fn goto(automaton: &Automaton, state: usize, sym: PackedSymbol) -> usize {
+    automaton.states[state].gotos.iter().find(|&&(s, _)| s == sym).map(|&(_, target)| target)
+        .expect("A derivation doesn't match the automaton.")
+}
+
+/// Where LR parses of two trees of the same form first take different actions: the state, the
+/// lookahead, and each tree's action.
+fn divergence<T>(cfg: &Cfg<T>, min: &[Option<(usize, Rule)>], automaton: &Automaton, first: &Derivation,
+                 second: &Derivation) -> Option<(usize, PackedSymbol, (Action, Place), (Action, Place))> {
+    let (mut a, mut b) = (Vec::new(), Vec::new());
+    actions(cfg, min, first, &mut Vec::new(), None, &mut a);
+    actions(cfg, min, second, &mut Vec::new(), None, &mut b);
+    let i = match a.iter().zip(&b).position(|(x, y)| x.0 != y.0) {
+        Some(i) => i,
+        None => return None,
+    };
+    let mut stack = vec![0];
+    for &(action, _) in &a[..i] {
+        let sym = match action {
+            Action::Shift(t) => t,
+            Action::Reduce(rule) => {
+                let len = stack.len() - automaton.rules[rule.0].1.len();
+                stack.truncate(len);
+                automaton.rules[rule.0].0
+            },
+        };
+        let next = goto(automaton, *stack.last().unwrap(), sym);
+        stack.push(next);
+    }
+    let lookahead = a[i..].iter().filter_map(|&(action, _)| match action {
+        Action::Shift(t) => Some(t),
+        Action::Reduce(_) => None,
+    }).next().unwrap_or(END_OF_INPUT);
+    Some((*stack.last().unwrap(), lookahead, a.swap_remove(i), b.swap_remove(i)))
+}
+
+/// Whether the actions `a` and `b` are those of `kind`, and if so, whether they're in the other order.
+fn matches(kind: ConflictKind, a: Action, b: Action) -> Option<bool> {
+    match (kind, a, b) {
+        (ConflictKind::ShiftReduce { reduce, .. }, Action::Shift(_), Action::Reduce(r)) if r == reduce => Some(false),
+        (ConflictKind::ShiftReduce { reduce, .. }, Action::Reduce(r), Action::Shift(_)) if r == reduce => Some(true),
+        (ConflictKind::ReduceReduce(x, y), Action::Reduce(r), Action::Reduce(s)) if (r, s) == (x, y) => Some(false),
+        (ConflictKind::ReduceReduce(x, y), Action::Reduce(r), Action::Reduce(s)) if (r, s) == (y, x) => Some(true),
+        _ => None,
+    }
+}
+
+/// A copy of `tree` with the dot at `place`.
+fn with_dot(tree: &Derivation, place: &Place) -> Derivation {
+    let mut tree = tree.clone();
+    {
+        let (ref path, end) = *place;
+        let (parent, index) = if end { (&path[..], None) } else { (&path[..path.len() - 1], path.last().cloned()) };
+        let mut node = &mut tree;
+        for &i in parent {
+            node = match *node {
+                Derivation::Node(_, _, ref mut children) => &mut children[i],
+                _ => unreachable!(),
+            };
+        }
+        if let Derivation::Node(_, _, ref mut children) = *node {
+            match index {
+                Some(i) => children.insert(i, Derivation::Dot),
+                None => children.push(Derivation::Dot),
+            }
+        }
+    }
+    tree
+}
+
+/// The nodes of `tree` without the dot, with the span of the form each derives.
+fn spans<'a>(tree: &'a Derivation, at: &mut usize, out: &mut Vec<(usize, usize, &'a Derivation)>) -> bool {
+    match *tree {
+        Derivation::Symbol(_) => { *at += 1; false },
+        Derivation::Dot => true,
+        Derivation::Node(_, _, ref children) => {
+            let start = *at;
+            let mut dot = false;
+            for child in children {
+                dot |= spans(child, at, out);
+            }
+            if !dot {
+                out.push((start, *at, tree));
+            }
+            dot
+        },
+    }
+}
+
+/// A copy of `tree` with the nodes in `shared` left unexpanded.
+fn collapse(tree: &Derivation, at: &mut usize, shared: &[(usize, usize, &Derivation)]) -> Derivation {
+    match *tree {
+        Derivation::Symbol(_) => { *at += 1; tree.clone() },
+        Derivation::Dot => Derivation::Dot,
+        Derivation::Node(sym, rule, ref children) => {
+            let mut end = *at;
+            let mut mine = Vec::new();
+            spans(tree, &mut end, &mut mine);
+            if mine.last().map_or(false, |&(_, _, t)| t == tree) && shared.iter().any(|&(s, e, t)| (s, e) == (*at, end) && t == tree) {
+                *at = end;
+                return Derivation::Symbol(sym);
+            }
+            Derivation::Node(sym, rule, children.iter().map(|c| collapse(c, at, shared)).collect())
+        },
+    }
+}
+
+/// Derive a form from the start symbol along a shortest path of items through the automaton, to
+/// `state` with the dot before the terminal: at the end of `reduce`, or if None, before the
+/// terminal in an item shifting it. With `follow`, the terminal must follow a reduction.
+///
+/// The symbols before the dot are left unexpanded, and those after it only as far as it takes for
+/// the terminal to come next.
+fn reaching<T>(deriver: &Deriver<T>, automaton: &Automaton, state: usize, reduce: Option<Rule>, follow: bool) -> Option<Derivation> {
+    let aug = automaton.augmented_rule();
+    let terminal = deriver.terminal;
+    let is_goal = |s: usize, item: Item, followed: bool| s == state && match reduce {
+        Some(rule) => item.rule == rule && automaton.next_symbol(item).is_none() && (followed || !follow),
+        None => automaton.next_symbol(item) == Some(terminal),
+    };
+
+    // Nodes are an item in a state, and whether the terminal can follow its rule. An edge either
+    // shifts the symbol after the dot, or starts a rule for it.
+    let start = (0, Item { rule: aug, dot: 0 }, terminal == END_OF_INPUT);
+    let mut parent = HashMap::new();
+    parent.insert(start, None);
+    let mut queue = VecDeque::new();
+    queue.push_back(start);
+    let mut goal = None;
+    while let Some(node) = queue.pop_front() {
+        let (s, item, followed) = node;
+        if is_goal(s, item, followed) {
+            goal = Some(node);
+            break;
+        }
+        let sym = match automaton.next_symbol(item) {
+            Some(sym) => sym,
+            None => continue,
+        };
+        let mut next = vec![(goto(automaton, s, sym), Item { rule: item.rule, dot: item.dot + 1 }, followed)];
+        if sym.is_nonterminal() {
+            let rest = &automaton.rules[item.rule.0].1[item.dot + 1..];
+            let follows = deriver.can_start(rest) || (followed && rest.iter().all(|&r| deriver.nullable(r)));
+            for r in (0..automaton.rules.len()).filter(|&r| automaton.rules[r].0 == sym) {
+                next.push((s, Item { rule: Rule(r), dot: 0 }, follows));
+            }
+        }
+        for n in next {
+            if !parent.contains_key(&n) {
+                parent.insert(n, Some(node));
+                queue.push_back(n);
+            }
+        }
+    }
+
+    let mut path = vec![match goal {
+        Some(goal) => goal,
+        None => return None,
+    }];
+    while let Some(&Some(prev)) = parent.get(path.last().unwrap()) {
+        path.push(prev);
+    }
+    path.reverse();
+
+    // Each rule started is a frame holding the symbols shifted in it.
+    let mut frames: Vec<(Rule, Vec<Derivation>)> = vec![(aug, Vec::new())];
+    for pair in path.windows(2) {
+        let ((_, prev, _), (_, item, _)) = (pair[0], pair[1]);
+        if item.dot == 0 {
+            frames.push((item.rule, Vec::new()));
+        } else {
+            let sym = automaton.next_symbol(prev).unwrap();
+            frames.last_mut().unwrap().1.push(Derivation::Symbol(sym.into()));
+        }
+    }
+    frames.last_mut().unwrap().1.push(Derivation::Dot);
+    let mut need = reduce.is_some() && follow;
+    let mut inner = None;
+    while let Some((rule, mut children)) = frames.pop() {
+        children.extend(inner.take());
+        let done = children.iter().filter(|&c| *c != Derivation::Dot).count();
+        let (lhs, ref rhs) = automaton.rules[rule.0];
+        for &sym in &rhs[done..] {
+            children.push(if !need {
+                Derivation::Symbol(sym.into())
+            } else if terminal != END_OF_INPUT && deriver.can_start(&[sym]) {
+                need = false;
+                deriver.starting_tree(sym)
+            } else {
+                shortest_tree(deriver.cfg, &deriver.min, sym)
+            });
+        }
+        inner = Some(Derivation::Node(lhs.into(), rule, children));
+    }
+    match inner {
+        Some(Derivation::Node(_, _, children)) => children.into_iter().find(|c| *c != Derivation::Dot),
+        _ => None,
+    }
+}
+
+/// Explain every unresolved conflict of the SLR(1) table of `cfg`, in the order of
+/// `Table::conflicts`. The search for unifying counterexamples is bounded by `max_length` and
+/// `max_steps` as in `ambiguity::find`.
+///
+/// If the terminal of a conflict can't follow the reduction in any derivation reaching its state,
+/// which SLR(1) lookahead doesn't tell apart, the reduce derivation leaves what follows the dot
+/// unexpanded. Conflicts with the accepting action, which only grammars deriving the start
+/// symbol from itself have, are left out.
+pub fn lr(cfg: &mut Cfg<Frozen>, max_length: usize, max_steps: usize) -> Vec<LrConflict> {
+    let table = lr::generate_table(cfg);
+    let cfg = &*cfg;
+    let automaton = lr::compute_lr0(cfg);
+    let aug = automaton.augmented_rule();
+    let conflicts: Vec<Conflict> = table.conflicts.iter().cloned().filter(|c| match c.kind {
+        ConflictKind::ReduceReduce(a, _) => a != aug,
+        ConflictKind::ShiftReduce { .. } => true,
+    }).collect();
+    if conflicts.is_empty() {
+        return Vec::new();
+    }
+
+    let min = compute_min_yield(cfg);
+    let mut unifying: Vec<Option<(Derivation, Derivation)>> = vec![None; conflicts.len()];
+    let mut left = conflicts.len();
+    ambiguity::search(cfg, max_length, max_steps, |found| {
+        let form: Vec<PackedSymbol> = found.form.iter().map(|&s| s.into()).collect();
+        let first = partial_tree(cfg, &form, &found.first);
+        let second = partial_tree(cfg, &form, &found.second);
+        if let Some((state, lookahead, a, b)) = divergence(cfg, &min, &automaton, &first, &second) {
+            for (i, conflict) in conflicts.iter().enumerate() {
+                if unifying[i].is_some() || conflict.state != state || PackedSymbol::from(conflict.terminal) != lookahead {
+                    continue;
+                }
+                if let Some(swapped) = matches(conflict.kind, a.0, b.0) {
+                    // Leave what both derive the same way unexpanded.
+                    let (x, y) = (with_dot(&first, &a.1), with_dot(&second, &b.1));
+                    let (mut xs, mut ys) = (Vec::new(), Vec::new());
+                    spans(&x, &mut 0, &mut xs);
+                    spans(&y, &mut 0, &mut ys);
+                    let (x, y) = (collapse(&x, &mut 0, &ys), collapse(&y, &mut 0, &xs));
+                    unifying[i] = Some(if swapped { (y, x) } else { (x, y) });
+                    left -= 1;
+                    break;
+                }
+            }
+        }
+        left == 0
+    });
+
+    let mut explained = Vec::new();
+    for (conflict, found) in conflicts.into_iter().zip(unifying) {
+        let (first, second, unifying) = match found {
+            Some((first, second)) => (Some(first), Some(second), true),
+            None => {
+                let deriver = Deriver::new(cfg, conflict.terminal.into());
+                let (a, b) = match conflict.kind {
+                    ConflictKind::ShiftReduce { reduce, .. } => (None, Some(reduce)),
+                    ConflictKind::ReduceReduce(a, b) => (Some(a), Some(b)),
+                };
+                let example = |reduce| reaching(&deriver, &automaton, conflict.state, reduce, true)
+                    .or_else(|| reaching(&deriver, &automaton, conflict.state, reduce, false));
+                (example(a), example(b), false)
+            },
+        };
+        explained.push(LrConflict { conflict: conflict, unifying: unifying, first: first, second: second });
+    }
+    explained
+}
@@ -0,0 +1,215 @@
+//! Graphviz DOT output for debugging grammars.
+//!
+//! - `dependencies` draws the nonterminal dependency graph, with an edge from each left-hand side
+//!   to every nonterminal on its right-hand sides. Mutually recursive nonterminals are boxed
+//!   together, and edges on a left-recursive cycle, which LL(1) can't parse, are red.
+//! - `lr_automaton` draws the LR(0) automaton the SLR(1) table is built from, with the items of
+//!   each state, and the states with unresolved conflicts in red.
+//! - `parse_tree` draws a parse tree from `cfg::tree`.
+//!
+//! Render the output with `dot -Tsvg`.
+
+use std::fmt::Write;
+use cfg::{Cfg, Frozen, PackedSymbol, Symbol, EPSILON};
+use cfg::lr::{self, Item};
+use cfg::tree::Tree;
+use cfg::util::strongly_connected_components;
+
+/// Escape `s` for a DOT string.
+fn escape(s: &str) -> String {
+    let mut out = String::new();
+    for c in s.chars() {
+        match c {
+            '"' | '\\' => { out.push('\\'); out.push(c) },
+            '\n' => out.push_str("\\n"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Quote `s` as a DOT string.
+fn quote(s: &str) -> String {
+    format!("\"{}\"", escape(s))
+}
+
+/// The nullable nonterminals of `cfg`, by index.
+fn nullable<T>(cfg: &Cfg<T>) -> Vec<bool> {
+    let mut nullable = vec![false; cfg.num_nonterminals() as usize];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for &(lhs, ref rhs) in cfg.rules() {
+            if !nullable[lhs.to_index()] && rhs.iter().all(|&s| s == EPSILON || (s.is_nonterminal() && nullable[s.to_index()])) {
+                nullable[lhs.to_index()] = true;
+                changed = true;
+            }
+        }
+    }
+    nullable
+}
+
+/// Draw the nonterminal dependency graph of `cfg`.
+pub fn dependencies<T>(cfg: &Cfg<T>) -> String {
+    let n = cfg.num_nonterminals() as usize;
+    let nullable = nullable(cfg);
+    let mut used = vec![false; n];
+    // Every dependency, and those in a left corner: preceded only by nullable symbols.
+    let mut edges: Vec<Vec<usize>> = vec![Vec::new(); n];
+    let mut left: Vec<Vec<usize>> = vec![Vec::new(); n];
+    for &(lhs, ref rhs) in cfg.rules() {
+        let a = lhs.to_index();
+        used[a] = true;
+        let mut corner = true;
+        for &sym in rhs.iter().filter(|&&s| s != EPSILON) {
+            if sym.is_terminal() {
+                corner = false;
+                continue;
+            }
+            let b = sym.to_index();
+            used[b] = true;
+            if !edges[a].contains(&b) {
+                edges[a].push(b);
+            }
+            if corner && !left[a].contains(&b) {
+                left[a].push(b);
+            }
+            corner = corner && nullable[b];
+        }
+    }
+
+    // Which component each nonterminal is in, and whether the component is a cycle.
+    let component_of = |graph: &[Vec<usize>]| -> (Vec<usize>, Vec<bool>) {
+        let mut of = vec![0; n];
+        let mut cyclic = Vec::new();
+        for (c, component) in strongly_connected_components(graph).into_iter().enumerate() {
+            for &v in &component {
+                of[v] = c;
+            }
+            cyclic.push(component.len() > 1 || graph[component[0]].contains(&component[0]));
+        }
+        (of, cyclic)
+    };
+    let (component, cyclic) = component_of(&edges);
+    let (left_component, left_cyclic) = component_of(&left);
+    let left_recursive = |a: usize, b: usize| {
+        left[a].contains(&b) && left_component[a] == left_component[b] && left_cyclic[left_component[a]]
+    };
+
+    let start = cfg.rules().nth(cfg.start).map(|&(lhs, _)| lhs.to_index());
+    let node = |out: &mut String, v: usize| {
+        let mut attrs = vec![format!("label={}", quote(&cfg.display_symbol(Symbol::Nonterminal(v as u32)).to_string()))];
+        if Some(v) == start {
+            attrs.push("peripheries=2".to_string());
+        }
+        if left[v].iter().any(|&b| left_recursive(v, b)) {
+            attrs.push("color=red, fontcolor=red".to_string());
+        }
+        write!(out, "n{} [{}];\n", v, attrs.join(", ")).unwrap();
+    };
+
+    let mut out = String::from("digraph dependencies {\n");
+    let mut drawn = vec![false; n];
+    for c in 0..cyclic.len() {
+        let members: Vec<usize> = (0..n).filter(|&v| used[v] && component[v] == c).collect();
+        if !cyclic[c] || members.is_empty() {
+            continue;
+        }
+        write!(out, "subgraph cluster_{} {{\nstyle=filled;\ncolor=\"#eeeeff\";\n", c).unwrap();
+        for &v in &members {
+            node(&mut out, v);
+            drawn[v] = true;
+        }
+        out.push_str("}\n");
+    }
+    for v in (0..n).filter(|&v| used[v] && !drawn[v]) {
+        node(&mut out, v);
+    }
+    for a in 0..n {
+        for &b in &edges[a] {
+            if left_recursive(a, b) {
+                write!(out, "n{} -> n{} [color=red, penwidth=2];\n", a, b).unwrap();
+            } else {
+                write!(out, "n{} -> n{};\n", a, b).unwrap();
+            }
+        }
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// Draw the LR(0) automaton of `cfg`, as used by `lr::generate_table`.
+pub fn lr_automaton(cfg: &mut Cfg<Frozen>) -> String {
+    let table = lr::generate_table(cfg);
+    let automaton = lr::compute_lr0(cfg);
+    let aug = automaton.augmented_rule();
+    let name = |sym: PackedSymbol| if sym == automaton.rules[aug.0].0 {
+        format!("{}'", cfg.display_symbol(automaton.rules[aug.0].1[0]))
+    } else {
+        cfg.display_symbol(sym).to_string()
+    };
+    let item = |item: Item| {
+        let (lhs, ref rhs) = automaton.rules[item.rule.0];
+        let mut text = format!("{} →", name(lhs));
+        for (i, &sym) in rhs.iter().enumerate() {
+            text.push_str(if i == item.dot { " • " } else { " " });
+            text.push_str(&name(sym));
+        }
+        if item.dot == rhs.len() {
+            text.push_str(" •");
+        }
+        text
+    };
+
+    let mut out = String::from("digraph lr0 {\nrankdir=LR;\nnode [shape=box, fontname=monospace];\n");
+    for (i, state) in automaton.states.iter().enumerate() {
+        // `\l` ends a left-justified line.
+        let items: String = state.items.iter().map(|&it| escape(&item(it)) + "\\l").collect();
+        let mut attrs = vec![format!("label=\"{}\\n{}\"", i, items)];
+        if state.items.contains(&Item { rule: aug, dot: 1 }) {
+            attrs.push("peripheries=2".to_string());
+        }
+        let conflicts: Vec<String> = table.conflicts.iter().filter(|c| c.state == i).map(|c| c.describe(cfg.names())).collect();
+        if !conflicts.is_empty() {
+            attrs.push(format!("color=red, penwidth=2, tooltip={}", quote(&conflicts.join("\n"))));
+        }
+        write!(out, "s{} [{}];\n", i, attrs.join(", ")).unwrap();
+    }
+    for (i, state) in automaton.states.iter().enumerate() {
+        for &(sym, target) in &state.gotos {
+            let style = if sym.is_terminal() { "" } else { ", style=dashed" };
+            write!(out, "s{} -> s{} [label={}{}];\n", i, target, quote(&name(sym)), style).unwrap();
+        }
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// Draw a parse tree of a string of `cfg`.
+pub fn parse_tree<T>(cfg: &Cfg<T>, tree: &Tree) -> String {
+    fn draw<T>(cfg: &Cfg<T>, tree: &Tree, next: &mut usize, out: &mut String) -> usize {
+        let id = *next;
+        *next += 1;
+        let label = quote(&cfg.display_symbol(tree.symbol()).to_string());
+        match *tree {
+            Tree::Leaf(_) => write!(out, "n{} [label={}, shape=box];\n", id, label).unwrap(),
+            Tree::Node(_, _, ref children) => {
+                write!(out, "n{} [label={}];\n", id, label).unwrap();
+                if children.is_empty() {
+                    let eps = *next;
+                    *next += 1;
+                    write!(out, "n{} [label=\"ε\", shape=plaintext];\nn{} -> n{};\n", eps, id, eps).unwrap();
+                }
+                for child in children {
+                    let c = draw(cfg, child, next, out);
+                    write!(out, "n{} -> n{};\n", id, c).unwrap();
+                }
+            },
+        }
+        id
+    }
+    let mut out = String::from("digraph tree {\nordering=out;\n");
+    draw(cfg, tree, &mut 0, &mut out);
+    out.push_str("}\n");
+    out
+}
@@ -0,0 +1,296 @@
+//! Desugaring EBNF operators into plain rules.
+//!
+//! An `Expr` is the right-hand side of an EBNF rule. `add_rules` turns it into rules of a `Cfg`,
+//! giving each operator that can't be expressed inline a fresh nonterminal:
+//!
+//! - A group of alternatives `(a | b)` inside a sequence becomes `N -> a | b`.
+//! - An optional `e?` or `[e]` becomes `N -> e | ε`.
+//! - A repetition `e*` or `{e}` becomes `N -> e N | ε`.
+//! - A repetition `e+` becomes `e N` in place, with `N` as for `e*`.
+//!
+//! Repetitions are right recursive, so desugaring doesn't introduce the left recursion LL(1)
+//! can't handle. An operator over an expression that can be empty would make an ambiguous
+//! grammar, so ε is first factored out of it: `{ a }+` becomes `a*`, and `(a? b?)*` becomes
+//! `(a b? | b)*`. Only ε written in the expression is seen; a nonterminal that derives ε through
+//! its rules still makes an ambiguous repetition.
+//!
+//! Fresh nonterminals are named after the rule they came from, such as `List#1`, skipping names
+//! already in use, and their `Origin` is recorded under the `Origins` key, so that diagnostics
+//! about them can point at the source.
+
+use std::collections::HashMap;
+use std::fmt;
+use cfg::{Cfg, Mutable, Rule, Symbol, SymbolTable, PackedSymbol, EPSILON};
+use cfg::bnf::Position;
+
+/// An EBNF operator.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Construct {
+    /// `( ... )`
+    Group,
+    /// `e?` or `[ ... ]`
+    Optional,
+    /// `e*` or `{ ... }`
+    Star,
+    /// `e+`
+    Plus,
+}
+
+impl fmt::Display for Construct {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Construct::Group => write!(f, "group"),
+            Construct::Optional => write!(f, "optional"),
+            Construct::Star => write!(f, "repetition `*`"),
+            Construct::Plus => write!(f, "repetition `+`"),
+        }
+    }
+}
+
+/// The right-hand side of an EBNF rule.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Expr {
+    Symbol(Symbol),
+    Seq(Vec<Expr>),
+    Alt(Vec<Expr>),
+    /// An operator applied to an expression, and where it is in the source.
+    Op(Construct, Position, Box<Expr>),
+}
+
+/// Where a nonterminal made by desugaring came from.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Origin {
+    /// The nonterminal whose rule contained the construct.
+    pub rule: Symbol,
+    pub construct: Construct,
+    pub position: Position,
+}
+
+impl Origin {
+    /// Describe the origin, naming the rule by `names`.
+    pub fn describe(&self, names: &SymbolTable) -> String {
+        format!("{} at {} in the rule for {}", self.construct, self.position, names.display(self.rule))
+    }
+}
+
+pub struct Origins;
+impl ::typemap::Key for Origins { type Value = HashMap<PackedSymbol, Origin>; }
+
+/// The origin of `nonterminal`, if it was made by desugaring.
+pub fn origin<T, S>(cfg: &Cfg<T>, nonterminal: S) -> Option<Origin> where PackedSymbol: From<S> {
+    cfg.extra().get::<Origins>().and_then(|o| o.get(&PackedSymbol::from(nonterminal)).cloned())
+}
+
+/// The definition of a fresh nonterminal, before or after lowering.
+enum Body<'e> {
+    Expr(&'e Expr),
+    Lowered(Vec<Vec<PackedSymbol>>),
+}
+
+struct Desugarer<'a, 'e> {
+    cfg: &'a mut Cfg<Mutable>,
+    rule: Symbol,
+    fresh: u32,
+    /// Fresh nonterminals whose rules are still to be added.
+    pending: Vec<(Symbol, Construct, Body<'e>)>,
+}
+
+impl<'a, 'e> Desugarer<'a, 'e> {
+    fn fresh(&mut self, construct: Construct, position: Position, body: Body<'e>) -> Symbol {
+        let base = self.cfg.display_symbol(self.rule).to_string();
+        let name = loop {
+            self.fresh += 1;
+            let name = format!("{}#{}", base, self.fresh);
+            if self.cfg.symbol_by_name(&name).is_none() {
+                break name;
+            }
+        };
+        let nt = self.cfg.add_named_nonterminal(&name);
+        let origin = Origin { rule: self.rule, construct: construct, position: position };
+        self.cfg.mut_extra().entry::<Origins>().or_insert_with(HashMap::new).insert(nt.into(), origin);
+        self.pending.push((nt, construct, body));
+        nt
+    }
+
+    fn add(&mut self, lhs: Symbol, rhs: Vec<PackedSymbol>) -> Rule {
+        if rhs.is_empty() {
+            self.cfg.add_rule(lhs, &[EPSILON])
+        } else {
+            self.cfg.add_rule(lhs, &rhs)
+        }
+    }
+
+    /// Lower `e` to alternatives.
+    fn alts(&mut self, e: &'e Expr) -> Vec<Vec<PackedSymbol>> {
+        match *e {
+            Expr::Alt(ref alts) => alts.iter().map(|a| { let mut seq = Vec::new(); self.seq(a, &mut seq); seq }).collect(),
+            Expr::Op(Construct::Group, _, ref inner) => self.alts(inner),
+            _ => { let mut seq = Vec::new(); self.seq(e, &mut seq); vec![seq] },
+        }
+    }
+
+    /// Lower `e` to a sequence, appended to `out`.
+    fn seq(&mut self, e: &'e Expr, out: &mut Vec<PackedSymbol>) {
+        match *e {
+            Expr::Symbol(sym) if sym == EPSILON.into() => (),
+            Expr::Symbol(sym) => out.push(sym.into()),
+            Expr::Seq(ref items) => for item in items {
+                self.seq(item, out);
+            },
+            Expr::Alt(ref alts) if alts.len() == 1 => self.seq(&alts[0], out),
+            Expr::Alt(_) => panic!("Alternatives inside a sequence must be in a group."),
+            Expr::Op(Construct::Group, pos, ref inner) => match **inner {
+                Expr::Alt(ref alts) if alts.len() > 1 => out.push(self.fresh(Construct::Group, pos, Body::Expr(inner)).into()),
+                _ => self.seq(inner, out),
+            },
+            Expr::Op(Construct::Plus, pos, ref inner) => {
+                // The first occurrence is lowered once and shared with the repetition.
+                let mut alts = self.alts(inner);
+                if alts.len() > 1 {
+                    alts = vec![vec![self.fresh(Construct::Group, pos, Body::Lowered(alts)).into()]];
+                }
+                out.extend(alts[0].iter().cloned());
+                out.push(self.fresh(Construct::Plus, pos, Body::Lowered(alts)).into());
+            },
+            Expr::Op(construct, pos, ref inner) => out.push(self.fresh(construct, pos, Body::Expr(inner)).into()),
+        }
+    }
+
+    fn define(&mut self, lhs: Symbol, e: &'e Expr) -> Vec<Rule> {
+        let rules = self.alts(e).into_iter().map(|alt| self.add(lhs, alt)).collect();
+        let mut i = 0;
+        while i < self.pending.len() {
+            let (nt, construct) = (self.pending[i].0, self.pending[i].1);
+            let mut alts = match ::std::mem::replace(&mut self.pending[i].2, Body::Lowered(Vec::new())) {
+                Body::Expr(e) => self.alts(e),
+                Body::Lowered(alts) => alts,
+            };
+            match construct {
+                Construct::Group => (),
+                Construct::Optional => alts.push(Vec::new()),
+                Construct::Star | Construct::Plus => {
+                    for alt in &mut alts {
+                        alt.push(nt.into());
+                    }
+                    alts.push(Vec::new());
+                },
+            }
+            for alt in alts {
+                self.add(nt, alt);
+            }
+            i += 1;
+        }
+        rules
+    }
+}
+
+/// Whether `e` matches the empty string, taking nonterminals not to.
+fn nullable(e: &Expr) -> bool {
+    match *e {
+        Expr::Symbol(sym) => sym == EPSILON.into(),
+        Expr::Seq(ref items) => items.iter().all(nullable),
+        Expr::Alt(ref alts) => alts.iter().any(nullable),
+        Expr::Op(Construct::Optional, _, _) | Expr::Op(Construct::Star, _, _) => true,
+        Expr::Op(_, _, ref inner) => nullable(inner),
+    }
+}
+
+/// An expression matching what `e` does except the empty string, or None if that's nothing.
+/// Alternatives inside a sequence are grouped at `pos`.
+fn without_empty(e: &Expr, pos: Position) -> Option<Expr> {
+    if !nullable(e) {
+        return Some(e.clone());
+    }
+    match *e {
+        Expr::Symbol(_) => None,
+        Expr::Seq(ref items) => {
+            // Everything before the first non-empty item matched the empty string.
+            let alts: Vec<Expr> = (0..items.len()).filter_map(|i| without_empty(&items[i], pos).map(|first| {
+                let first = match first {
+                    Expr::Alt(alts) => Expr::Op(Construct::Group, pos, Box::new(Expr::Alt(alts))),
+                    first => first,
+                };
+                let mut seq = vec![first];
+                seq.extend(items[i + 1..].iter().cloned());
+                if seq.len() == 1 { seq.pop().unwrap() } else { Expr::Seq(seq) }
+            })).collect();
+            alternatives(alts)
+        },
+        Expr::Alt(ref alts) => alternatives(alts.iter().filter_map(|a| without_empty(a, pos)).collect()),
+        Expr::Op(Construct::Star, p, ref inner) | Expr::Op(Construct::Plus, p, ref inner) =>
+            without_empty(inner, pos).map(|inner| Expr::Op(Construct::Plus, p, Box::new(inner))),
+        Expr::Op(_, _, ref inner) => without_empty(inner, pos),
+    }
+}
+
+/// The alternatives `alts` as one expression, with alternatives among them, grouped or not, spliced
+/// in, or None if there are none.
+fn alternatives(alts: Vec<Expr>) -> Option<Expr> {
+    let mut flat = Vec::new();
+    for alt in alts {
+        match alt {
+            Expr::Alt(inner) => flat.extend(inner),
+            Expr::Op(Construct::Group, _, inner) => match *inner {
+                Expr::Alt(inner) => flat.extend(inner),
+                inner => flat.push(inner),
+            },
+            alt => flat.push(alt),
+        }
+    }
+    let mut alts = flat;
+    match alts.len() {
+        0 => None,
+        1 => alts.pop(),
+        _ => Some(Expr::Alt(alts)),
+    }
+}
+
+/// Repeating `e+` is the same as repeating `e`.
+fn without_plus(e: Expr) -> Expr {
+    match e {
+        Expr::Op(Construct::Plus, _, inner) => without_plus(*inner),
+        Expr::Op(Construct::Group, pos, inner) => Expr::Op(Construct::Group, pos, Box::new(without_plus(*inner))),
+        Expr::Alt(alts) => Expr::Alt(alts.into_iter().map(without_plus).collect()),
+        e => e,
+    }
+}
+
+/// Rewrite the operators of `e` so that none applies to an expression that can be empty.
+fn factor_empty(e: &Expr) -> Expr {
+    match *e {
+        Expr::Symbol(sym) => Expr::Symbol(sym),
+        Expr::Seq(ref items) => Expr::Seq(items.iter().map(factor_empty).collect()),
+        Expr::Alt(ref alts) => Expr::Alt(alts.iter().map(factor_empty).collect()),
+        Expr::Op(Construct::Group, pos, ref inner) => Expr::Op(Construct::Group, pos, Box::new(factor_empty(inner))),
+        Expr::Op(construct, pos, ref inner) => {
+            let mut inner = factor_empty(inner);
+            let mut construct = construct;
+            if nullable(&inner) {
+                inner = match without_empty(&inner, pos) {
+                    Some(inner) => inner,
+                    None => return Expr::Symbol(EPSILON.into()),
+                };
+                // A repetition of something that can be empty can be repeated no times.
+                if construct == Construct::Plus {
+                    construct = Construct::Star;
+                }
+            }
+            if construct != Construct::Optional {
+                inner = without_plus(inner);
+            }
+            Expr::Op(construct, pos, Box::new(inner))
+        },
+    }
+}
+
+/// Add the rules `lhs -> e`, desugaring EBNF operators, and return them. The rules of fresh
+/// nonterminals are added after them.
+///
+/// Panics if an `Alt` of several alternatives is inside a sequence without a `Group`.
+pub fn add_rules(cfg: &mut Cfg<Mutable>, lhs: Symbol, e: &Expr) -> Vec<Rule> {
+    let fresh = cfg.extra().get::<Origins>()
+        .map_or(0, |o| o.values().filter(|o| o.rule == lhs).count() as u32);
+    let e = factor_empty(e);
+    let mut d = Desugarer { cfg: cfg, rule: lhs, fresh: fresh, pending: Vec::new() };
+    d.define(lhs, &e)
+}
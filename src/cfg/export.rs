@@ -0,0 +1,468 @@
+//! Exporting grammars to other parser generators.
+//!
+//! `bison`, `antlr` and `lark` write a `Cfg` as a bison `.y` file, an ANTLR 4 `.g4` file and a
+//! Lark `.lark` file. The rules of the start symbol come first, and each nonterminal's rules are
+//! written together as alternatives, with an ɛ-rule as an empty alternative.
+//!
+//! Terminals are written according to what they are:
+//!
+//! - A terminal with a character class from `cfg::scannerless` is a character literal if the
+//!   class has one character, and otherwise a token matching the class, where the format can
+//!   define one.
+//! - A terminal named with quotes, such as `'+'` from `cfg::yacc`, is a literal of the text
+//!   between them, and so is one whose name isn't an identifier, such as `+` from `cfg::bnf`.
+//! - Any other terminal is a token named by its name, which the format declares but leaves to
+//!   the lexer to define.
+//!
+//! A nonterminal that is used but has no rules derives nothing, so it is declared as a token,
+//! which the lexer never returns.
+//!
+//! Names are turned into identifiers of the format: characters that aren't allowed become `_`,
+//! case is changed where the format uses it to tell rules from tokens, reserved words get a `_`
+//! appended, and names that end up equal get numeric suffixes. Symbols without names are named
+//! after their index.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use cfg::{Cfg, Rule, PackedSymbol, EPSILON};
+use cfg::prec::{Assoc, Precedence};
+use cfg::scannerless::CharClasses;
+use cfg::yacc;
+use lex::CharSet;
+
+/// How a terminal is written.
+enum Terminal {
+    /// A token the lexer defines, by its name.
+    Token(String),
+    /// Literal text.
+    Literal(String),
+    /// Any one character of a set.
+    Class(CharSet),
+}
+
+fn is_ident(name: &str) -> bool {
+    name.chars().next().map_or(false, |c| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn unquote(name: &str) -> Option<String> {
+    let quote = name.chars().next();
+    if name.len() < 2 || !(quote == Some('\'') || quote == Some('"')) || name.chars().last() != quote {
+        return None;
+    }
+    let inner = &name[1..name.len() - 1];
+    let mut text = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => text.push('\n'),
+            Some('r') => text.push('\r'),
+            Some('t') => text.push('\t'),
+            Some(c) => text.push(c),
+            None => return None,
+        }
+    }
+    if text.is_empty() { None } else { Some(text) }
+}
+
+fn terminal<T>(cfg: &Cfg<T>, t: PackedSymbol) -> Terminal {
+    if let Some(class) = cfg.extra().get::<CharClasses>().and_then(|c| c.get(&t)) {
+        return match class.ranges() {
+            [(lo, hi)] if lo == hi => Terminal::Literal(lo.to_string()),
+            _ => Terminal::Class(class.clone()),
+        };
+    }
+    match cfg.symbol_name(t) {
+        Some(name) => match unquote(name) {
+            Some(text) => Terminal::Literal(text),
+            None if is_ident(name) => Terminal::Token(name.to_string()),
+            None => Terminal::Literal(name.to_string()),
+        },
+        None => Terminal::Token(format!("T{}", t.to_index())),
+    }
+}
+
+/// The case of an identifier, which some formats use to tell rules from tokens.
+#[derive(Copy, Clone, PartialEq)]
+enum Case {
+    Any,
+    Upper,
+    Lower,
+    /// Starting with an uppercase letter.
+    Capitalized,
+    /// Starting with a lowercase letter.
+    Uncapitalized,
+}
+
+/// Unique identifiers for symbols.
+struct Names {
+    names: HashMap<PackedSymbol, String>,
+    used: HashSet<String>,
+    reserved: &'static [&'static str],
+}
+
+impl Names {
+    fn new(reserved: &'static [&'static str]) -> Names {
+        Names { names: HashMap::new(), used: HashSet::new(), reserved: reserved }
+    }
+
+    /// Name `sym` by `name`, made into an identifier in `case`.
+    fn assign(&mut self, sym: PackedSymbol, name: &str, case: Case) -> String {
+        let mut base: String = name.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+        if !base.chars().any(|c| c.is_ascii_alphanumeric()) {
+            base = format!("{}", sym.to_index());
+        }
+        let letter = if sym.is_terminal() { 'T' } else { 'N' };
+        if case != Case::Any && !base.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            base.insert(0, letter);
+        } else if base.starts_with(|c: char| c.is_ascii_digit()) {
+            base.insert(0, letter);
+        }
+        match case {
+            Case::Any => (),
+            Case::Upper => base = base.to_ascii_uppercase(),
+            Case::Lower => base = base.to_ascii_lowercase(),
+            Case::Capitalized => base[..1].make_ascii_uppercase(),
+            Case::Uncapitalized => base[..1].make_ascii_lowercase(),
+        }
+        if self.reserved.contains(&&base[..]) {
+            base.push('_');
+        }
+        let mut name = base.clone();
+        let mut n = 1;
+        while self.used.contains(&name) {
+            n += 1;
+            name = format!("{}_{}", base, n);
+        }
+        self.used.insert(name.clone());
+        self.names.insert(sym, name.clone());
+        name
+    }
+}
+
+/// Each nonterminal with its rules, the start symbol first and the rest in order of their first
+/// rule.
+fn rules_by_lhs<T>(cfg: &Cfg<T>) -> Vec<(PackedSymbol, Vec<Rule>)> {
+    let mut groups: Vec<(PackedSymbol, Vec<Rule>)> = Vec::new();
+    for (i, &(lhs, _)) in cfg.rules().enumerate() {
+        match groups.iter().position(|&(l, _)| l == lhs) {
+            Some(g) => groups[g].1.push(Rule(i)),
+            None => groups.push((lhs, vec![Rule(i)])),
+        }
+    }
+    if let Some(&(start, _)) = cfg.rules().nth(cfg.start) {
+        if let Some(g) = groups.iter().position(|&(l, _)| l == start) {
+            let group = groups.remove(g);
+            groups.insert(0, group);
+        }
+    }
+    groups
+}
+
+/// Nonterminals used on a right-hand side that have no rules, in order of first use.
+fn undefined<T>(cfg: &Cfg<T>, groups: &[(PackedSymbol, Vec<Rule>)]) -> Vec<PackedSymbol> {
+    let mut undefined = Vec::new();
+    for &(_, ref rhs) in cfg.rules() {
+        for &sym in rhs {
+            if sym.is_nonterminal() && !groups.iter().any(|&(l, _)| l == sym) && !undefined.contains(&sym) {
+                undefined.push(sym);
+            }
+        }
+    }
+    undefined
+}
+
+fn is_empty(rhs: &[PackedSymbol]) -> bool {
+    rhs.iter().all(|&s| s == EPSILON)
+}
+
+/// Write `text` quoted by `quote`, with backslash escapes, and control characters written by
+/// `escape`.
+fn quote(text: &str, quote: char, escape: fn(char) -> String) -> String {
+    let mut out = String::new();
+    out.push(quote);
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c == quote => { out.push('\\'); out.push(c) },
+            c if c.is_control() => out.push_str(&escape(c)),
+            c => out.push(c),
+        }
+    }
+    out.push(quote);
+    out
+}
+
+fn c_escape(c: char) -> String {
+    format!("\\x{:02x}", c as u32)
+}
+
+fn java_escape(c: char) -> String {
+    if (c as u32) <= 0xFFFF { format!("\\u{:04X}", c as u32) } else { format!("\\u{{{:X}}}", c as u32) }
+}
+
+fn python_escape(c: char) -> String {
+    if (c as u32) <= 0xFFFF { format!("\\u{:04x}", c as u32) } else { format!("\\U{:08x}", c as u32) }
+}
+
+/// Write `set` as a bracketed character set, with characters outside printable ASCII written by
+/// `escape`.
+fn bracket(set: &CharSet, special: &str, escape: fn(char) -> String) -> String {
+    let show = |c: char| if c == '\\' || c == ']' || c == '-' || c == '^' || special.contains(c) {
+        format!("\\{}", c)
+    } else if c > ' ' && c <= '~' {
+        c.to_string()
+    } else {
+        escape(c)
+    };
+    let mut out = String::from("[");
+    for &(lo, hi) in set.ranges() {
+        out.push_str(&show(lo));
+        if hi != lo {
+            out.push('-');
+            out.push_str(&show(hi));
+        }
+    }
+    out.push(']');
+    out
+}
+
+const BISON_RESERVED: &'static [&'static str] = &["error", "YYEOF", "YYUNDEF", "YYerror"];
+
+/// Write `cfg` as a bison grammar file.
+///
+/// Tokens are declared with `%token`, and literals of one character are written as character
+/// literals. Other literals and character classes are tokens with their text as a string alias,
+/// which the lexer must still return. Precedence declarations in `cfg::prec` become `%left`,
+/// `%right` and `%nonassoc`, rules with overridden precedence get a `%prec`, and actions kept by
+/// `cfg::yacc` are written after their rules. ɛ-rules are written `%empty`.
+pub fn bison<T>(cfg: &Cfg<T>) -> String {
+    let mut names = Names::new(BISON_RESERVED);
+    let mut out = String::new();
+    let groups = rules_by_lhs(cfg);
+
+    for t in 2..cfg.num_terminals() {
+        let t = PackedSymbol(t);
+        match terminal(cfg, t) {
+            Terminal::Literal(ref text) if text.len() == 1 => {
+                names.names.insert(t, quote(text, '\'', c_escape));
+            },
+            Terminal::Token(ref name) if name == "error" => { names.names.insert(t, name.clone()); },
+            Terminal::Token(name) => {
+                let name = names.assign(t, &name, Case::Any);
+                writeln!(out, "%token {}", name).unwrap();
+            },
+            Terminal::Literal(_) | Terminal::Class(_) => {
+                let text = cfg.display_symbol(t).to_string();
+                let name = names.assign(t, &text, Case::Upper);
+                if name == text {
+                    writeln!(out, "%token {}", name).unwrap();
+                } else {
+                    writeln!(out, "%token {} {}", name, quote(&text, '"', c_escape)).unwrap();
+                }
+            },
+        }
+    }
+    for &(lhs, _) in &groups {
+        let name = cfg.display_symbol(lhs).to_string();
+        names.assign(lhs, &name, Case::Any);
+    }
+    for nt in undefined(cfg, &groups) {
+        let name = cfg.display_symbol(nt).to_string();
+        writeln!(out, "%token {}", names.assign(nt, &name, Case::Any)).unwrap();
+    }
+
+    let prec = cfg.extra().get::<Precedence>();
+    if let Some(prec) = prec {
+        let mut levels: Vec<(u32, Assoc, Vec<PackedSymbol>)> = Vec::new();
+        for t in 2..cfg.num_terminals() {
+            let t = PackedSymbol(t);
+            if let Some(level) = prec.terminal(t) {
+                match levels.iter().position(|l| l.0 == level.level) {
+                    Some(l) => levels[l].2.push(t),
+                    None => levels.push((level.level, level.assoc, vec![t])),
+                }
+            }
+        }
+        levels.sort_by_key(|l| l.0);
+        for (_, assoc, terminals) in levels {
+            let directive = match assoc { Assoc::Left => "%left", Assoc::Right => "%right", Assoc::Nonassoc => "%nonassoc" };
+            let terminals: Vec<&str> = terminals.iter().map(|t| &names.names[t][..]).collect();
+            writeln!(out, "{} {}", directive, terminals.join(" ")).unwrap();
+        }
+    }
+    if let Some(&(start, _)) = groups.first() {
+        writeln!(out, "%start {}", names.names[&start]).unwrap();
+    }
+    out.push_str("\n%%\n");
+
+    for (lhs, rules) in groups {
+        write!(out, "\n{}\n", names.names[&lhs]).unwrap();
+        for (i, &rule) in rules.iter().enumerate() {
+            let (_, rhs) = cfg.get_rule(rule).unwrap();
+            out.push_str(if i == 0 { "    :" } else { "    |" });
+            if is_empty(rhs) {
+                out.push_str(" %empty");
+            }
+            for sym in rhs.iter().filter(|&&s| s != EPSILON) {
+                write!(out, " {}", names.names[sym]).unwrap();
+            }
+            if let Some(t) = prec.and_then(|p| p.rule_override_terminal(rule)) {
+                write!(out, " %prec {}", names.names[&PackedSymbol::from(t)]).unwrap();
+            }
+            if let Some(code) = yacc::action(cfg, rule) {
+                write!(out, " {{{}}}", code).unwrap();
+            }
+            out.push('\n');
+        }
+        out.push_str("    ;\n");
+    }
+    out.push_str("\n%%\n");
+    out
+}
+
+const ANTLR_RESERVED: &'static [&'static str] = &[
+    "import", "fragment", "lexer", "parser", "grammar", "returns", "locals", "throws", "catch",
+    "finally", "mode", "options", "tokens", "channels", "EOF",
+];
+
+/// Write `cfg` as a combined ANTLR 4 grammar called `name`, which must match the file name.
+///
+/// Nonterminals become parser rules starting with a lowercase letter, and tokens start with an
+/// uppercase one. Tokens are declared in a `tokens` block, literals are written in place, and
+/// character classes become lexer rules.
+pub fn antlr<T>(cfg: &Cfg<T>, name: &str) -> String {
+    let mut names = Names::new(ANTLR_RESERVED);
+    let mut out = String::new();
+    let groups = rules_by_lhs(cfg);
+    let mut tokens = Vec::new();
+    let mut classes = Vec::new();
+
+    for t in 2..cfg.num_terminals() {
+        let t = PackedSymbol(t);
+        match terminal(cfg, t) {
+            Terminal::Literal(ref text) => { names.names.insert(t, quote(text, '\'', java_escape)); },
+            Terminal::Token(ref name) => tokens.push(names.assign(t, name, Case::Capitalized)),
+            Terminal::Class(set) => {
+                let text = cfg.display_symbol(t).to_string();
+                classes.push((names.assign(t, &text, Case::Upper), set));
+            },
+        }
+    }
+    for &(lhs, _) in &groups {
+        let name = cfg.display_symbol(lhs).to_string();
+        names.assign(lhs, &name, Case::Uncapitalized);
+    }
+    for nt in undefined(cfg, &groups) {
+        let name = cfg.display_symbol(nt).to_string();
+        tokens.push(names.assign(nt, &name, Case::Capitalized));
+    }
+
+    writeln!(out, "grammar {};", name).unwrap();
+    if !tokens.is_empty() {
+        write!(out, "\ntokens {{ {} }}\n", tokens.join(", ")).unwrap();
+    }
+    for (lhs, rules) in groups {
+        write!(out, "\n{}\n", names.names[&lhs]).unwrap();
+        for (i, &rule) in rules.iter().enumerate() {
+            let (_, rhs) = cfg.get_rule(rule).unwrap();
+            out.push_str(if i == 0 { "    :" } else { "    |" });
+            if is_empty(rhs) {
+                out.push_str(" /* ε */");
+            }
+            for sym in rhs.iter().filter(|&&s| s != EPSILON) {
+                write!(out, " {}", names.names[sym]).unwrap();
+            }
+            out.push('\n');
+        }
+        out.push_str("    ;\n");
+    }
+    for (name, set) in classes {
+        write!(out, "\n{} : {} ;\n", name, bracket(&set, "", java_escape)).unwrap();
+    }
+    out
+}
+
+// Lark has no reserved words, but rules are usually handled by methods of a `Transformer` named
+// after them, so Python's keywords and the transformer's own methods are avoided.
+const LARK_RESERVED: &'static [&'static str] = &[
+    "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del", "elif",
+    "else", "except", "finally", "for", "from", "global", "if", "import", "in", "is", "lambda",
+    "nonlocal", "not", "or", "pass", "raise", "return", "try", "while", "with", "yield",
+    "transform",
+];
+
+/// Write `cfg` as a Lark grammar.
+///
+/// Rule names are lowercase and terminal names uppercase, as Lark requires. Lark starts from
+/// `start`, so if the start symbol has another name a rule `start: <name>` is added. Tokens are
+/// declared with `%declare`, for a custom lexer to provide, literals are written in place, and
+/// character classes become terminals defined by a regular expression.
+pub fn lark<T>(cfg: &Cfg<T>) -> String {
+    let mut names = Names::new(LARK_RESERVED);
+    let mut out = String::new();
+    let groups = rules_by_lhs(cfg);
+    let mut tokens = Vec::new();
+    let mut classes = Vec::new();
+
+    for t in 2..cfg.num_terminals() {
+        let t = PackedSymbol(t);
+        match terminal(cfg, t) {
+            Terminal::Literal(ref text) => { names.names.insert(t, quote(text, '"', python_escape)); },
+            Terminal::Token(ref name) => tokens.push(names.assign(t, name, Case::Upper)),
+            Terminal::Class(set) => {
+                let text = cfg.display_symbol(t).to_string();
+                classes.push((names.assign(t, &text, Case::Upper), set));
+            },
+        }
+    }
+    let start = groups.first().map(|&(start, _)| start);
+    if let Some(start) = start {
+        let name = cfg.display_symbol(start).to_string();
+        if names.assign(start, &name, Case::Lower) != "start" {
+            names.used.insert("start".to_string());
+        }
+    }
+    for &(lhs, _) in groups.iter().skip(1) {
+        let name = cfg.display_symbol(lhs).to_string();
+        names.assign(lhs, &name, Case::Lower);
+    }
+    for nt in undefined(cfg, &groups) {
+        let name = cfg.display_symbol(nt).to_string();
+        tokens.push(names.assign(nt, &name, Case::Upper));
+    }
+
+    if let Some(start) = start {
+        if names.names[&start] != "start" {
+            write!(out, "start: {}\n\n", names.names[&start]).unwrap();
+        }
+    }
+    for (lhs, rules) in groups {
+        let name = &names.names[&lhs];
+        write!(out, "{}:", name).unwrap();
+        for (i, &rule) in rules.iter().enumerate() {
+            let (_, rhs) = cfg.get_rule(rule).unwrap();
+            if i > 0 {
+                write!(out, "\n{:1$}|", "", name.len()).unwrap();
+            }
+            for sym in rhs.iter().filter(|&&s| s != EPSILON) {
+                write!(out, " {}", names.names[sym]).unwrap();
+            }
+        }
+        out.push_str("\n\n");
+    }
+    if !tokens.is_empty() {
+        writeln!(out, "%declare {}", tokens.join(" ")).unwrap();
+    }
+    for (name, set) in classes {
+        writeln!(out, "{}: /{}/", name, bracket(&set, "/", python_escape)).unwrap();
+    }
+    out
+}
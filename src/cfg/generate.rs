@@ -0,0 +1,435 @@
+//! Random sentences of a grammar, for fuzzing parsers.
+//!
+//! A `Generator` expands nonterminals leftmost first, choosing among each nonterminal's rules at
+//! random in proportion to their weights, which are 1 unless set. Below `max_depth` levels of the
+//! derivation tree, every nonterminal is expanded by the rule it derives its shortest string
+//! with, from `util::compute_min_yield`, so generation always ends. Rules that can't derive a
+//! terminal string are never chosen.
+//!
+//! The random numbers come from `Rng`, seeded when the generator is made, so the same seed
+//! generates the same sentences again.
+//!
+//! Random derivation favors short sentences. To draw sentences of a given length uniformly,
+//! `Counts` counts the derivations of each length from each nonterminal, and `Counts::sample`
+//! chooses each rule, and how many terminals each symbol of it derives, in proportion to the
+//! number of derivations that choice leaves (the recursive method). Every derivation of the
+//! length is equally likely, so for an unambiguous grammar every sentence is. Counts are `u128`,
+//! and a length with `u128::MAX` or more derivations, such as any past 127 terminals for `S -> ε |
+//! a S | b S`, is too many to count: it can't be sampled, rather than sampled unevenly.
+//!
+//! For small grammars, `sentences` enumerates every sentence up to a length instead, each once.
+
+use std::collections::BTreeSet;
+use std::collections::btree_set;
+use cfg::{Cfg, Rule, Symbol, PackedSymbol, EPSILON};
+use cfg::util::{compute_min_yield, strongly_connected_components};
+
+/// A small seedable random number generator (SplitMix64).
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0..n`. Panics if `n` is 0.
+    pub fn below(&mut self, n: u64) -> u64 {
+        assert!(n > 0, "Rng::below(0)");
+        ((self.next_u64() as u128 * n as u128) >> 64) as u64
+    }
+
+    /// A number in `0..n`, for `n` as wide as counts of derivations. Panics if `n` is 0.
+    fn below_u128(&mut self, n: u128) -> u128 {
+        assert!(n > 0, "Rng::below_u128(0)");
+        // Draws past the last multiple of `n` would favor small numbers, so they're drawn again.
+        let limit = ::std::u128::MAX - ::std::u128::MAX % n;
+        loop {
+            let x = (self.next_u64() as u128) << 64 | self.next_u64() as u128;
+            if x < limit {
+                return x % n;
+            }
+        }
+    }
+}
+
+/// Generates random sentences of a grammar.
+pub struct Generator<'a, T: 'a> {
+    cfg: &'a Cfg<T>,
+    rng: Rng,
+    max_depth: usize,
+    weights: Vec<u32>,
+    min: Vec<Option<(usize, Rule)>>,
+    /// The rules of each nonterminal that derive a terminal string.
+    by_lhs: Vec<Vec<Rule>>,
+}
+
+impl<'a, T> Generator<'a, T> {
+    /// Construct a generator for `cfg` seeded with `seed`, with a `max_depth` of 16.
+    pub fn new(cfg: &'a Cfg<T>, seed: u64) -> Generator<'a, T> {
+        let min = compute_min_yield(cfg);
+        let mut by_lhs = vec![Vec::new(); cfg.num_nonterminals() as usize];
+        for (i, &(lhs, ref rhs)) in cfg.rules().enumerate() {
+            if rhs.iter().all(|&s| s.is_terminal() || min[s.to_index()].is_some()) {
+                by_lhs[lhs.to_index()].push(Rule(i));
+            }
+        }
+        Generator {
+            cfg: cfg,
+            rng: Rng::new(seed),
+            max_depth: 16,
+            weights: vec![1; cfg.rules().len()],
+            min: min,
+            by_lhs: by_lhs,
+        }
+    }
+
+    /// Expand nonterminals below `depth` levels of the tree by their shortest rules.
+    pub fn max_depth(&mut self, depth: usize) -> &mut Generator<'a, T> {
+        self.max_depth = depth;
+        self
+    }
+
+    /// Choose `rule` in proportion to `weight` among the rules of its nonterminal. A rule with
+    /// weight 0 is only chosen as a shortest rule, past the depth budget or when all the rules of
+    /// its nonterminal have weight 0.
+    pub fn weight(&mut self, rule: Rule, weight: u32) -> &mut Generator<'a, T> {
+        self.weights[rule.0] = weight;
+        self
+    }
+
+    /// Generate a sentence from the start symbol.
+    pub fn sentence(&mut self) -> Vec<PackedSymbol> {
+        self.derivation().0
+    }
+
+    /// Generate a sentence from the start symbol, with the rules of its leftmost derivation.
+    pub fn derivation(&mut self) -> (Vec<PackedSymbol>, Vec<Rule>) {
+        let start = self.cfg.get_rule(Rule(self.cfg.start)).expect("The grammar has no rules.").0;
+        self.derivation_from(start)
+    }
+
+    /// Generate a string of terminals from `sym`, with the rules of its leftmost derivation.
+    ///
+    /// Panics if `sym` derives no terminal string.
+    pub fn derivation_from<S>(&mut self, sym: S) -> (Vec<PackedSymbol>, Vec<Rule>) where PackedSymbol: From<S> {
+        let sym = PackedSymbol::from(sym);
+        if sym.is_nonterminal() && self.min[sym.to_index()].is_none() {
+            panic!("{} derives no terminal string.", self.cfg.display_symbol::<PackedSymbol>(sym));
+        }
+        let mut sentence = Vec::new();
+        let mut derivation = Vec::new();
+        // The symbols left to derive, the leftmost last, with their depth in the tree.
+        let mut stack = vec![(sym, 0)];
+        while let Some((sym, depth)) = stack.pop() {
+            if sym == EPSILON {
+                continue;
+            }
+            if sym.is_terminal() {
+                sentence.push(sym);
+                continue;
+            }
+            let rule = self.choose(sym, depth);
+            derivation.push(rule);
+            let (_, rhs) = self.cfg.get_rule(rule).unwrap();
+            stack.extend(rhs.iter().rev().map(|&s| (s, depth + 1)));
+        }
+        (sentence, derivation)
+    }
+
+    fn choose(&mut self, nonterminal: PackedSymbol, depth: usize) -> Rule {
+        let shortest = self.min[nonterminal.to_index()].unwrap().1;
+        if depth >= self.max_depth {
+            return shortest;
+        }
+        let rules = &self.by_lhs[nonterminal.to_index()];
+        let total: u64 = rules.iter().map(|r| self.weights[r.0] as u64).sum();
+        if total == 0 {
+            return shortest;
+        }
+        let mut pick = self.rng.below(total);
+        for &r in rules {
+            let weight = self.weights[r.0] as u64;
+            if pick < weight {
+                return r;
+            }
+            pick -= weight;
+        }
+        unreachable!()
+    }
+}
+
+/// The number of derivations of each length from each nonterminal, up to a maximum length.
+pub struct Counts<'a, T: 'a> {
+    cfg: &'a Cfg<T>,
+    max_length: usize,
+    /// For each nonterminal, its number of derivations of each length, saturating at
+    /// `u128::MAX`.
+    nonterminals: Vec<Vec<u128>>,
+    /// For each rule and each position in its right-hand side without ε, the number of
+    /// derivations of each length from the symbols from there on, saturating likewise.
+    suffixes: Vec<Vec<Vec<u128>>>,
+}
+
+impl<'a, T> Counts<'a, T> {
+    /// Count the derivations of strings of up to `max_length` terminals from each nonterminal.
+    ///
+    /// Returns the nonterminal as the error if one derives itself, with the rest deriving ε,
+    /// since it then has infinitely many derivations.
+    pub fn new(cfg: &'a Cfg<T>, max_length: usize) -> Result<Counts<'a, T>, Symbol> {
+        let rules: Vec<Vec<PackedSymbol>> = cfg.rules().map(|&(_, ref rhs)| {
+            rhs.iter().cloned().filter(|&s| s != EPSILON).collect()
+        }).collect();
+        try!(check_cycles(cfg, &rules));
+
+        let n = cfg.num_nonterminals() as usize;
+        let mut nonterminals = vec![vec![0u128; max_length + 1]; n];
+        let mut suffixes: Vec<Vec<Vec<u128>>> = rules.iter().map(|rhs| {
+            let mut suffix = vec![vec![0u128; max_length + 1]; rhs.len() + 1];
+            suffix[rhs.len()][0] = 1;
+            suffix
+        }).collect();
+        // Derivations of length `len` only depend on those of the same length through nullable
+        // symbols, and without cycles those settle in a round per nonterminal.
+        for len in 0..max_length + 1 {
+            let mut changed = true;
+            while changed {
+                changed = false;
+                for r in 0..rules.len() {
+                    for i in (0..rules[r].len()).rev() {
+                        let sym = rules[r][i];
+                        let mut total = 0u128;
+                        for k in 0..len + 1 {
+                            let first = if sym.is_terminal() { (k == 1) as u128 } else { nonterminals[sym.to_index()][k] };
+                            total = total.saturating_add(first.saturating_mul(suffixes[r][i + 1][len - k]));
+                        }
+                        suffixes[r][i][len] = total;
+                    }
+                }
+                for a in 0..n {
+                    let total = cfg.rules().enumerate().filter(|&(_, &(lhs, _))| lhs.to_index() == a)
+                        .fold(0u128, |total, (r, _)| total.saturating_add(suffixes[r][0][len]));
+                    if total != nonterminals[a][len] {
+                        nonterminals[a][len] = total;
+                        changed = true;
+                    }
+                }
+            }
+        }
+        Ok(Counts { cfg: cfg, max_length: max_length, nonterminals: nonterminals, suffixes: suffixes })
+    }
+
+    /// The number of derivations of strings of `length` terminals from `sym`, or None if there
+    /// are too many to count.
+    pub fn count<S>(&self, sym: S, length: usize) -> Option<u128> where PackedSymbol: From<S> {
+        match self.saturating_count(sym.into(), length) {
+            ::std::u128::MAX => None,
+            count => Some(count),
+        }
+    }
+
+    fn saturating_count(&self, sym: PackedSymbol, length: usize) -> u128 {
+        if length > self.max_length {
+            panic!("Only derivations of up to {} terminals were counted.", self.max_length);
+        }
+        if sym.is_terminal() { (length == 1) as u128 } else { self.nonterminals[sym.to_index()][length] }
+    }
+
+    /// Draw a sentence of `length` terminals uniformly, with the rules of its leftmost
+    /// derivation, or None if there is none or there are too many derivations to count.
+    pub fn sample(&self, rng: &mut Rng, length: usize) -> Option<(Vec<PackedSymbol>, Vec<Rule>)> {
+        let start = self.cfg.get_rule(Rule(self.cfg.start)).expect("The grammar has no rules.").0;
+        self.sample_from(rng, start, length)
+    }
+
+    /// Draw a string of `length` terminals derived from `sym` uniformly, with the rules of its
+    /// leftmost derivation, or None if there is none or there are too many derivations to count.
+    pub fn sample_from<S>(&self, rng: &mut Rng, sym: S, length: usize) -> Option<(Vec<PackedSymbol>, Vec<Rule>)> where PackedSymbol: From<S> {
+        let sym = PackedSymbol::from(sym);
+        // Every count sampling consults is at most this one, so if it didn't saturate none did.
+        match self.count::<PackedSymbol>(sym, length) {
+            None | Some(0) => return None,
+            Some(_) => (),
+        }
+        let mut sentence = Vec::new();
+        let mut derivation = Vec::new();
+        self.derive(rng, sym, length, &mut sentence, &mut derivation);
+        Some((sentence, derivation))
+    }
+
+    fn derive(&self, rng: &mut Rng, sym: PackedSymbol, length: usize, sentence: &mut Vec<PackedSymbol>, derivation: &mut Vec<Rule>) {
+        if sym.is_terminal() {
+            sentence.push(sym);
+            return;
+        }
+        let rules: Vec<usize> = (0..self.suffixes.len()).filter(|&r| self.cfg.get_rule(Rule(r)).unwrap().0 == sym).collect();
+        let r = choose_weighted(rng, rules.iter().map(|&r| (r, self.suffixes[r][0][length])));
+        derivation.push(Rule(r));
+        let rhs: Vec<PackedSymbol> = self.cfg.get_rule(Rule(r)).unwrap().1.iter().cloned().filter(|&s| s != EPSILON).collect();
+        let mut left = length;
+        for (i, &s) in rhs.iter().enumerate() {
+            let suffix = &self.suffixes[r][i + 1];
+            let k = choose_weighted(rng, (0..left + 1).map(|k| (k, self.saturating_count(s, k).saturating_mul(suffix[left - k]))));
+            self.derive(rng, s, k, sentence, derivation);
+            left -= k;
+        }
+    }
+}
+
+/// Choose among `choices` in proportion to their weights, at least one of which isn't 0, and
+/// whose total is a count that didn't saturate.
+fn choose_weighted<I: Iterator<Item=(usize, u128)> + Clone>(rng: &mut Rng, choices: I) -> usize {
+    let total = choices.clone().fold(0u128, |total, (_, w)| total + w);
+    let mut pick = rng.below_u128(total);
+    for (choice, weight) in choices {
+        if pick < weight {
+            return choice;
+        }
+        pick -= weight;
+    }
+    unreachable!()
+}
+
+/// Find a nonterminal that derives a terminal string and derives itself, with the rest deriving ε.
+fn check_cycles<T>(cfg: &Cfg<T>, rules: &[Vec<PackedSymbol>]) -> Result<(), Symbol> {
+    let min = compute_min_yield(cfg);
+    let nullable = |s: PackedSymbol| s.is_nonterminal() && min[s.to_index()].map(|(l, _)| l) == Some(0);
+    let mut units = vec![Vec::new(); cfg.num_nonterminals() as usize];
+    for (&(lhs, _), rhs) in cfg.rules().zip(rules) {
+        if rhs.iter().any(|&s| s.is_nonterminal() && min[s.to_index()].is_none()) {
+            continue;
+        }
+        for (i, &s) in rhs.iter().enumerate() {
+            if s.is_nonterminal() && rhs[..i].iter().chain(&rhs[i + 1..]).all(|&o| nullable(o)) {
+                units[lhs.to_index()].push(s.to_index());
+            }
+        }
+    }
+    for component in strongly_connected_components(&units) {
+        let a = component[0];
+        if component.len() > 1 || units[a].contains(&a) {
+            return Err(Symbol::Nonterminal(a as u32));
+        }
+    }
+    Ok(())
+}
+
+/// Every sentence of `cfg` of at most `max_length` terminals, shortest first, and in order of
+/// their symbols among those of a length.
+pub fn sentences<'a, T>(cfg: &'a Cfg<T>, max_length: usize) -> Sentences<'a, T> {
+    let rules: Vec<Vec<PackedSymbol>> = cfg.rules().map(|&(_, ref rhs)| {
+        rhs.iter().cloned().filter(|&s| s != EPSILON).collect()
+    }).collect();
+    Sentences {
+        cfg: cfg,
+        max_length: max_length,
+        rules: rules,
+        min: compute_min_yield(cfg),
+        languages: vec![Vec::new(); cfg.num_nonterminals() as usize],
+        current: BTreeSet::new().into_iter(),
+    }
+}
+
+/// An iterator over the sentences of a grammar up to a length, from `sentences`.
+///
+/// The strings of each length every nonterminal derives are kept, and built from those of the
+/// lengths before, so it takes memory in proportion to the language.
+pub struct Sentences<'a, T: 'a> {
+    cfg: &'a Cfg<T>,
+    max_length: usize,
+    /// The rules without ε.
+    rules: Vec<Vec<PackedSymbol>>,
+    min: Vec<Option<(usize, Rule)>>,
+    /// For each nonterminal, the strings of each length so far that it derives.
+    languages: Vec<Vec<BTreeSet<Vec<PackedSymbol>>>>,
+    current: btree_set::IntoIter<Vec<PackedSymbol>>,
+}
+
+impl<'a, T> Sentences<'a, T> {
+    fn min_length(&self, sym: PackedSymbol) -> Option<usize> {
+        if sym.is_terminal() { Some(1) } else { self.min[sym.to_index()].map(|(l, _)| l) }
+    }
+
+    /// The strings of `length` terminals that `seq` derives, from the languages so far.
+    fn derive(&self, seq: &[PackedSymbol], length: usize) -> Vec<Vec<PackedSymbol>> {
+        let (&first, rest) = match seq.split_first() {
+            Some(split) => split,
+            None => return if length == 0 { vec![Vec::new()] } else { Vec::new() },
+        };
+        let rest_min = rest.iter().fold(Some(0), |len, &s| len.and_then(|len| self.min_length(s).map(|l| len + l)));
+        let (first_min, rest_min) = match (self.min_length(first), rest_min) {
+            (Some(first), Some(rest)) if first + rest <= length => (first, rest),
+            _ => return Vec::new(),
+        };
+        let mut strings = Vec::new();
+        for k in first_min..length - rest_min + 1 {
+            let firsts: Vec<Vec<PackedSymbol>> = if first.is_terminal() {
+                if k == 1 { vec![vec![first]] } else { Vec::new() }
+            } else {
+                self.languages[first.to_index()][k].iter().cloned().collect()
+            };
+            if firsts.is_empty() {
+                continue;
+            }
+            let rests = self.derive(rest, length - k);
+            for f in &firsts {
+                for r in &rests {
+                    strings.push(f.iter().chain(r).cloned().collect());
+                }
+            }
+        }
+        strings
+    }
+
+    /// Add the strings of `length` terminals to the languages.
+    fn extend(&mut self, length: usize) {
+        for language in &mut self.languages {
+            language.push(BTreeSet::new());
+        }
+        // Strings of a length can come from others of the same length through nullable symbols.
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (r, &(lhs, _)) in self.cfg.rules().enumerate() {
+                for string in self.derive(&self.rules[r], length) {
+                    changed |= self.languages[lhs.to_index()][length].insert(string);
+                }
+            }
+        }
+    }
+}
+
+impl<'a, T> Iterator for Sentences<'a, T> {
+    type Item = Vec<PackedSymbol>;
+
+    fn next(&mut self) -> Option<Vec<PackedSymbol>> {
+        loop {
+            if let Some(sentence) = self.current.next() {
+                return Some(sentence);
+            }
+            let length = match self.languages.first() {
+                Some(language) => language.len(),
+                None => return None,
+            };
+            let start = match self.cfg.get_rule(Rule(self.cfg.start)) {
+                Some((start, _)) => start,
+                None => return None,
+            };
+            if length > self.max_length {
+                return None;
+            }
+            self.extend(length);
+            self.current = self.languages[start.to_index()][length].clone().into_iter();
+        }
+    }
+}
@@ -1,91 +1,375 @@
 //! LL(1) table generator.
 
-use cfg::{Cfg, Rule, EPSILON, END_OF_INPUT, Frozen, Token, Symbol, PackedSymbol};
+use cfg::{Cfg, Rule, EPSILON, END_OF_INPUT, Frozen, Token, Symbol, PackedSymbol, SymbolTable};
 use cfg::util::{compute_follow, compute_first_of, Follow};
+use cfg::render;
 
 pub struct Table {
     pub start: Symbol,
     pub rules: Vec<(PackedSymbol, Vec<PackedSymbol>)>,
+    pub names: SymbolTable,
     #[cfg(test)]
     pub table: Vec<Vec<Option<Rule>>>,
     #[cfg(not(test))]
     table: Vec<Vec<Option<Rule>>>,
 }
 
+/// Read access to a LL(1) parse table, so that the parsers work with any representation of it.
+pub trait ParseTable {
+    fn start(&self) -> Symbol;
+
+    /// Number of terminals, including `END_OF_INPUT` and `EPSILON`, the table has columns for.
+    fn num_terminals(&self) -> u32;
+
+    /// Number of nonterminals the table has rows for.
+    fn num_nonterminals(&self) -> u32;
+
+    /// The rule to expand the nonterminal with index `nonterminal` with on the lookahead terminal
+    /// with index `terminal`.
+    fn lookup(&self, nonterminal: usize, terminal: usize) -> Option<Rule>;
+
+    /// The right-hand side of `rule`.
+    fn rhs(&self, rule: Rule) -> &[PackedSymbol];
+
+    /// The names of the grammar's symbols, for messages.
+    fn names(&self) -> &SymbolTable;
+}
+
+impl Table {
+    /// The rule to expand `nonterminal` with on lookahead `terminal`.
+    pub fn entry<N, T>(&self, nonterminal: N, terminal: T) -> Option<Rule> where PackedSymbol: From<N>, PackedSymbol: From<T> {
+        self.table[PackedSymbol::from(nonterminal).to_index()][PackedSymbol::from(terminal).to_index()]
+    }
+}
+
+impl ParseTable for Table {
+    fn start(&self) -> Symbol {
+        self.start
+    }
+
+    fn num_terminals(&self) -> u32 {
+        self.table.first().map_or(0, |row| row.len() as u32)
+    }
+
+    fn num_nonterminals(&self) -> u32 {
+        self.table.len() as u32
+    }
+
+    fn lookup(&self, nonterminal: usize, terminal: usize) -> Option<Rule> {
+        self.table[nonterminal][terminal]
+    }
+
+    fn rhs(&self, rule: Rule) -> &[PackedSymbol] {
+        &self.rules[rule.0].1
+    }
+
+    fn names(&self) -> &SymbolTable {
+        &self.names
+    }
+}
+
 impl ::std::fmt::Debug for Table {
     fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
-        try!(writeln!(f, "Table: start rule is {:?}", self.start));
-        for (i, nonterms) in self.table.iter().enumerate() {
-            try!(write!(f, "{:?}:\t", Symbol::Nonterminal(i as u32)));
-            for term in nonterms {
-                try!(write!(f, "{:?}\t\t", term));
-            }
-            try!(write!(f, "\n"));
-        }
-        Ok(())
+        try!(writeln!(f, "Table: start rule is {}", self.names.display(self.start)));
+        write!(f, "{}", render::ll1_table(self, render::Format::Text))
     }
 }
 
-pub fn generate_table(cfg: &mut Cfg<Frozen>) -> Table {
+/// Every rule predicted for each nonterminal and lookahead terminal, indexed like the table.
+///
+/// A cell with more than one rule is a conflict, which `generate_table` panics on. The `EPSILON`
+/// column, which rules deriving ε are predicted in, is never a lookahead, so it has no conflicts.
+pub fn compute_cells(cfg: &mut Cfg<Frozen>) -> Vec<Vec<Vec<Rule>>> {
     compute_follow(cfg);
     let follow = cfg.extra().get::<Follow>().unwrap();
 
-    let mut table = vec![vec![None; cfg.max_term as usize]; cfg.max_nonterm as usize];
+    let mut cells = vec![vec![Vec::new(); cfg.max_term as usize]; cfg.max_nonterm as usize];
     for (i, &(lhs, ref rhs)) in cfg.rules().enumerate() {
         let first = compute_first_of(cfg, rhs);
-        for term in &first {
-            let slot = &mut table[lhs.to_index()][term.to_index()];
-            if slot.is_some() {
-                panic!("Conflict in LL(1) table generation! Sorry boss.");
+        let mut predict = |term: &PackedSymbol| {
+            let cell: &mut Vec<Rule> = &mut cells[lhs.to_index()][term.to_index()];
+            if !cell.contains(&Rule(i)) {
+                cell.push(Rule(i));
             }
-            *slot = Some(Rule(i));
+        };
+        for term in &first {
+            predict(term);
         }
         if first.contains(&EPSILON) {
-            let follow = &follow[lhs.to_index()];
-            for b in follow {
-                let slot = &mut table[lhs.to_index()][b.to_index()];
-                if slot.is_some() {
-                    panic!("Conflict in LL(1) table generation! Sorry boss.");
-                }
-                *slot = Some(Rule(i));
-            }
-            /*
-            if follow.contains(&END_OF_INPUT) {
-                let slot = &mut table[lhs.to_index()][END_OF_INPUT.to_index()];
-                if slot.is_some() {
-                    panic!("Conflict in LL(1) table generation! Sorry boss.");
-                }
-                *slot = Some(Rule(i));
+            for b in &follow[lhs.to_index()] {
+                predict(b);
             }
-            */
         }
     }
-    Table { rules: cfg.rules.clone(), start: cfg.rules[cfg.start].0.into(), table: table }
+    cells
+}
+
+pub fn generate_table(cfg: &mut Cfg<Frozen>) -> Table {
+    let cells = compute_cells(cfg);
+    let table = cells.into_iter().map(|row| row.into_iter().enumerate().map(|(t, cell)| {
+        if cell.len() > 1 && t != EPSILON.to_index() {
+            panic!("Conflict in LL(1) table generation! Sorry boss.");
+        }
+        cell.first().cloned()
+    }).collect()).collect();
+    Table { rules: cfg.rules.clone(), start: cfg.rules[cfg.start].0.into(), names: cfg.names.clone(), table: table }
 }
 
 /// Parse a string, returning the rules applied to derive the string.
-pub fn parse(tab: &Table, mut s: Vec<&Token>) -> Vec<Rule> {
-    let mut derivation = Vec::new();
-    s.push(&END_OF_INPUT);
-    let mut stack = vec![END_OF_INPUT.into(), tab.start];
+pub fn parse<T: ParseTable + ?Sized>(tab: &T, s: Vec<&Token>) -> Vec<Rule> {
+    let mut parser = Parser::new(tab);
+    for token in s {
+        parser.feed(token.to_terminal());
+    }
+    parser.feed(END_OF_INPUT.into());
+    parser.into_derivation()
+}
+
+/// Parse the prefix of a string derived from the nonterminal `start`, returning the rules applied
+/// to derive it and its length.
+///
+/// The first token after the prefix, or the end of input, is the lookahead used to choose
+/// ɛ-productions. If the table has no entry for it, but everything left to derive can derive ɛ,
+/// the prefix ends there and ɛ is derived from the rest.
+pub fn parse_prefix<T: ParseTable + ?Sized>(tab: &T, start: Symbol, s: &[&Token]) -> (Vec<Rule>, usize) {
+    let mut parser = Parser::starting_at(tab, start);
     let mut idx = 0;
-    let mut a = s[idx];
-    while *stack.last().unwrap() != END_OF_INPUT.into() {
-        if *stack.last().unwrap() == a.to_terminal().into() { stack.pop(); a = s[idx+1]; idx += 1; }
-        else if stack.last().unwrap().is_terminal() { panic!("Parse error! Expected {:?}, found {:?}.", stack.last().unwrap(), a.to_terminal()) }
-        else if tab.table[stack.last().unwrap().to_index()][a.to_terminal().to_index()].is_none() { panic!("Parse error! Found error entry in table.") }
-        else { // it's a nonterminal, and not an error!
-            let rule_idx = tab.table[stack.last().unwrap().to_index()][a.to_terminal().to_index()].unwrap().0;
-            let &(_, ref rhs) = &tab.rules[rule_idx];
-            derivation.push(Rule(rule_idx));
-            stack.pop();
-            for sym in rhs.iter().rev() {
-                stack.push(sym.into());
+    while !parser.is_done() {
+        let a = s.get(idx).map(|t| t.to_terminal()).unwrap_or(END_OF_INPUT.into());
+        if !parser.feed(a) {
+            break;
+        }
+        idx += 1;
+    }
+    (parser.into_derivation(), idx)
+}
+
+/// Why a terminal can't come next in an LL(1) parse.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Error {
+    /// The terminal `expected` is on top of the stack.
+    Expected { expected: Symbol, found: Symbol },
+    /// The table has no entry for `nonterminal` on `found`.
+    NoEntry { nonterminal: Symbol, found: Symbol },
+}
+
+impl Error {
+    /// Explain the error, naming symbols by `names`.
+    pub fn describe(&self, names: &SymbolTable) -> String {
+        match *self {
+            Error::Expected { expected, found } =>
+                format!("Expected {}, found {}.", names.display(expected), names.display(found)),
+            Error::NoEntry { nonterminal, found } =>
+                format!("Found error entry in table for {} on {}.", names.display(nonterminal), names.display(found)),
+        }
+    }
+}
+
+/// An LL(1) parser that is fed one terminal at a time.
+///
+/// Between terminals, `expected` tells which terminals the parser can accept next. This lets a
+/// lexer only try the patterns that make sense at the current position.
+pub struct Parser<'a, T: ParseTable + ?Sized + 'a = Table> {
+    tab: &'a T,
+    stack: Vec<Symbol>,
+    derivation: Vec<Rule>,
+}
+
+impl<'a, T: ParseTable + ?Sized> Parser<'a, T> {
+    /// A parser for the whole input, which must be fed `END_OF_INPUT` once the input runs out.
+    pub fn new(tab: &'a T) -> Parser<'a, T> {
+        Parser { tab: tab, stack: vec![END_OF_INPUT.into(), tab.start()], derivation: Vec::new() }
+    }
+
+    /// A parser for a prefix of the input derived from the nonterminal `start`.
+    pub fn starting_at(tab: &'a T, start: Symbol) -> Parser<'a, T> {
+        Parser { tab: tab, stack: vec![start], derivation: Vec::new() }
+    }
+
+    /// Whether the parser has derived everything it was started with.
+    pub fn is_done(&self) -> bool {
+        self.stack.is_empty()
+    }
+
+    /// The terminals that can come next: the terminal on top of the stack, or those with an entry
+    /// in the table row of the nonterminal on top of the stack.
+    pub fn expected(&self) -> Vec<Symbol> {
+        match self.stack.last() {
+            None => Vec::new(),
+            Some(&top) if top.is_terminal() => vec![top],
+            Some(&top) => (0..self.tab.num_terminals())
+                .filter(|&t| self.tab.lookup(top.to_index(), t as usize).is_some())
+                .map(Symbol::Terminal)
+                .collect(),
+        }
+    }
+
+    /// Expand the stack with lookahead `a` until `a` is consumed, returning true, or the stack is
+    /// empty, returning false. The stack is also emptied, deriving ɛ, when `a` has no entry in the
+    /// table but everything left on the stack can derive ɛ.
+    ///
+    /// Panics if `a` can't come next; see `try_feed`.
+    pub fn feed(&mut self, a: Symbol) -> bool {
+        match self.try_feed(a) {
+            Ok(consumed) => consumed,
+            Err(e) => panic!("Parse error! {}", e.describe(self.tab.names())),
+        }
+    }
+
+    /// Like `feed`, but returns an error if `a` can't come next. The parser is left as it was
+    /// when the error was found, with the rules applied until then.
+    pub fn try_feed(&mut self, a: Symbol) -> Result<bool, Error> {
+        while let Some(&top) = self.stack.last() {
+            if top == a { self.stack.pop(); self.pop_epsilons(); return Ok(true); }
+            else if top.is_terminal() { return Err(Error::Expected { expected: top, found: a }) }
+            else if self.tab.lookup(top.to_index(), a.to_index()).is_none() {
+                if self.derive_empty() { return Ok(false); }
+                return Err(Error::NoEntry { nonterminal: top, found: a })
+            }
+            else { // it's a nonterminal, and not an error!
+                let rule = self.tab.lookup(top.to_index(), a.to_index()).unwrap();
+                let rhs = self.tab.rhs(rule);
+                self.derivation.push(rule);
+                self.stack.pop();
+                for sym in rhs.iter().rev() {
+                    self.stack.push(sym.into());
+                }
+                self.pop_epsilons();
+            }
+        }
+        Ok(false)
+    }
+
+    /// Derive ɛ from everything left on the stack, if it can be, emptying it.
+    fn derive_empty(&mut self) -> bool {
+        let mut rules = Vec::new();
+        for &sym in self.stack.iter().rev() {
+            if sym.is_terminal() || !self.empty_derivation(sym.to_index(), &mut Vec::new(), &mut rules) {
+                return false;
+            }
+        }
+        self.stack.clear();
+        self.derivation.extend(rules);
+        true
+    }
+
+    /// Push the rules of a leftmost derivation of ɛ from `nonterminal` onto `rules`, using only
+    /// rules in its row of the table and not deriving any nonterminal in `visiting` again.
+    fn empty_derivation(&self, nonterminal: usize, visiting: &mut Vec<usize>, rules: &mut Vec<Rule>) -> bool {
+        visiting.push(nonterminal);
+        let len = rules.len();
+        let mut tried = Vec::new();
+        for t in 0..self.tab.num_terminals() as usize {
+            let rule = match self.tab.lookup(nonterminal, t) {
+                Some(rule) if !tried.contains(&rule) => rule,
+                _ => continue,
+            };
+            tried.push(rule);
+            rules.push(rule);
+            let derived = self.tab.rhs(rule).iter().all(|&sym| {
+                sym == EPSILON || (sym.is_nonterminal() && !visiting.contains(&sym.to_index())
+                                   && self.empty_derivation(sym.to_index(), visiting, rules))
+            });
+            if derived {
+                visiting.pop();
+                return true;
             }
+            rules.truncate(len);
+        }
+        visiting.pop();
+        false
+    }
+
+    fn pop_epsilons(&mut self) {
+        while self.stack.last() == Some(&EPSILON.into()) {
+            self.stack.pop();
         }
-        while *stack.last().unwrap() == EPSILON.into() {
-            stack.pop();
+    }
+
+    /// The rules applied so far.
+    pub fn derivation(&self) -> &[Rule] {
+        &self.derivation
+    }
+
+    pub fn into_derivation(self) -> Vec<Rule> {
+        self.derivation
+    }
+}
+
+/// A parse table as static data, written out by `write_static`.
+///
+/// Symbols are in their packed representation, and rules are indices into `rules`. Typically a
+/// build script writes the table to `OUT_DIR`, and it is included as
+///
+/// ```ignore
+/// static TABLE: StaticTable<'static> = include!(concat!(env!("OUT_DIR"), "/table.rs"));
+/// ```
+pub struct StaticTable<'a> {
+    pub start: u32,
+    pub rules: &'a [(u32, &'a [u32])],
+    pub table: &'a [&'a [Option<u32>]],
+}
+
+/// Write `tab` as a Rust expression constructing an equivalent `StaticTable`.
+pub fn write_static(tab: &Table, out: &mut ::std::io::Write) -> ::std::io::Result<()> {
+    try!(writeln!(out, "// Generated by pct. Do not edit."));
+    try!(writeln!(out, "::pct::cfg::ll1::StaticTable {{"));
+    try!(writeln!(out, "    start: {},", PackedSymbol::from(tab.start).0));
+    try!(writeln!(out, "    rules: &["));
+    for &(lhs, ref rhs) in &tab.rules {
+        let rhs = rhs.iter().map(|s| s.0.to_string()).collect::<Vec<_>>().join(", ");
+        try!(writeln!(out, "        ({}, &[{}]),", lhs.0, rhs));
+    }
+    try!(writeln!(out, "    ],"));
+    try!(writeln!(out, "    table: &["));
+    for row in &tab.table {
+        let row = row.iter().map(|e| match *e {
+            Some(Rule(r)) => format!("Some({})", r),
+            None => "None".to_string(),
+        }).collect::<Vec<_>>().join(", ");
+        try!(writeln!(out, "        &[{}],", row));
+    }
+    try!(writeln!(out, "    ],"));
+    try!(writeln!(out, "}}"));
+    Ok(())
+}
+
+/// Parse a string with a `StaticTable`, passing each rule applied to `derive`. This does not
+/// allocate: `stack` is used as the parse stack, and parsing panics if it overflows.
+///
+/// The rules are passed in the same order `parse` would return them, and like `parse` it panics
+/// if tokens are left over. `END_OF_INPUT` takes a slot of the stack under the start symbol.
+pub fn parse_static<F: FnMut(Rule)>(tab: &StaticTable, s: &[&Token], stack: &mut [u32], mut derive: F) {
+    fn push(stack: &mut [u32], len: &mut usize, sym: u32) {
+        if *len == stack.len() {
+            panic!("Parse error! Stack overflow.");
+        }
+        stack[*len] = sym;
+        *len += 1;
+    }
+
+    let mut len = 0;
+    push(stack, &mut len, END_OF_INPUT.0);
+    push(stack, &mut len, tab.start);
+    let mut idx = 0;
+    while len > 0 {
+        let top = PackedSymbol(stack[len - 1]);
+        let a = s.get(idx).map(|t| PackedSymbol::from(t.to_terminal())).unwrap_or(END_OF_INPUT);
+        if top == EPSILON { len -= 1; }
+        else if top == a { len -= 1; idx += 1; }
+        else if top.is_terminal() { panic!("Parse error! Expected {:?}, found {:?}.", Symbol::from(top), Symbol::from(a)) }
+        else {
+            match tab.table[top.to_index()][a.to_index()] {
+                None => panic!("Parse error! Found error entry in table."),
+                Some(rule_idx) => {
+                    let (_, rhs) = tab.rules[rule_idx as usize];
+                    derive(Rule(rule_idx as usize));
+                    len -= 1;
+                    for &sym in rhs.iter().rev() {
+                        push(stack, &mut len, sym);
+                    }
+                },
+            }
         }
     }
-    derivation
 }
@@ -0,0 +1,320 @@
+//! SLR(1) table generator.
+//!
+//! The automaton is the canonical collection of LR(0) item sets, over a copy of the grammar's
+//! rules with ɛ removed and an augmented rule `S' -> S` appended. Reductions are entered on the
+//! FOLLOW set of the rule's left-hand side.
+//!
+//! Shift/reduce conflicts are resolved with the declarations in `cfg::prec`, if any. Conflicts
+//! that can't be resolved are recorded in `Table::conflicts` and resolved the way yacc does: in
+//! favor of the shift, or of the earlier rule for reduce/reduce conflicts.
+
+use std::collections::HashMap;
+use std::fmt;
+use cfg::{Cfg, Rule, EPSILON, END_OF_INPUT, Frozen, Token, Symbol, PackedSymbol, SymbolTable};
+use cfg::util::{compute_follow, Follow};
+use cfg::prec::{Precedence, Resolution, Unresolved};
+
+/// An LR(0) item: a rule with a position in its right-hand side.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct Item {
+    pub rule: Rule,
+    pub dot: usize,
+}
+
+/// A state of the LR(0) automaton.
+pub struct State {
+    /// The kernel items, followed by the items added by closure.
+    pub items: Vec<Item>,
+    /// The number of kernel items at the start of `items`.
+    pub kernel: usize,
+    /// Transitions out of this state, in order of first appearance in `items`.
+    pub gotos: Vec<(PackedSymbol, usize)>,
+}
+
+/// The LR(0) automaton of a grammar. State 0 is the initial state.
+pub struct Automaton {
+    /// The grammar's rules without ɛ symbols, followed by the augmented rule `S' -> S`.
+    pub rules: Vec<(PackedSymbol, Vec<PackedSymbol>)>,
+    pub states: Vec<State>,
+}
+
+impl Automaton {
+    /// The augmented rule `S' -> S`.
+    pub fn augmented_rule(&self) -> Rule {
+        Rule(self.rules.len() - 1)
+    }
+
+    /// The symbol after the dot of `item`, if any.
+    pub fn next_symbol(&self, item: Item) -> Option<PackedSymbol> {
+        self.rules[item.rule.0].1.get(item.dot).cloned()
+    }
+}
+
+/// Build the LR(0) automaton of `cfg`.
+pub fn compute_lr0<T>(cfg: &Cfg<T>) -> Automaton {
+    let mut rules: Vec<(PackedSymbol, Vec<PackedSymbol>)> = cfg.rules().map(|&(lhs, ref rhs)| {
+        (lhs, rhs.iter().cloned().filter(|&s| s != EPSILON).collect())
+    }).collect();
+    let start = cfg.rules[cfg.start].0;
+    rules.push((Symbol::Nonterminal(cfg.max_nonterm as u32).into(), vec![start]));
+
+    let mut by_lhs = vec![Vec::new(); cfg.max_nonterm as usize];
+    for (i, &(lhs, _)) in rules[..rules.len() - 1].iter().enumerate() {
+        by_lhs[lhs.to_index()].push(Rule(i));
+    }
+
+    let closure = |kernel: Vec<Item>| -> Vec<Item> {
+        let mut items = kernel;
+        let mut added = vec![false; cfg.max_nonterm as usize];
+        let mut i = 0;
+        while i < items.len() {
+            let Item { rule, dot } = items[i];
+            if let Some(&sym) = rules[rule.0].1.get(dot) {
+                if sym.is_nonterminal() && !added[sym.to_index()] {
+                    added[sym.to_index()] = true;
+                    items.extend(by_lhs[sym.to_index()].iter().map(|&r| Item { rule: r, dot: 0 }));
+                }
+            }
+            i += 1;
+        }
+        items
+    };
+
+    let initial = vec![Item { rule: Rule(rules.len() - 1), dot: 0 }];
+    let mut seen = HashMap::new();
+    seen.insert(initial.clone(), 0);
+    let mut states = vec![State { items: closure(initial), kernel: 1, gotos: Vec::new() }];
+
+    let mut s = 0;
+    while s < states.len() {
+        let mut kernels: Vec<(PackedSymbol, Vec<Item>)> = Vec::new();
+        for &item in &states[s].items {
+            if let Some(&sym) = rules[item.rule.0].1.get(item.dot) {
+                let next = Item { rule: item.rule, dot: item.dot + 1 };
+                match kernels.iter().position(|&(x, _)| x == sym) {
+                    Some(k) => kernels[k].1.push(next),
+                    None => kernels.push((sym, vec![next])),
+                }
+            }
+        }
+        for (sym, mut kernel) in kernels {
+            kernel.sort();
+            let target = match seen.get(&kernel) {
+                Some(&t) => t,
+                None => {
+                    let t = states.len();
+                    seen.insert(kernel.clone(), t);
+                    let len = kernel.len();
+                    states.push(State { items: closure(kernel), kernel: len, gotos: Vec::new() });
+                    t
+                }
+            };
+            states[s].gotos.push((sym, target));
+        }
+        s += 1;
+    }
+
+    Automaton { rules: rules, states: states }
+}
+
+/// An entry in the action table.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Action {
+    Shift(usize),
+    Reduce(Rule),
+    Accept,
+}
+
+/// A conflict between two actions.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum ConflictKind {
+    ShiftReduce { shift: usize, reduce: Rule },
+    ReduceReduce(Rule, Rule),
+}
+
+/// A conflict that couldn't be resolved by precedence.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Conflict {
+    pub state: usize,
+    pub terminal: Symbol,
+    pub kind: ConflictKind,
+    /// Why precedence didn't resolve a shift/reduce conflict.
+    pub reason: Option<Unresolved>,
+}
+
+impl Conflict {
+    /// Explain the conflict, naming symbols by `names`.
+    pub fn describe(&self, names: &SymbolTable) -> String {
+        let mut s = match self.kind {
+            ConflictKind::ShiftReduce { shift, reduce } =>
+                format!("state {}: shift/reduce conflict on {} between shift to state {} and reduce by rule {}",
+                        self.state, names.display(self.terminal), shift, reduce.0),
+            ConflictKind::ReduceReduce(a, b) =>
+                format!("state {}: reduce/reduce conflict on {} between rules {} and {}",
+                        self.state, names.display(self.terminal), a.0, b.0),
+        };
+        if let Some(reason) = self.reason {
+            s.push_str(&format!(" ({})", reason.describe(names)));
+        }
+        s
+    }
+}
+
+pub struct Table {
+    pub start: Symbol,
+    /// The rules of the grammar with ɛ symbols removed.
+    pub rules: Vec<(PackedSymbol, Vec<PackedSymbol>)>,
+    pub names: SymbolTable,
+    pub conflicts: Vec<Conflict>,
+    action: Vec<Vec<Option<Action>>>,
+    goto: Vec<Vec<Option<usize>>>,
+}
+
+impl Table {
+    /// The action for `terminal` in `state`.
+    pub fn action<S>(&self, state: usize, terminal: S) -> Option<Action> where PackedSymbol: From<S> {
+        self.action[state][PackedSymbol::from(terminal).to_index()]
+    }
+
+    /// The state to go to after reducing to `nonterminal` in `state`.
+    pub fn goto<S>(&self, state: usize, nonterminal: S) -> Option<usize> where PackedSymbol: From<S> {
+        self.goto[state][PackedSymbol::from(nonterminal).to_index()]
+    }
+
+    /// Number of states in the automaton the table was built from.
+    pub fn num_states(&self) -> usize {
+        self.action.len()
+    }
+}
+
+impl fmt::Debug for Table {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(writeln!(f, "Table: start symbol is {}", self.names.display(self.start)));
+        for (i, (actions, gotos)) in self.action.iter().zip(self.goto.iter()).enumerate() {
+            try!(write!(f, "{}:\t", i));
+            for action in actions {
+                try!(write!(f, "{:?}\t\t", action));
+            }
+            for goto in gotos {
+                try!(write!(f, "{:?}\t\t", goto));
+            }
+            try!(write!(f, "\n"));
+        }
+        for conflict in &self.conflicts {
+            try!(writeln!(f, "{}", conflict.describe(&self.names)));
+        }
+        Ok(())
+    }
+}
+
+pub fn generate_table(cfg: &mut Cfg<Frozen>) -> Table {
+    compute_follow(cfg);
+    let follow = cfg.extra().get::<Follow>().unwrap();
+    let prec = cfg.extra().get::<Precedence>();
+    let automaton = compute_lr0(cfg);
+    let aug = automaton.augmented_rule();
+
+    let mut action = vec![vec![None; cfg.max_term as usize]; automaton.states.len()];
+    let mut goto = vec![vec![None; cfg.max_nonterm as usize]; automaton.states.len()];
+    // Cells made errors by `%nonassoc`, which later reductions mustn't fill again.
+    let mut errors = vec![vec![false; cfg.max_term as usize]; automaton.states.len()];
+    let mut conflicts = Vec::new();
+
+    for (i, state) in automaton.states.iter().enumerate() {
+        for &(sym, target) in &state.gotos {
+            if sym.is_terminal() {
+                action[i][sym.to_index()] = Some(Action::Shift(target));
+            } else {
+                goto[i][sym.to_index()] = Some(target);
+            }
+        }
+        for &item in &state.items {
+            if automaton.next_symbol(item).is_some() {
+                continue;
+            }
+            if item.rule == aug {
+                action[i][END_OF_INPUT.to_index()] = Some(Action::Accept);
+                continue;
+            }
+            let lhs = automaton.rules[item.rule.0].0;
+            for &t in &follow[lhs.to_index()] {
+                if errors[i][t.to_index()] {
+                    continue;
+                }
+                let slot = &mut action[i][t.to_index()];
+                match *slot {
+                    None => *slot = Some(Action::Reduce(item.rule)),
+                    Some(Action::Shift(shift)) => {
+                        let resolution = match prec {
+                            Some(prec) => prec.resolve(cfg, item.rule, t),
+                            None => Resolution::Unresolved(Unresolved::RuleHasNoPrecedence(item.rule)),
+                        };
+                        match resolution {
+                            Resolution::Shift => (),
+                            Resolution::Reduce => *slot = Some(Action::Reduce(item.rule)),
+                            Resolution::Error => {
+                                *slot = None;
+                                errors[i][t.to_index()] = true;
+                            },
+                            Resolution::Unresolved(reason) => conflicts.push(Conflict {
+                                state: i,
+                                terminal: t.into(),
+                                kind: ConflictKind::ShiftReduce { shift: shift, reduce: item.rule },
+                                reason: Some(reason),
+                            }),
+                        }
+                    },
+                    Some(Action::Reduce(other)) => {
+                        conflicts.push(Conflict {
+                            state: i,
+                            terminal: t.into(),
+                            kind: ConflictKind::ReduceReduce(other, item.rule),
+                            reason: None,
+                        });
+                        if item.rule.0 < other.0 {
+                            *slot = Some(Action::Reduce(item.rule));
+                        }
+                    },
+                    Some(Action::Accept) => conflicts.push(Conflict {
+                        state: i,
+                        terminal: t.into(),
+                        kind: ConflictKind::ReduceReduce(aug, item.rule),
+                        reason: None,
+                    }),
+                }
+            }
+        }
+    }
+
+    let mut rules = automaton.rules;
+    rules.pop();
+    Table { start: cfg.rules[cfg.start].0.into(), rules: rules, names: cfg.names.clone(), conflicts: conflicts, action: action, goto: goto }
+}
+
+/// Parse a string, returning the rules reduced by, in order.
+///
+/// This is the rightmost derivation of the string, reversed.
+pub fn parse(tab: &Table, mut s: Vec<&Token>) -> Vec<Rule> {
+    let mut reductions = Vec::new();
+    s.push(&END_OF_INPUT);
+    let mut stack = vec![0];
+    let mut idx = 0;
+    loop {
+        let state = *stack.last().unwrap();
+        let a = s[idx].to_terminal();
+        match tab.action(state, a) {
+            Some(Action::Shift(next)) => { stack.push(next); idx += 1; },
+            Some(Action::Reduce(r)) => {
+                let &(lhs, ref rhs) = &tab.rules[r.0];
+                let len = stack.len() - rhs.len();
+                stack.truncate(len);
+                let top = *stack.last().unwrap();
+                stack.push(tab.goto(top, lhs).unwrap());
+                reductions.push(r);
+            },
+            Some(Action::Accept) => break,
+            None => panic!("Parse error! Unexpected {} in state {}.", tab.names.display(a), state),
+        }
+    }
+    reductions
+}
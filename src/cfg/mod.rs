@@ -34,6 +34,10 @@
 //!
 //! ɛ is represented as a rule with an empty rhs.
 //!
+//! Symbols may be given names, which are kept in a `SymbolTable` owned by the `Cfg`. Anything that
+//! prints symbols or rules goes through it, falling back to the `Debug` representation for unnamed
+//! symbols.
+//!
 //! # Freezing
 //!
 //! For efficiency, many algorithms may want to compute relations on the set of terminals,
@@ -51,11 +55,30 @@
 
 pub mod util;
 pub mod bnf;
+pub mod ebnf;
+pub mod abnf;
+pub mod w3c;
+pub mod yacc;
+pub mod export;
+pub mod railroad;
+pub mod render;
+pub mod tree;
+pub mod dot;
+pub mod ambiguity;
+pub mod counterexample;
+pub mod generate;
+pub mod shortest;
 pub mod ll1;
+pub mod codegen;
+pub mod compress;
+pub mod lr;
+pub mod prec;
+pub mod pratt;
+pub mod scannerless;
 mod test;
 
 /// A Symbol is either a non-terminal or a terminal.
-#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
 pub enum Symbol {
     Terminal(u32),
     Nonterminal(u32),
@@ -73,7 +96,7 @@ impl Symbol {
     }
 }
 
-#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
 /// A PackedSymbol is a more compact representation of a Symbol
 pub struct PackedSymbol(u32);
 
@@ -140,10 +163,108 @@ pub struct Cfg<T> {
     // precisely what we want.
     max_nonterm: i32,
     max_term: i32,
+    names: SymbolTable,
+}
+
+/// The names of the symbols of a grammar, which can be looked up in both directions.
+///
+/// Names are unique among all terminals and nonterminals.
+#[derive(Clone, Debug)]
+pub struct SymbolTable {
+    names: ::std::collections::HashMap<PackedSymbol, String>,
+    symbols: ::std::collections::HashMap<String, PackedSymbol>,
+}
+
+impl SymbolTable {
+    /// Construct a table without any names.
+    pub fn new() -> SymbolTable {
+        SymbolTable { names: ::std::collections::HashMap::new(), symbols: ::std::collections::HashMap::new() }
+    }
+
+    /// Name `sym`, replacing any name it had. Panics if the name is already in use by another
+    /// symbol.
+    pub fn set_name<S>(&mut self, sym: S, name: &str) where PackedSymbol: From<S> {
+        if let Err(other) = self.try_set_name(sym, name) {
+            panic!("The name {:?} is already used by {:?}.", name, other);
+        }
+    }
+
+    /// Name `sym`, replacing any name it had, unless the name is already in use by another symbol,
+    /// which is returned.
+    pub fn try_set_name<S>(&mut self, sym: S, name: &str) -> Result<(), Symbol> where PackedSymbol: From<S> {
+        let sym = PackedSymbol::from(sym);
+        if let Some(&other) = self.symbols.get(name) {
+            if other != sym {
+                return Err(other.into());
+            }
+        }
+        if let Some(old) = self.names.insert(sym, name.to_string()) {
+            self.symbols.remove(&old);
+        }
+        self.symbols.insert(name.to_string(), sym);
+        Ok(())
+    }
+
+    /// The name of `sym`, if it has one.
+    pub fn name<S>(&self, sym: S) -> Option<&str> where PackedSymbol: From<S> {
+        self.names.get(&PackedSymbol::from(sym)).map(|s| &s[..])
+    }
+
+    /// The symbol named `name`, if any.
+    pub fn symbol(&self, name: &str) -> Option<Symbol> {
+        self.symbols.get(name).map(|&s| s.into())
+    }
+
+    /// Display `sym` by name. `END_OF_INPUT` and `EPSILON` are `$` and `ε` unless named otherwise.
+    pub fn display<'a, S>(&'a self, sym: S) -> DisplaySymbol<'a> where PackedSymbol: From<S> {
+        DisplaySymbol { names: self, sym: PackedSymbol::from(sym) }
+    }
+
+    /// Display the rule `lhs -> rhs` by the names of its symbols.
+    pub fn display_rule<'a>(&'a self, lhs: PackedSymbol, rhs: &'a [PackedSymbol]) -> DisplayRule<'a> {
+        DisplayRule { names: self, rule: (lhs, rhs) }
+    }
+}
+
+/// Displays a symbol by its name in a `SymbolTable`.
+pub struct DisplaySymbol<'a> {
+    names: &'a SymbolTable,
+    sym: PackedSymbol,
+}
+
+impl<'a> ::std::fmt::Display for DisplaySymbol<'a> {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        match self.names.name(self.sym) {
+            Some(name) => write!(f, "{}", name),
+            None if self.sym == END_OF_INPUT => write!(f, "$"),
+            None if self.sym == EPSILON => write!(f, "ε"),
+            None => write!(f, "{:?}", Symbol::from(self.sym)),
+        }
+    }
+}
+
+/// Displays a rule of a grammar as `lhs -> rhs[0] rhs[1] ...`, with an empty rhs shown as `ε`.
+pub struct DisplayRule<'a> {
+    names: &'a SymbolTable,
+    rule: (PackedSymbol, &'a [PackedSymbol]),
+}
+
+impl<'a> ::std::fmt::Display for DisplayRule<'a> {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        let (lhs, rhs) = self.rule;
+        try!(write!(f, "{} ->", self.names.display(lhs)));
+        if rhs.is_empty() {
+            try!(write!(f, " ε"));
+        }
+        for &sym in rhs {
+            try!(write!(f, " {}", self.names.display(sym)));
+        }
+        Ok(())
+    }
 }
 
 /// A Rule maps from a nonterminal to a sequence of symbols it can be replaced with.
-#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
 pub struct Rule(pub usize);
 
 /// A marker type indicating that a `Cfg` can be mutated.
@@ -157,7 +278,7 @@ pub static EPSILON: PackedSymbol = PackedSymbol(1);
 impl Cfg<Mutable> {
     /// Construct a CFG for the empty language.
     pub fn new() -> Cfg<Mutable> {
-        Cfg { phantom: ::std::marker::PhantomData, start: 0, rules: Vec::new(), max_nonterm: 0, max_term: 2, extra: ::typemap::TypeMap::new() }
+        Cfg { phantom: ::std::marker::PhantomData, start: 0, rules: Vec::new(), max_nonterm: 0, max_term: 2, extra: ::typemap::TypeMap::new(), names: SymbolTable::new() }
     }
 
     /// Add a nonterminal, returning the new grammar symbol that can be used.
@@ -174,6 +295,48 @@ impl Cfg<Mutable> {
         Symbol::Terminal(val as u32)
     }
 
+    /// Add a nonterminal named `name`, returning it. Panics if the name is already in use.
+    pub fn add_named_nonterminal(&mut self, name: &str) -> Symbol {
+        let sym = self.add_nonterminal();
+        self.names.set_name(sym, name);
+        sym
+    }
+
+    /// Add a terminal named `name`, returning it. Panics if the name is already in use.
+    pub fn add_named_terminal(&mut self, name: &str) -> Symbol {
+        let sym = self.add_terminal();
+        self.names.set_name(sym, name);
+        sym
+    }
+
+    /// Add a nonterminal named `name`, returning it, unless the name is already used by another
+    /// symbol, which is returned.
+    pub fn try_add_named_nonterminal(&mut self, name: &str) -> Result<Symbol, Symbol> {
+        match self.names.symbol(name) {
+            Some(other) => Err(other),
+            None => Ok(self.add_named_nonterminal(name)),
+        }
+    }
+
+    /// Add a terminal named `name`, returning it, unless the name is already used by another
+    /// symbol, which is returned.
+    pub fn try_add_named_terminal(&mut self, name: &str) -> Result<Symbol, Symbol> {
+        match self.names.symbol(name) {
+            Some(other) => Err(other),
+            None => Ok(self.add_named_terminal(name)),
+        }
+    }
+
+    /// Name an existing symbol. See `SymbolTable::set_name`.
+    pub fn set_name<S>(&mut self, sym: S, name: &str) where PackedSymbol: From<S> {
+        self.names.set_name(sym, name);
+    }
+
+    /// Name an existing symbol. See `SymbolTable::try_set_name`.
+    pub fn try_set_name<S>(&mut self, sym: S, name: &str) -> Result<(), Symbol> where PackedSymbol: From<S> {
+        self.names.try_set_name(sym, name)
+    }
+
     /// Add a rule to the grammar, `lhs -> rhs[0] rhs[1] ...`, returning a `Rule`.
     pub fn add_rule<'a, L, R>(&mut self, lhs: L, rhs: &'a [R]) -> Rule where PackedSymbol: From<L>, PackedSymbol: From<&'a R>, {
         self.rules.push((lhs.into(), rhs.iter().map(PackedSymbol::from).collect()));
@@ -187,14 +350,15 @@ impl Cfg<Mutable> {
 
     /// Freeze this `Cfg`, preventing later mutations.
     pub fn freeze(self) -> Cfg<Frozen> {
-        let Cfg { phantom: _phantom, rules, extra, start, max_nonterm, max_term } = self;
+        let Cfg { phantom: _phantom, rules, extra, start, max_nonterm, max_term, names } = self;
         Cfg {
             phantom: ::std::marker::PhantomData::<Frozen>,
             rules: rules,
             extra: extra,
             start: start,
             max_nonterm: max_nonterm,
-            max_term: max_term
+            max_term: max_term,
+            names: names,
         }
     }
 }
@@ -215,6 +379,33 @@ impl<T> Cfg<T> {
         self.rules.get(r.0).map(|&(s, ref r)| (s, &r[..]))
     }
 
+    /// The names of the grammar's symbols.
+    pub fn names(&self) -> &SymbolTable {
+        &self.names
+    }
+
+    /// The name of `sym`, if it has one.
+    pub fn symbol_name<S>(&self, sym: S) -> Option<&str> where PackedSymbol: From<S> {
+        self.names.name(sym)
+    }
+
+    /// The symbol named `name`, if any.
+    pub fn symbol_by_name(&self, name: &str) -> Option<Symbol> {
+        self.names.symbol(name)
+    }
+
+    /// Display `sym` by name.
+    pub fn display_symbol<'a, S>(&'a self, sym: S) -> DisplaySymbol<'a> where PackedSymbol: From<S> {
+        self.names.display(sym)
+    }
+
+    /// Display `r` as `lhs -> rhs[0] rhs[1] ...` by the names of its symbols. Panics if the rule
+    /// doesn't exist.
+    pub fn display_rule<'a>(&'a self, r: Rule) -> DisplayRule<'a> {
+        let (lhs, rhs) = self.get_rule(r).unwrap();
+        self.names.display_rule(lhs, rhs)
+    }
+
     /// Number of terminals used by the grammar.
     ///
     /// Each integer from 0 to this number (exclusive) is a valid terminal.
@@ -0,0 +1,131 @@
+//! Pratt (precedence climbing) parsing.
+//!
+//! Instead of encoding every precedence level as a chain of nonterminals, expressions can be
+//! described by an operator table of terminals with binding powers. Everything between operators
+//! is an *atom*, parsed from a nonterminal of a `Cfg` with that grammar's LL(1) table.
+//!
+//! Binding powers work as usual: a left-associative infix operator at level `n` has binding powers
+//! `(2n, 2n + 1)`, a right-associative one `(2n + 1, 2n)`. Prefix operators only have a right
+//! binding power, and postfix operators only a left one.
+//!
+//! Since the atom grammar can't refer back to expressions, parentheses are declared as a `group`.
+
+use std::collections::HashMap;
+use cfg::{Rule, Token, Symbol, PackedSymbol, END_OF_INPUT};
+use cfg::ll1::{self, Table};
+
+/// An operator table.
+pub struct Operators {
+    prefix: HashMap<PackedSymbol, u32>,
+    infix: HashMap<PackedSymbol, (u32, u32)>,
+    postfix: HashMap<PackedSymbol, u32>,
+    group: HashMap<PackedSymbol, PackedSymbol>,
+}
+
+impl Operators {
+    /// Construct an empty operator table.
+    pub fn new() -> Operators {
+        Operators { prefix: HashMap::new(), infix: HashMap::new(), postfix: HashMap::new(), group: HashMap::new() }
+    }
+
+    /// Declare a prefix operator with right binding power `right`.
+    pub fn prefix<S>(&mut self, op: S, right: u32) -> &mut Operators where PackedSymbol: From<S> {
+        self.prefix.insert(op.into(), right);
+        self
+    }
+
+    /// Declare an infix operator with binding powers `left` and `right`.
+    pub fn infix<S>(&mut self, op: S, left: u32, right: u32) -> &mut Operators where PackedSymbol: From<S> {
+        self.infix.insert(op.into(), (left, right));
+        self
+    }
+
+    /// Declare a postfix operator with left binding power `left`.
+    pub fn postfix<S>(&mut self, op: S, left: u32) -> &mut Operators where PackedSymbol: From<S> {
+        self.postfix.insert(op.into(), left);
+        self
+    }
+
+    /// Declare a pair of grouping terminals, such as parentheses, that may surround any
+    /// expression.
+    pub fn group<S>(&mut self, open: S, close: S) -> &mut Operators where PackedSymbol: From<S> {
+        self.group.insert(open.into(), close.into());
+        self
+    }
+}
+
+/// A parse tree of an expression.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Tree {
+    /// An atom, with the rules applied to derive it from the atom nonterminal.
+    Atom(Vec<Rule>),
+    Prefix(Symbol, Box<Tree>),
+    Infix(Box<Tree>, Symbol, Box<Tree>),
+    Postfix(Box<Tree>, Symbol),
+}
+
+struct Parser<'a, 'b: 'a> {
+    tab: &'a Table,
+    atom: Symbol,
+    ops: &'a Operators,
+    s: &'a [&'b Token],
+    idx: usize,
+}
+
+impl<'a, 'b> Parser<'a, 'b> {
+    fn peek(&self) -> PackedSymbol {
+        self.s.get(self.idx).map(|t| t.to_terminal().into()).unwrap_or(END_OF_INPUT)
+    }
+
+    fn expr(&mut self, min: u32) -> Tree {
+        let a = self.peek();
+        let mut lhs = if let Some(&right) = self.ops.prefix.get(&a) {
+            self.idx += 1;
+            Tree::Prefix(a.into(), Box::new(self.expr(right)))
+        } else if let Some(&close) = self.ops.group.get(&a) {
+            self.idx += 1;
+            let inner = self.expr(0);
+            if self.peek() != close {
+                panic!("Parse error! Expected {}, found {}.", self.tab.names.display(close), self.tab.names.display(self.peek()));
+            }
+            self.idx += 1;
+            inner
+        } else {
+            let (derivation, len) = ll1::parse_prefix(self.tab, self.atom, &self.s[self.idx..]);
+            self.idx += len;
+            Tree::Atom(derivation)
+        };
+
+        loop {
+            let op = self.peek();
+            if let Some(&left) = self.ops.postfix.get(&op) {
+                if left < min {
+                    break;
+                }
+                self.idx += 1;
+                lhs = Tree::Postfix(Box::new(lhs), op.into());
+            } else if let Some(&(left, right)) = self.ops.infix.get(&op) {
+                if left < min {
+                    break;
+                }
+                self.idx += 1;
+                let rhs = self.expr(right);
+                lhs = Tree::Infix(Box::new(lhs), op.into(), Box::new(rhs));
+            } else {
+                break;
+            }
+        }
+        lhs
+    }
+}
+
+/// Parse a string as an expression over `ops`, whose atoms are derived from the nonterminal
+/// `atom` using `tab`.
+pub fn parse(tab: &Table, atom: Symbol, ops: &Operators, s: Vec<&Token>) -> Tree {
+    let mut parser = Parser { tab: tab, atom: atom, ops: ops, s: &s[..], idx: 0 };
+    let tree = parser.expr(0);
+    if parser.idx != s.len() {
+        panic!("Parse error! Expected an operator, found {}.", tab.names.display(s[parser.idx].to_terminal()));
+    }
+    tree
+}
@@ -0,0 +1,158 @@
+//! Operator precedence and associativity declarations.
+//!
+//! These are the yacc-style `%left`, `%right`, `%nonassoc` and `%prec` declarations. They are
+//! stored in the `extra` map of a `Cfg` under the `Precedence` key, and are consulted by the LR
+//! table generator to resolve shift/reduce conflicts in ambiguous grammars such as `E -> E + E |
+//! E * E`.
+//!
+//! Each call to `declare` introduces a new precedence level, binding tighter than every level
+//! declared before it. The precedence of a rule is that of the last terminal in its right-hand
+//! side, unless it was overridden with `set_rule_precedence`.
+
+use std::collections::HashMap;
+use cfg::{Cfg, Rule, Symbol, PackedSymbol, SymbolTable};
+
+/// The associativity of a precedence level.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum Assoc {
+    Left,
+    Right,
+    Nonassoc,
+}
+
+/// A precedence level. Higher levels bind tighter.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Level {
+    pub level: u32,
+    pub assoc: Assoc,
+}
+
+/// The precedence declarations of a grammar.
+#[derive(Clone, Debug)]
+pub struct Declarations {
+    levels: u32,
+    terminals: HashMap<PackedSymbol, Level>,
+    /// Each overridden rule's level, and the terminal it was taken from.
+    rules: HashMap<Rule, (Level, PackedSymbol)>,
+}
+
+pub struct Precedence;
+impl ::typemap::Key for Precedence { type Value = Declarations; }
+
+/// The outcome of consulting the declarations about a shift/reduce conflict.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Resolution {
+    Shift,
+    Reduce,
+    /// The rule and the terminal are `%nonassoc` at the same level; the input is an error.
+    Error,
+    Unresolved(Unresolved),
+}
+
+/// Why a shift/reduce conflict could not be resolved by precedence.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Unresolved {
+    RuleHasNoPrecedence(Rule),
+    TerminalHasNoPrecedence(Symbol),
+}
+
+impl Unresolved {
+    /// Explain the reason, naming symbols by `names`.
+    pub fn describe(&self, names: &SymbolTable) -> String {
+        match *self {
+            Unresolved::RuleHasNoPrecedence(r) =>
+                format!("rule {} has no precedence: it has no %prec and no terminal with a declared precedence", r.0),
+            Unresolved::TerminalHasNoPrecedence(t) =>
+                format!("{} has no declared precedence", names.display(t)),
+        }
+    }
+}
+
+impl Declarations {
+    pub fn new() -> Declarations {
+        Declarations { levels: 0, terminals: HashMap::new(), rules: HashMap::new() }
+    }
+
+    /// Declare a new precedence level containing `terminals`, returning it.
+    ///
+    /// Terminals that were already declared are moved to the new level.
+    pub fn declare<'a, R>(&mut self, assoc: Assoc, terminals: &'a [R]) -> Level where PackedSymbol: From<&'a R> {
+        self.levels += 1;
+        let level = Level { level: self.levels, assoc: assoc };
+        for t in terminals {
+            self.terminals.insert(t.into(), level);
+        }
+        level
+    }
+
+    /// Give `rule` the precedence of `terminal`, as with yacc's `%prec`.
+    ///
+    /// Returns false, leaving the rule untouched, if `terminal` has no declared precedence.
+    pub fn set_rule_precedence<S>(&mut self, rule: Rule, terminal: S) -> bool where PackedSymbol: From<S> {
+        let terminal = terminal.into();
+        match self.terminals.get(&terminal) {
+            Some(&level) => { self.rules.insert(rule, (level, terminal)); true },
+            None => false,
+        }
+    }
+
+    /// The `%prec` override of a rule, if it has one.
+    pub fn rule_override(&self, rule: Rule) -> Option<Level> {
+        self.rules.get(&rule).map(|&(level, _)| level)
+    }
+
+    /// The terminal named by the `%prec` override of a rule, if it has one.
+    pub fn rule_override_terminal(&self, rule: Rule) -> Option<Symbol> {
+        self.rules.get(&rule).map(|&(_, terminal)| terminal.into())
+    }
+
+    /// The declared precedence of a terminal.
+    pub fn terminal<S>(&self, terminal: S) -> Option<Level> where PackedSymbol: From<S> {
+        self.terminals.get(&terminal.into()).cloned()
+    }
+
+    /// The precedence of a rule: its `%prec` override, or else that of the last terminal on its
+    /// right-hand side.
+    pub fn rule<T>(&self, cfg: &Cfg<T>, rule: Rule) -> Option<Level> {
+        if let Some(&(level, _)) = self.rules.get(&rule) {
+            return Some(level);
+        }
+        cfg.get_rule(rule)
+            .and_then(|(_, rhs)| rhs.iter().rev().find(|s| s.is_terminal() && **s != ::cfg::EPSILON).cloned())
+            .and_then(|t| self.terminals.get(&t).cloned())
+    }
+
+    /// Decide between reducing by `rule` and shifting `lookahead`.
+    pub fn resolve<T, S>(&self, cfg: &Cfg<T>, rule: Rule, lookahead: S) -> Resolution where PackedSymbol: From<S> {
+        let lookahead = PackedSymbol::from(lookahead);
+        let r = match self.rule(cfg, rule) {
+            Some(r) => r,
+            None => return Resolution::Unresolved(Unresolved::RuleHasNoPrecedence(rule)),
+        };
+        let t = match self.terminals.get(&lookahead).cloned() {
+            Some(t) => t,
+            None => return Resolution::Unresolved(Unresolved::TerminalHasNoPrecedence(lookahead.into())),
+        };
+        if t.level > r.level {
+            Resolution::Shift
+        } else if t.level < r.level {
+            Resolution::Reduce
+        } else {
+            match t.assoc {
+                Assoc::Left => Resolution::Reduce,
+                Assoc::Right => Resolution::Shift,
+                Assoc::Nonassoc => Resolution::Error,
+            }
+        }
+    }
+}
+
+/// Declare a new precedence level in `cfg`. See `Declarations::declare`.
+pub fn declare<'a, T, R>(cfg: &mut Cfg<T>, assoc: Assoc, terminals: &'a [R]) -> Level where PackedSymbol: From<&'a R> {
+    cfg.mut_extra().entry::<Precedence>().or_insert_with(Declarations::new).declare(assoc, terminals)
+}
+
+/// Override the precedence of `rule` in `cfg`. See `Declarations::set_rule_precedence`.
+pub fn set_rule_precedence<T, S>(cfg: &mut Cfg<T>, rule: Rule, terminal: S) -> bool where PackedSymbol: From<S> {
+    cfg.mut_extra().entry::<Precedence>().or_insert_with(Declarations::new).set_rule_precedence(rule, terminal)
+}
@@ -0,0 +1,310 @@
+//! Drawing grammars as railroad diagrams.
+//!
+//! `html` writes a page with an SVG railroad diagram for every nonterminal, and `svg` draws the
+//! diagram of one. The alternatives of a nonterminal are branches, terminals are drawn in
+//! rounded boxes and nonterminals in square ones that link to their own diagram.
+//!
+//! Right recursion is drawn as a loop: the rules `N -> a N | b N | c` are the repetition
+//! `(a | b)* c`, which is how `cfg::ebnf` desugars `*` and `+`. Nonterminals made by desugaring
+//! are drawn in place, so a grammar imported from EBNF looks like its source.
+
+use std::collections::HashSet;
+use std::fmt::Write;
+use cfg::{Cfg, PackedSymbol, EPSILON};
+use cfg::ebnf;
+
+/// A railroad diagram.
+#[derive(Clone, PartialEq, Debug)]
+enum Diagram {
+    Terminal(String),
+    /// A nonterminal, and the anchor of its diagram if it has one.
+    Nonterminal(String, Option<String>),
+    /// A track with nothing on it.
+    Skip,
+    Sequence(Vec<Diagram>),
+    /// Alternatives, the first on the main track.
+    Choice(Vec<Diagram>),
+    /// One or more repetitions, with a track looping back under.
+    OneOrMore(Box<Diagram>),
+}
+
+/// Vertical space between tracks.
+const VS: u32 = 8;
+/// The radius of the arcs joining tracks.
+const AR: u32 = 10;
+/// Horizontal space between items in a sequence.
+const GAP: u32 = 10;
+/// The height of a box, and the width of one of its characters.
+const BOX: u32 = 22;
+const CHAR: u32 = 8;
+
+/// The size of a diagram: its width, and its extent above and below the main track.
+#[derive(Copy, Clone)]
+struct Size {
+    width: u32,
+    up: u32,
+    down: u32,
+}
+
+fn zero_or_more(body: Diagram) -> Diagram {
+    Diagram::Choice(vec![Diagram::Skip, Diagram::OneOrMore(Box::new(body))])
+}
+
+impl Diagram {
+    fn size(&self) -> Size {
+        match *self {
+            Diagram::Terminal(ref text) | Diagram::Nonterminal(ref text, _) =>
+                Size { width: text.chars().count() as u32 * CHAR + 2 * GAP, up: BOX / 2, down: BOX / 2 },
+            Diagram::Skip => Size { width: 0, up: 0, down: 0 },
+            Diagram::Sequence(ref items) => {
+                let sizes: Vec<Size> = items.iter().map(Diagram::size).collect();
+                Size {
+                    width: sizes.iter().map(|s| s.width).sum::<u32>() + GAP * (sizes.len() as u32).saturating_sub(1),
+                    up: sizes.iter().map(|s| s.up).max().unwrap_or(0),
+                    down: sizes.iter().map(|s| s.down).max().unwrap_or(0),
+                }
+            },
+            Diagram::Choice(ref alts) => {
+                let sizes: Vec<Size> = alts.iter().map(Diagram::size).collect();
+                let offsets = Diagram::offsets(&sizes);
+                Size {
+                    width: sizes.iter().map(|s| s.width).max().unwrap_or(0) + 4 * AR,
+                    up: sizes[0].up,
+                    down: offsets[offsets.len() - 1] + sizes[sizes.len() - 1].down,
+                }
+            },
+            Diagram::OneOrMore(ref body) => {
+                let size = body.size();
+                Size { width: size.width + 2 * AR, up: size.up, down: Diagram::loop_offset(size) }
+            },
+        }
+    }
+
+    /// How far below the main track each alternative of a choice is.
+    fn offsets(sizes: &[Size]) -> Vec<u32> {
+        let mut offsets = vec![0];
+        for i in 1..sizes.len() {
+            let y = offsets[i - 1] + sizes[i - 1].down + VS + sizes[i].up;
+            offsets.push(::std::cmp::max(y, 2 * AR));
+        }
+        offsets
+    }
+
+    /// How far below the main track the loop of a repetition of a `body` this size is.
+    fn loop_offset(body: Size) -> u32 {
+        ::std::cmp::max(body.down + VS + AR, 2 * AR)
+    }
+
+    /// Draw the diagram with its main track entering at `(x, y)`.
+    fn render(&self, x: u32, y: u32, out: &mut String) {
+        let size = self.size();
+        match *self {
+            Diagram::Terminal(ref text) => {
+                write!(out, "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"{}\" class=\"terminal\"/>\n",
+                       x, y - BOX / 2, size.width, BOX, BOX / 2).unwrap();
+                write!(out, "<text x=\"{}\" y=\"{}\">{}</text>\n", x + size.width / 2, y + 4, escape(text)).unwrap();
+            },
+            Diagram::Nonterminal(ref text, ref link) => {
+                if let Some(ref link) = *link {
+                    write!(out, "<a href=\"#{}\">", escape(link)).unwrap();
+                }
+                write!(out, "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" class=\"nonterminal\"/>",
+                       x, y - BOX / 2, size.width, BOX).unwrap();
+                write!(out, "<text x=\"{}\" y=\"{}\">{}</text>", x + size.width / 2, y + 4, escape(text)).unwrap();
+                if link.is_some() {
+                    out.push_str("</a>");
+                }
+                out.push('\n');
+            },
+            Diagram::Skip => (),
+            Diagram::Sequence(ref items) => {
+                let mut x = x;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(out, "<path d=\"M{} {}h{}\"/>\n", x, y, GAP).unwrap();
+                        x += GAP;
+                    }
+                    item.render(x, y, out);
+                    x += item.size().width;
+                }
+            },
+            Diagram::Choice(ref alts) => {
+                let sizes: Vec<Size> = alts.iter().map(Diagram::size).collect();
+                let offsets = Diagram::offsets(&sizes);
+                let inner = size.width - 4 * AR;
+                for (i, alt) in alts.iter().enumerate() {
+                    let rest = inner - sizes[i].width;
+                    if i == 0 {
+                        write!(out, "<path d=\"M{} {}h{}\"/>\n", x, y, 2 * AR).unwrap();
+                        write!(out, "<path d=\"M{} {}h{}\"/>\n", x + 2 * AR + sizes[i].width, y, rest + 2 * AR).unwrap();
+                    } else {
+                        let drop = offsets[i] - 2 * AR;
+                        write!(out, "<path d=\"M{} {}a{r} {r} 0 0 1 {r} {r}v{}a{r} {r} 0 0 0 {r} {r}\"/>\n",
+                               x, y, drop, r = AR).unwrap();
+                        write!(out, "<path d=\"M{} {}h{}a{r} {r} 0 0 0 {r} -{r}v-{}a{r} {r} 0 0 1 {r} -{r}\"/>\n",
+                               x + 2 * AR + sizes[i].width, y + offsets[i], rest, drop, r = AR).unwrap();
+                    }
+                    alt.render(x + 2 * AR, y + offsets[i], out);
+                }
+            },
+            Diagram::OneOrMore(ref body) => {
+                let inner = body.size();
+                let drop = Diagram::loop_offset(inner) - 2 * AR;
+                write!(out, "<path d=\"M{} {}h{}\"/>\n", x, y, AR).unwrap();
+                write!(out, "<path d=\"M{} {}h{}\"/>\n", x + AR + inner.width, y, AR).unwrap();
+                write!(out, "<path d=\"M{} {}a{r} {r} 0 0 1 {r} {r}v{}a{r} {r} 0 0 1 -{r} {r}h-{}a{r} {r} 0 0 1 -{r} -{r}v-{}a{r} {r} 0 0 1 {r} -{r}\"/>\n",
+                       x + AR + inner.width, y, drop, inner.width, drop, r = AR).unwrap();
+                body.render(x + AR, y, out);
+            },
+        }
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// The anchor of a nonterminal's diagram in the page.
+fn anchor(nt: PackedSymbol) -> String {
+    format!("nt{}", nt.to_index())
+}
+
+/// Whether `nt` is drawn in place rather than in a diagram of its own.
+fn inlined<T>(cfg: &Cfg<T>, nt: PackedSymbol) -> bool {
+    ebnf::origin(cfg, nt).is_some()
+}
+
+fn choice(mut alts: Vec<Diagram>) -> Diagram {
+    match alts.len() {
+        0 => Diagram::Skip,
+        1 => alts.pop().unwrap(),
+        _ => Diagram::Choice(alts),
+    }
+}
+
+fn sequence(mut items: Vec<Diagram>) -> Diagram {
+    match items.len() {
+        0 => Diagram::Skip,
+        1 => items.pop().unwrap(),
+        _ => Diagram::Sequence(items),
+    }
+}
+
+/// The diagram of a sequence of symbols. `expanding` holds the nonterminals being drawn in
+/// place, which are drawn as boxes if they recur.
+fn symbols<T>(cfg: &Cfg<T>, rhs: &[PackedSymbol], expanding: &mut Vec<PackedSymbol>) -> Diagram {
+    let mut items: Vec<Diagram> = Vec::new();
+    for &sym in rhs.iter().filter(|&&s| s != EPSILON) {
+        let name = cfg.display_symbol(sym).to_string();
+        if sym.is_terminal() {
+            items.push(Diagram::Terminal(name));
+            continue;
+        }
+        if !inlined(cfg, sym) || expanding.contains(&sym) {
+            let link = if inlined(cfg, sym) { None } else { Some(anchor(sym)) };
+            items.push(Diagram::Nonterminal(name, link));
+            continue;
+        }
+        expanding.push(sym);
+        let d = nonterminal(cfg, sym, expanding);
+        expanding.pop();
+        // `e (e)*` is `e+`.
+        if let Diagram::Choice(ref alts) = d {
+            if let [Diagram::Skip, Diagram::OneOrMore(ref body)] = alts[..] {
+                let body_items = match **body { Diagram::Sequence(ref v) => v.clone(), ref b => vec![b.clone()] };
+                if items.ends_with(&body_items) {
+                    let len = items.len() - body_items.len();
+                    items.truncate(len);
+                    items.push(Diagram::OneOrMore(body.clone()));
+                    continue;
+                }
+            }
+        }
+        match d {
+            Diagram::Sequence(v) => items.extend(v),
+            d => items.push(d),
+        }
+    }
+    sequence(items)
+}
+
+/// The diagram of the rules of `nt`.
+fn nonterminal<T>(cfg: &Cfg<T>, nt: PackedSymbol, expanding: &mut Vec<PackedSymbol>) -> Diagram {
+    let rules: Vec<&[PackedSymbol]> = cfg.rules().filter(|&&(lhs, _)| lhs == nt).map(|&(_, ref rhs)| &rhs[..]).collect();
+    let recursive: Vec<&[PackedSymbol]> = rules.iter().cloned()
+        .filter(|rhs| rhs.last() == Some(&nt) && !rhs[..rhs.len() - 1].contains(&nt)).collect();
+    let base: Vec<&[PackedSymbol]> = rules.iter().cloned().filter(|rhs| !rhs.contains(&nt)).collect();
+
+    if recursive.is_empty() || recursive.len() + base.len() != rules.len() || base.is_empty() {
+        return choice(rules.iter().map(|rhs| symbols(cfg, rhs, expanding)).collect());
+    }
+    let body = choice(recursive.iter().map(|rhs| symbols(cfg, &rhs[..rhs.len() - 1], expanding)).collect());
+    let mut rest: Vec<Diagram> = base.iter().map(|rhs| symbols(cfg, rhs, expanding)).collect();
+    // A skip goes first, so that the track runs straight past the other alternatives.
+    if let Some(i) = rest.iter().position(|d| *d == Diagram::Skip) {
+        let skip = rest.remove(i);
+        rest.insert(0, skip);
+    }
+    let rest = choice(rest);
+    if rest == Diagram::Skip {
+        zero_or_more(body)
+    } else {
+        Diagram::Sequence(vec![zero_or_more(body), rest])
+    }
+}
+
+/// Draw the railroad diagram of the rules of `nonterminal` as an SVG element.
+pub fn svg<T, S>(cfg: &Cfg<T>, nonterminal: S) -> String where PackedSymbol: From<S> {
+    let nt = PackedSymbol::from(nonterminal);
+    let d = self::nonterminal(cfg, nt, &mut vec![nt]);
+    let size = d.size();
+    let pad = 20;
+    let width = size.width + 2 * pad + 2 * GAP;
+    let height = size.up + size.down + 2 * pad;
+    let y = pad + size.up;
+    let mut out = String::new();
+    write!(out, "<svg class=\"railroad\" xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n",
+           width, height, width, height).unwrap();
+    write!(out, "<path d=\"M{} {}v{}M{} {}h{}\"/>\n", pad, y - AR, 2 * AR, pad, y, GAP).unwrap();
+    d.render(pad + GAP, y, &mut out);
+    let end = pad + GAP + size.width;
+    write!(out, "<path d=\"M{} {}h{}M{} {}v{}\"/>\n", end, y, GAP, end + GAP, y - AR, 2 * AR).unwrap();
+    out.push_str("</svg>\n");
+    out
+}
+
+/// Write an HTML page titled `title` with the railroad diagram of every nonterminal with rules,
+/// the start symbol first, except those made by desugaring EBNF, which are drawn in place.
+pub fn html<T>(cfg: &Cfg<T>, title: &str) -> String {
+    let mut order: Vec<PackedSymbol> = Vec::new();
+    if let Some(&(start, _)) = cfg.rules().nth(cfg.start) {
+        order.push(start);
+    }
+    let mut seen: HashSet<PackedSymbol> = order.iter().cloned().collect();
+    for &(lhs, _) in cfg.rules() {
+        if seen.insert(lhs) {
+            order.push(lhs);
+        }
+    }
+
+    let mut out = String::new();
+    out.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    write!(out, "<title>{}</title>\n", escape(title)).unwrap();
+    out.push_str("<style>
+svg.railroad path { stroke: #333; stroke-width: 2; fill: none; }
+svg.railroad rect { stroke: #333; stroke-width: 2; fill: #eef; }
+svg.railroad rect.terminal { fill: #efe; }
+svg.railroad text { font: 13px monospace; text-anchor: middle; }
+svg.railroad a text { fill: #00c; }
+</style>
+</head>
+<body>
+");
+    write!(out, "<h1>{}</h1>\n", escape(title)).unwrap();
+    for nt in order.into_iter().filter(|&nt| !inlined(cfg, nt)) {
+        write!(out, "<h2 id=\"{}\">{}</h2>\n", anchor(nt), escape(&cfg.display_symbol(nt).to_string())).unwrap();
+        out.push_str(&svg(cfg, nt));
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
@@ -0,0 +1,186 @@
+//! Rendering parse tables for people to read.
+//!
+//! `ll1_table` renders an LL(1) parse table, and `ll1_grammar` the table of a grammar with every
+//! rule predicted in each cell, so that conflicts show. Rows are nonterminals and columns are
+//! terminals, both by name, and each cell holds the numbers of its rules:
+//!
+//! ```text
+//!    $  d  e  f
+//! -------------
+//! S:    0  0
+//! A:    2  1
+//! B:       3
+//! C: 4        5
+//! ```
+//!
+//! Conflicting rules are joined by `/`, in bold in Markdown, and in HTML the cell is highlighted
+//! and shows the rules themselves when hovered.
+
+use std::fmt::Write;
+use cfg::{Cfg, Frozen, Rule, Symbol, SymbolTable, EPSILON};
+use cfg::ll1::{self, ParseTable};
+
+/// A format to render a table in.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Format {
+    /// Aligned columns of plain text.
+    Text,
+    /// A GitHub-flavored Markdown table.
+    Markdown,
+    /// An HTML `<table>`.
+    Html,
+    /// Comma-separated values, as in RFC 4180.
+    Csv,
+}
+
+/// A table to render: the names of its columns, and each row's name and cells, with the text of
+/// the rules that can be in them.
+struct Grid {
+    columns: Vec<String>,
+    rows: Vec<(String, Vec<Vec<(Rule, String)>>)>,
+}
+
+/// Render an LL(1) parse table in `format`.
+pub fn ll1_table<T: ParseTable + ?Sized>(tab: &T, format: Format) -> String {
+    let names = tab.names();
+    let terminals = columns(tab.num_terminals());
+    let rows = (0..tab.num_nonterminals()).map(|n| {
+        let nt = Symbol::Nonterminal(n);
+        let cells = terminals.iter().map(|&t| {
+            tab.lookup(n as usize, t.to_index()).map(|r| (r, names.display_rule(nt.into(), tab.rhs(r)).to_string()))
+                .into_iter().collect()
+        }).collect();
+        (names.display(nt).to_string(), cells)
+    }).collect();
+    render(&Grid { columns: column_names(names, &terminals), rows: rows }, format)
+}
+
+/// Render the LL(1) table of `cfg` in `format`, with every rule predicted in each cell, even if
+/// the grammar isn't LL(1).
+pub fn ll1_grammar(cfg: &mut Cfg<Frozen>, format: Format) -> String {
+    let cells = ll1::compute_cells(cfg);
+    let terminals = columns(cfg.num_terminals());
+    let rows = cells.iter().enumerate().map(|(n, row)| {
+        let cells = terminals.iter().map(|&t| {
+            row[t.to_index()].iter().map(|&r| (r, cfg.display_rule(r).to_string())).collect()
+        }).collect();
+        (cfg.display_symbol(Symbol::Nonterminal(n as u32)).to_string(), cells)
+    }).collect();
+    render(&Grid { columns: column_names(cfg.names(), &terminals), rows: rows }, format)
+}
+
+/// The terminals with columns: all of them but `EPSILON`.
+fn columns(num_terminals: u32) -> Vec<Symbol> {
+    (0..num_terminals).map(Symbol::Terminal).filter(|&t| t != Symbol::from(EPSILON)).collect()
+}
+
+fn column_names(names: &SymbolTable, terminals: &[Symbol]) -> Vec<String> {
+    terminals.iter().map(|&t| names.display(t).to_string()).collect()
+}
+
+fn cell_text(cell: &[(Rule, String)]) -> String {
+    cell.iter().map(|&(r, _)| r.0.to_string()).collect::<Vec<_>>().join("/")
+}
+
+fn render(grid: &Grid, format: Format) -> String {
+    match format {
+        Format::Text => text(grid),
+        Format::Markdown => markdown(grid),
+        Format::Html => html(grid),
+        Format::Csv => csv(grid),
+    }
+}
+
+fn text(grid: &Grid) -> String {
+    let label = grid.rows.iter().map(|&(ref name, _)| name.chars().count() + 1).max().unwrap_or(0);
+    let widths: Vec<usize> = grid.columns.iter().enumerate().map(|(i, name)| {
+        grid.rows.iter().map(|&(_, ref cells)| cell_text(&cells[i]).chars().count())
+            .fold(name.chars().count(), ::std::cmp::max)
+    }).collect();
+
+    let line = |name: &str, cells: Vec<String>| {
+        let cells: Vec<String> = cells.iter().zip(&widths).map(|(c, &w)| format!("{:>1$}", c, w)).collect();
+        format!("{:2$} {}", name, cells.join("  "), label).trim_end().to_string()
+    };
+    let mut lines = vec![line("", grid.columns.clone())];
+    lines.push("-".repeat(label + 1 + widths.iter().sum::<usize>() + 2 * widths.len().saturating_sub(1)));
+    for &(ref name, ref cells) in &grid.rows {
+        lines.push(line(&format!("{}:", name), cells.iter().map(|c| cell_text(c)).collect()));
+    }
+    lines.join("\n") + "\n"
+}
+
+fn markdown(grid: &Grid) -> String {
+    let escape = |s: &str| s.chars().fold(String::new(), |mut out, c| {
+        if "\\|*_`<>[]".contains(c) {
+            out.push('\\');
+        }
+        out.push(c);
+        out
+    });
+    let mut out = String::from("|   |");
+    for name in &grid.columns {
+        write!(out, " {} |", escape(name)).unwrap();
+    }
+    out.push_str("\n|---|");
+    for _ in &grid.columns {
+        out.push_str("---|");
+    }
+    out.push('\n');
+    for &(ref name, ref cells) in &grid.rows {
+        write!(out, "| {} |", escape(name)).unwrap();
+        for cell in cells {
+            match cell.len() {
+                0 => out.push_str("   |"),
+                1 => write!(out, " {} |", cell_text(cell)).unwrap(),
+                _ => write!(out, " **{}** |", cell_text(cell)).unwrap(),
+            }
+        }
+        out.push('\n');
+    }
+    out
+}
+
+fn html(grid: &Grid) -> String {
+    let escape = |s: &str| s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;");
+    let mut out = String::from("<table class=\"ll1\">\n<tr><th></th>");
+    for name in &grid.columns {
+        write!(out, "<th>{}</th>", escape(name)).unwrap();
+    }
+    out.push_str("</tr>\n");
+    for &(ref name, ref cells) in &grid.rows {
+        write!(out, "<tr><th>{}</th>", escape(name)).unwrap();
+        for cell in cells {
+            if cell.is_empty() {
+                out.push_str("<td></td>");
+                continue;
+            }
+            let title = cell.iter().map(|&(r, ref rule)| format!("{}: {}", r.0, rule)).collect::<Vec<_>>().join("\n");
+            if cell.len() > 1 {
+                out.push_str("<td class=\"conflict\" style=\"background: #fcc\"");
+            } else {
+                out.push_str("<td");
+            }
+            write!(out, " title=\"{}\">{}</td>", escape(&title), cell_text(cell)).unwrap();
+        }
+        out.push_str("</tr>\n");
+    }
+    out.push_str("</table>\n");
+    out
+}
+
+fn csv(grid: &Grid) -> String {
+    let field = |s: &str| if s.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') || s.trim() != s {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    };
+    let mut lines = Vec::new();
+    let header: Vec<String> = Some(String::new()).into_iter().chain(grid.columns.iter().map(|c| field(c))).collect();
+    lines.push(header.join(","));
+    for &(ref name, ref cells) in &grid.rows {
+        let row: Vec<String> = Some(field(name)).into_iter().chain(cells.iter().map(|c| cell_text(c))).collect();
+        lines.push(row.join(","));
+    }
+    lines.join("\r\n") + "\r\n"
+}
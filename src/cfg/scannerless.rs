@@ -0,0 +1,194 @@
+//! Scannerless LL(1) parsing.
+//!
+//! In a scannerless grammar, every terminal is a class of characters, registered with `add_class`,
+//! and the parser consumes a `&str` directly. The classes may overlap and may be as large as all of
+//! Unicode, so FIRST and FOLLOW are taken as sets of characters rather than of terminals: two
+//! rules conflict when the characters predicting them intersect.
+//!
+//! The table is indexed by the ranges of a partition of the characters used by the grammar, such
+//! that every class is a union of them, plus a column for the end of input.
+
+use std::collections::HashMap;
+use cfg::{Cfg, Rule, EPSILON, END_OF_INPUT, Frozen, Mutable, Symbol, PackedSymbol, SymbolTable};
+use cfg::util::{compute_follow, compute_first_of, Follow};
+use lex::CharSet;
+
+pub struct CharClasses;
+impl ::typemap::Key for CharClasses { type Value = HashMap<PackedSymbol, CharSet>; }
+
+/// Add a terminal matching any character of `class`, returning it.
+pub fn add_class(cfg: &mut Cfg<Mutable>, class: CharSet) -> Symbol {
+    let t = cfg.add_terminal();
+    cfg.mut_extra().entry::<CharClasses>().or_insert_with(HashMap::new).insert(t.into(), class);
+    t
+}
+
+/// The terminal matching exactly the characters of `class`. If there is none, one is added and
+/// named `name`.
+pub fn class_terminal(cfg: &mut Cfg<Mutable>, class: CharSet, name: &str) -> Symbol {
+    let existing = cfg.extra().get::<CharClasses>()
+        .and_then(|c| c.iter().filter(|&(_, set)| *set == class).map(|(&t, _)| t).min());
+    match existing {
+        Some(t) => t.into(),
+        None => {
+            let t = add_class(cfg, class);
+            cfg.set_name(t, name);
+            t
+        },
+    }
+}
+
+/// The characters of a set of terminals, and whether it contains `END_OF_INPUT`.
+///
+/// ɛ is ignored. Panics if a terminal has no character class.
+pub fn chars_of<'a, T, I>(cfg: &Cfg<T>, terminals: I) -> (CharSet, bool) where I: IntoIterator<Item=&'a PackedSymbol> {
+    let classes = cfg.extra().get::<CharClasses>();
+    let mut set = CharSet::new();
+    let mut end = false;
+    for &t in terminals {
+        if t == END_OF_INPUT {
+            end = true;
+        } else if t != EPSILON {
+            match classes.and_then(|c| c.get(&t)) {
+                Some(class) => set = set.union(class),
+                None => panic!("{} has no character class", cfg.display_symbol(t)),
+            }
+        }
+    }
+    (set, end)
+}
+
+/// Computes FIRST of a sequence as a set of characters, and whether the sequence is nullable.
+pub fn compute_first_chars<'a, T, R>(cfg: &Cfg<T>, seq: &'a [R]) -> (CharSet, bool) where PackedSymbol: From<&'a R> {
+    let first = compute_first_of(cfg, seq);
+    let nullable = seq.is_empty() || first.contains(&EPSILON);
+    (chars_of(cfg, &first).0, nullable)
+}
+
+/// Computes FOLLOW of each nonterminal as a set of characters, and whether the end of input may
+/// follow it.
+pub fn compute_follow_chars(cfg: &mut Cfg<Frozen>) -> Vec<(CharSet, bool)> {
+    compute_follow(cfg);
+    let follow = cfg.extra().get::<Follow>().unwrap();
+    follow.iter().map(|f| chars_of(cfg, f)).collect()
+}
+
+pub struct Table {
+    pub start: Symbol,
+    pub rules: Vec<(PackedSymbol, Vec<PackedSymbol>)>,
+    pub names: SymbolTable,
+    classes: HashMap<PackedSymbol, CharSet>,
+    ranges: Vec<(char, char)>,
+    // One column per range, and a last one for the end of input.
+    table: Vec<Vec<Option<Rule>>>,
+}
+
+impl Table {
+    /// The column of the table for lookahead `c`, if any.
+    fn column(&self, c: Option<char>) -> Option<usize> {
+        let c = match c {
+            Some(c) => c,
+            None => return Some(self.ranges.len()),
+        };
+        match self.ranges.binary_search_by(|&(lo, _)| lo.cmp(&c)) {
+            Ok(i) => Some(i),
+            Err(0) => None,
+            Err(i) => if self.ranges[i - 1].1 >= c { Some(i - 1) } else { None },
+        }
+    }
+
+    /// The rule to expand `nonterminal` with on lookahead `c`, where `None` is the end of input.
+    pub fn entry<S>(&self, nonterminal: S, c: Option<char>) -> Option<Rule> where PackedSymbol: From<S> {
+        let row = &self.table[PackedSymbol::from(nonterminal).to_index()];
+        self.column(c).and_then(|col| row[col])
+    }
+}
+
+// Split the characters of `sets` into disjoint ranges, such that each set is a union of them.
+fn partition(sets: &[&CharSet]) -> Vec<(char, char)> {
+    let mut bounds = Vec::new();
+    for set in sets {
+        for &(lo, hi) in set.ranges() {
+            bounds.push(lo as u32);
+            bounds.push(hi as u32 + 1);
+        }
+    }
+    bounds.sort();
+    bounds.dedup();
+    let used = sets.iter().fold(CharSet::new(), |acc, set| acc.union(set));
+    bounds.windows(2)
+        .filter_map(|w| ::std::char::from_u32(w[0]).map(|lo| (lo, w[1] - 1)))
+        .filter(|&(lo, _)| used.contains(lo))
+        // A range can only end among the surrogates if the next one starts right after them.
+        .map(|(lo, hi)| (lo, ::std::char::from_u32(if hi >= 0xD800 && hi <= 0xDFFF { 0xD7FF } else { hi }).unwrap()))
+        .collect()
+}
+
+pub fn generate_table(cfg: &mut Cfg<Frozen>) -> Table {
+    let follow = compute_follow_chars(cfg);
+    let classes = cfg.extra().get::<CharClasses>().cloned().unwrap_or(HashMap::new());
+
+    // The characters and end of input predicting each rule.
+    let predict = cfg.rules().map(|&(lhs, ref rhs)| {
+        let (mut first, nullable) = compute_first_chars(cfg, rhs);
+        let mut end = false;
+        if nullable {
+            first = first.union(&follow[lhs.to_index()].0);
+            end = follow[lhs.to_index()].1;
+        }
+        (lhs, first, end)
+    }).collect::<Vec<_>>();
+
+    let ranges = partition(&classes.values().collect::<Vec<_>>());
+    let mut table = vec![vec![None; ranges.len() + 1]; cfg.max_nonterm as usize];
+    for (i, &(lhs, ref chars, end)) in predict.iter().enumerate() {
+        let row = &mut table[lhs.to_index()];
+        let mut cols = ranges.iter().enumerate()
+            .filter(|&(_, &(lo, _))| chars.contains(lo))
+            .map(|(col, _)| col)
+            .collect::<Vec<_>>();
+        if end {
+            cols.push(ranges.len());
+        }
+        for col in cols {
+            if row[col].is_some() {
+                panic!("Conflict in LL(1) table generation! Sorry boss.");
+            }
+            row[col] = Some(Rule(i));
+        }
+    }
+
+    Table { start: cfg.rules[cfg.start].0.into(), rules: cfg.rules.clone(), names: cfg.names.clone(), classes: classes, ranges: ranges, table: table }
+}
+
+/// Parse a string, returning the rules applied to derive it.
+pub fn parse_str(tab: &Table, s: &str) -> Vec<Rule> {
+    let mut derivation = Vec::new();
+    let mut stack = vec![tab.start];
+    let mut chars = s.chars().peekable();
+    while let Some(top) = stack.pop() {
+        let a = chars.peek().cloned();
+        if top == EPSILON.into() {
+            continue;
+        } else if top.is_terminal() {
+            match a {
+                Some(c) if tab.classes[&PackedSymbol::from(top)].contains(c) => { chars.next(); },
+                _ => panic!("Parse error! Expected {}, found {:?}.", tab.names.display(top), a),
+            }
+        } else {
+            match tab.entry(top, a) {
+                Some(rule) => {
+                    derivation.push(rule);
+                    for sym in tab.rules[rule.0].1.iter().rev() {
+                        stack.push(sym.into());
+                    }
+                },
+                None => panic!("Parse error! Found error entry in table."),
+            }
+        }
+    }
+    if let Some(c) = chars.next() {
+        panic!("Parse error! Expected end of input, found {:?}.", c);
+    }
+    derivation
+}
@@ -0,0 +1,64 @@
+//! Shortest example sentences.
+//!
+//! `nonterminals` gives a shortest terminal string each nonterminal derives, and `rules` one
+//! that each rule derives, with their leftmost derivations. They're built from the rules that
+//! `util::compute_min_yield` chooses with Knuth's generalization of Dijkstra's algorithm. These
+//! make examples for error messages ("expected something like `( a )`"), fallbacks when fuzzing,
+//! and documentation.
+
+use cfg::{Cfg, Rule, Symbol, EPSILON};
+use cfg::util::compute_min_yield;
+
+/// A terminal string and a leftmost derivation of it.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Example {
+    pub sentence: Vec<Symbol>,
+    pub derivation: Vec<Rule>,
+}
+
+impl Example {
+    /// The sentence, with the symbols named as in `cfg`.
+    pub fn display<T>(&self, cfg: &Cfg<T>) -> String {
+        let words: Vec<String> = self.sentence.iter().map(|&s| cfg.display_symbol(s).to_string()).collect();
+        words.join(" ")
+    }
+}
+
+/// Expand `rule` and then each nonterminal by its rule in `min`, leftmost first.
+fn expand<T>(cfg: &Cfg<T>, min: &[Option<(usize, Rule)>], rule: Rule, example: &mut Example) {
+    example.derivation.push(rule);
+    for &sym in cfg.get_rule(rule).unwrap().1 {
+        if sym == EPSILON {
+            continue;
+        }
+        if sym.is_terminal() {
+            example.sentence.push(sym.into());
+        } else {
+            expand(cfg, min, min[sym.to_index()].unwrap().1, example);
+        }
+    }
+}
+
+/// A shortest example of each rule, by index, or None for rules that derive no terminal string.
+pub fn rules<T>(cfg: &Cfg<T>) -> Vec<Option<Example>> {
+    let min = compute_min_yield(cfg);
+    cfg.rules().enumerate().map(|(i, &(_, ref rhs))| {
+        if rhs.iter().any(|&s| s.is_nonterminal() && min[s.to_index()].is_none()) {
+            return None;
+        }
+        let mut example = Example { sentence: Vec::new(), derivation: Vec::new() };
+        expand(cfg, &min, Rule(i), &mut example);
+        Some(example)
+    }).collect()
+}
+
+/// A shortest example of each nonterminal, by index, or None for nonterminals that derive no
+/// terminal string.
+pub fn nonterminals<T>(cfg: &Cfg<T>) -> Vec<Option<Example>> {
+    let min = compute_min_yield(cfg);
+    min.iter().map(|m| m.map(|(_, rule)| {
+        let mut example = Example { sentence: Vec::new(), derivation: Vec::new() };
+        expand(cfg, &min, rule, &mut example);
+        example
+    })).collect()
+}
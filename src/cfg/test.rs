@@ -1,7 +1,7 @@
 #![cfg(test)]
 
-use cfg::{ll1, Symbol, Cfg, EPSILON, END_OF_INPUT, Token, Rule};
-use cfg::util::{compute_first_of, Follow, compute_follow};
+use cfg::{abnf, bnf, codegen, ebnf, export, compress, railroad, render, tree, dot, ambiguity, counterexample, generate, shortest, ll1, lr, prec, pratt, scannerless, w3c, yacc, Symbol, Cfg, EPSILON, END_OF_INPUT, Token, Rule};
+use cfg::util::{compute_first, compute_first_of, First, Follow, compute_follow};
 
 use cfg::bnf::{from_str, to_string};
 
@@ -37,6 +37,11 @@ fn first_is_correct() {
     //assert_eq!(compute_first_of(&mut cfg, &[c]), vec![].into_iter().collect());
     assert_eq!(compute_first_of(&mut cfg, &[a, c]), vec![EPSILON, d.into(), f.into()].into_iter().collect());
     assert!(compute_first_of::<_, Symbol>(&mut cfg, &[]).is_empty());
+
+    let mut cfg = cfg.freeze();
+    compute_first(&mut cfg);
+    assert_eq!(cfg.extra().get::<First>().unwrap()[&s.into()], vec![d.into(), e.into()].into_iter().collect());
+    assert_eq!(compute_first_of(&cfg, &[a, c]), vec![EPSILON, d.into(), f.into()].into_iter().collect());
 }
 
 #[test]
@@ -48,12 +53,102 @@ B e
 C
 C f";
     let c = from_str(input);
-    assert_eq!("S -> ABC
-A -> ε
-A -> d
-B -> e
-C -> ε
-C -> f", to_string(&c));
+    assert_eq!("S -> A B C
+A -> ε | 'd'
+B -> 'e'
+C -> ε | 'f'", to_string(&c));
+
+    // The old names are still filled in for code that reads them.
+    #[allow(deprecated)]
+    let names = c.extra().get::<bnf::BNFName>().unwrap();
+    assert_eq!(names[&c.symbol_by_name("A").unwrap()], 'A');
+    assert_eq!(names[&c.symbol_by_name("f").unwrap()], 'f');
+}
+
+#[test]
+fn bnf_parse_round_trips() {
+    let input = "# Sums of numbers.
+expr ::= term '+' expr | term
+term -> NUM
+      | \"(\" expr ')'   # parenthesized
+      | <empty term>
+<empty term> -> ε
+opt -> | 'it''s'";
+    let c = bnf::parse(input).unwrap();
+    let expr = c.symbol_by_name("expr").unwrap();
+    let num = c.symbol_by_name("NUM").unwrap();
+    assert_eq!(c.get_rule(Rule(0)).unwrap().0, expr.into());
+    assert!(num.is_terminal());
+    assert!(c.symbol_by_name("empty term").unwrap().is_nonterminal());
+    assert!(c.symbol_by_name("it").unwrap().is_terminal());
+
+    let out = to_string(&c);
+    assert_eq!(out, "expr -> term '+' expr | term
+term -> 'NUM' | '(' expr ')' | <empty term>
+<empty term> -> ε
+opt -> ε | 'it' 's'");
+    assert_eq!(to_string(&bnf::parse(&out).unwrap()), out);
+}
+
+#[test]
+fn bnf_round_trips_nonterminals_without_rules() {
+    let mut cfg = Cfg::new();
+    let s = cfg.add_named_nonterminal("S");
+    let a = cfg.add_named_nonterminal("A");
+    let x = cfg.add_named_terminal("x");
+    cfg.add_rule(s, &[a, x]);
+
+    let out = to_string(&cfg);
+    assert_eq!(out, "%nonterminal A\nS -> A 'x'");
+    let c = bnf::parse(&out).unwrap();
+    assert!(c.symbol_by_name("A").unwrap().is_nonterminal());
+    assert_eq!(to_string(&c), out);
+}
+
+#[test]
+fn bnf_round_trips_names_with_closing_brackets() {
+    let mut cfg = Cfg::new();
+    let s = cfg.add_named_nonterminal("a -> b");
+    let t = cfg.add_named_nonterminal("\\>");
+    let x = cfg.add_named_terminal("'>'");
+    cfg.add_rule(s, &[t]);
+    cfg.add_rule(t, &[x]);
+
+    let out = to_string(&cfg);
+    assert_eq!(out, "<a -\\> b> -> <\\\\\\>>\n<\\\\\\>> -> '\\'>\\''");
+    let c = bnf::parse(&out).unwrap();
+    assert!(c.symbol_by_name("a -> b").unwrap().is_nonterminal());
+    assert!(c.symbol_by_name("\\>").unwrap().is_nonterminal());
+    assert_eq!(to_string(&c), out);
+}
+
+#[test]
+fn bnf_round_trips_the_start_rule() {
+    let mut cfg = Cfg::new();
+    let a = cfg.add_named_nonterminal("A");
+    let s = cfg.add_named_nonterminal("S");
+    let x = cfg.add_named_terminal("x");
+    cfg.add_rule(a, &[x]);
+    let start = cfg.add_rule(s, &[a, a]);
+    cfg.set_start(start);
+
+    let out = to_string(&cfg);
+    assert_eq!(out, "%start S\nA -> 'x'\nS -> A A");
+    let c = bnf::parse(&out).unwrap();
+    assert_eq!(c.get_rule(Rule(c.start)).unwrap().0, c.symbol_by_name("S").unwrap().into());
+    assert_eq!(to_string(&c), out);
+}
+
+#[test]
+fn bnf_parse_reports_errors() {
+    use cfg::bnf::{Error, Position};
+    assert_eq!(bnf::parse("a -> 'b\nc").err(), Some(Error::Unterminated(Position { line: 1, column: 6 })));
+    assert_eq!(bnf::parse("a ->\n  -> c").err(), Some(Error::Expected(Position { line: 2, column: 3 }, "a symbol")));
+    assert_eq!(bnf::parse("a -> b ; c").err(), Some(Error::UnexpectedChar(Position { line: 1, column: 8 }, ';')));
+    assert_eq!(bnf::parse("a -> 'a'").err(), Some(Error::NameClash(Position { line: 1, column: 6 }, "a".to_string())));
+    assert_eq!(bnf::parse("# nothing").err(), Some(Error::NoRules));
+    assert_eq!(bnf::parse("%begin a\na -> b").err(), Some(Error::UnknownDirective(Position { line: 1, column: 1 }, "begin".to_string())));
+    assert_eq!(bnf::parse("%start b\na -> b").err(), Some(Error::Expected(Position { line: 1, column: 8 }, "a nonterminal with rules")));
 }
 
 #[test]
@@ -159,3 +254,956 @@ fn can_parse_ll1_string() {
     let derivation = ll1::parse(&tab, vec![&e as &Token]);
     assert_eq!(derivation, vec![Rule(0), Rule(1), Rule(3), Rule(4)]);
 }
+
+#[test]
+#[should_panic(expected = "Found error entry in table")]
+fn ll1_parse_rejects_tokens_after_nullable_tail() {
+    let mut cfg = Cfg::new();
+    let s = cfg.add_nonterminal();
+    let c = cfg.add_nonterminal();
+    let e = cfg.add_terminal();
+    let f = cfg.add_terminal();
+
+    // S -> e C
+    // C -> EPSILON | f
+
+    cfg.add_rule(s, &[e, c]);
+    cfg.add_rule(c, &[EPSILON]);
+    cfg.add_rule(c, &[f]);
+
+    let mut cfg = cfg.freeze();
+    let tab = ll1::generate_table(&mut cfg);
+    ll1::parse(&tab, vec![&e as &Token, &e]);
+}
+
+fn ambiguous_expression_grammar() -> (Cfg<::cfg::Mutable>, Symbol, Symbol, Symbol) {
+    let mut cfg = Cfg::new();
+    let e = cfg.add_nonterminal();
+    let plus = cfg.add_terminal();
+    let times = cfg.add_terminal();
+    let n = cfg.add_terminal();
+
+    // E -> E + E | E * E | n
+
+    let start = cfg.add_rule(e, &[e, plus, e]);
+    cfg.add_rule(e, &[e, times, e]);
+    cfg.add_rule(e, &[n]);
+    cfg.set_start(start);
+    (cfg, plus, times, n)
+}
+
+#[test]
+fn precedence_resolves_lr_conflicts() {
+    let (mut cfg, plus, times, n) = ambiguous_expression_grammar();
+    prec::declare(&mut cfg, prec::Assoc::Left, &[plus]);
+    prec::declare(&mut cfg, prec::Assoc::Left, &[times]);
+
+    let mut cfg = cfg.freeze();
+    let tab = lr::generate_table(&mut cfg);
+    assert!(tab.conflicts.is_empty());
+
+    let derivation = lr::parse(&tab, vec![&n as &Token, &plus, &n, &times, &n]);
+    assert_eq!(derivation, vec![Rule(2), Rule(2), Rule(2), Rule(1), Rule(0)]);
+    let derivation = lr::parse(&tab, vec![&n as &Token, &plus, &n, &plus, &n]);
+    assert_eq!(derivation, vec![Rule(2), Rule(2), Rule(0), Rule(2), Rule(0)]);
+}
+
+#[test]
+fn unresolved_lr_conflicts_are_reported() {
+    let (mut cfg, plus, times, _) = ambiguous_expression_grammar();
+    prec::declare(&mut cfg, prec::Assoc::Nonassoc, &[plus]);
+
+    let mut cfg = cfg.freeze();
+    let tab = lr::generate_table(&mut cfg);
+    // `E + E . *` and `E * E . +` and `E * E . *` are unresolved; `E + E . +` is an error.
+    assert_eq!(tab.conflicts.len(), 3);
+    let conflict = tab.conflicts.iter().find(|c| c.terminal == times && match c.kind {
+        lr::ConflictKind::ShiftReduce { reduce, .. } => reduce == Rule(0),
+        _ => false,
+    }).unwrap();
+    assert_eq!(conflict.reason, Some(prec::Unresolved::TerminalHasNoPrecedence(times)));
+}
+
+#[test]
+fn nonassoc_errors_are_not_overwritten() {
+    let mut cfg = Cfg::new();
+    let e = cfg.add_nonterminal();
+    let x = cfg.add_nonterminal();
+    let lt = cfg.add_terminal();
+    let n = cfg.add_terminal();
+
+    // E -> E < E | X | n
+    // X -> E < E
+
+    let start = cfg.add_rule(e, &[e, lt, e]);
+    cfg.add_rule(e, &[x]);
+    cfg.add_rule(e, &[n]);
+    cfg.add_rule(x, &[e, lt, e]);
+    cfg.set_start(start);
+    prec::declare(&mut cfg, prec::Assoc::Nonassoc, &[lt]);
+
+    let mut cfg = cfg.freeze();
+    let tab = lr::generate_table(&mut cfg);
+    // After `E < E`, both rules reduce on `<`, and both are errors.
+    let state = (0..tab.num_states()).find(|&s| tab.action(s, END_OF_INPUT) == Some(lr::Action::Reduce(Rule(0)))).unwrap();
+    assert_eq!(tab.action(state, lt), None);
+}
+
+#[test]
+fn can_parse_pratt_expression() {
+    let mut cfg = Cfg::new();
+    let atom = cfg.add_nonterminal();
+    let n = cfg.add_terminal();
+    let plus = cfg.add_terminal();
+    let times = cfg.add_terminal();
+    let minus = cfg.add_terminal();
+    let bang = cfg.add_terminal();
+    let open = cfg.add_terminal();
+    let close = cfg.add_terminal();
+
+    // A -> n
+
+    cfg.add_rule(atom, &[n]);
+
+    let mut cfg = cfg.freeze();
+    let tab = ll1::generate_table(&mut cfg);
+    let mut ops = pratt::Operators::new();
+    ops.infix(plus, 1, 2).infix(times, 3, 4).prefix(minus, 5).postfix(bang, 7).group(open, close);
+
+    use cfg::pratt::Tree::*;
+    let leaf = || Box::new(Atom(vec![Rule(0)]));
+    let tree = pratt::parse(&tab, atom, &ops, vec![&minus as &Token, &n, &plus, &n, &times, &n, &bang]);
+    assert_eq!(tree, Infix(Box::new(Prefix(minus, leaf())), plus, Box::new(Infix(leaf(), times, Box::new(Postfix(leaf(), bang))))));
+
+    let tree = pratt::parse(&tab, atom, &ops, vec![&open as &Token, &n, &plus, &n, &close, &times, &n]);
+    assert_eq!(tree, Infix(Box::new(Infix(leaf(), plus, leaf())), times, leaf()));
+}
+
+#[test]
+fn pratt_atoms_can_end_in_nullable_nonterminals() {
+    let mut cfg = Cfg::new();
+    let atom = cfg.add_nonterminal();
+    let b = cfg.add_nonterminal();
+    let n = cfg.add_terminal();
+    let dot = cfg.add_terminal();
+    let plus = cfg.add_terminal();
+
+    // A -> n B
+    // B -> ε | . n
+
+    cfg.add_rule(atom, &[n, b]);
+    cfg.add_rule(b, &[EPSILON]);
+    cfg.add_rule(b, &[dot, n]);
+
+    let mut cfg = cfg.freeze();
+    let tab = ll1::generate_table(&mut cfg);
+    let mut ops = pratt::Operators::new();
+    ops.infix(plus, 1, 2);
+
+    use cfg::pratt::Tree::*;
+    let tree = pratt::parse(&tab, atom, &ops, vec![&n as &Token, &plus, &n, &dot, &n]);
+    assert_eq!(tree, Infix(Box::new(Atom(vec![Rule(0), Rule(1)])), plus, Box::new(Atom(vec![Rule(0), Rule(2)]))));
+}
+
+#[test]
+fn can_parse_scannerless_string() {
+    use lex::CharSet;
+
+    let mut cfg = Cfg::new();
+    let s = cfg.add_nonterminal();
+    let r = cfg.add_nonterminal();
+    let letter = scannerless::add_class(&mut cfg, CharSet::from_ranges(&[('a', 'z'), ('α', 'ω')]));
+    let digit = scannerless::add_class(&mut cfg, CharSet::range('0', '9'));
+
+    // S -> letter R
+    // R -> letter R | digit R | EPSILON
+
+    cfg.add_rule(s, &[letter, r]);
+    cfg.add_rule(r, &[letter, r]);
+    cfg.add_rule(r, &[digit, r]);
+    cfg.add_rule(r, &[EPSILON]);
+
+    let mut ok = cfg.freeze();
+    let tab = scannerless::generate_table(&mut ok);
+    assert_eq!(tab.entry(r, Some('β')), Some(Rule(1)));
+    assert_eq!(tab.entry(r, None), Some(Rule(3)));
+    assert_eq!(scannerless::parse_str(&tab, "aβ1"), vec![Rule(0), Rule(1), Rule(2), Rule(3)]);
+}
+
+#[test]
+#[should_panic(expected = "Conflict")]
+fn overlapping_classes_conflict() {
+    use lex::CharSet;
+
+    let mut cfg = Cfg::new();
+    let s = cfg.add_nonterminal();
+    let letter = scannerless::add_class(&mut cfg, CharSet::range('a', 'z'));
+    let x = scannerless::add_class(&mut cfg, CharSet::single('x'));
+
+    // S -> letter | x
+
+    cfg.add_rule(s, &[letter]);
+    cfg.add_rule(s, &[x]);
+
+    scannerless::generate_table(&mut cfg.freeze());
+}
+
+#[test]
+fn can_generate_recursive_descent_parser() {
+    let mut cfg = Cfg::new();
+    let s = cfg.add_nonterminal();
+    let a = cfg.add_nonterminal();
+    let b = cfg.add_nonterminal();
+    let c = cfg.add_nonterminal();
+
+    let d = cfg.add_terminal();
+    let e = cfg.add_terminal();
+    let f = cfg.add_terminal();
+
+    // S -> A B C
+    // A -> EPSILON | d
+    // B -> e
+    // C -> EPSILON | f
+
+    cfg.add_rule(s, &[a, b, c]);
+    cfg.add_rule(a, &[EPSILON]);
+    cfg.add_rule(a, &[d]);
+    cfg.add_rule(b, &[e]);
+    cfg.add_rule(c, &[EPSILON]);
+    cfg.add_rule(c, &[f]);
+
+    let mut cfg = cfg.freeze();
+    let tab = ll1::generate_table(&mut cfg);
+    let mut out = Vec::new();
+    codegen::generate_parser(&cfg, &tab, &mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.contains("    p.nonterminal_0();\n    p.expect(0);\n"));
+    assert!(out.contains("&[\"$\", \"ε\", \"Terminal(2)\", \"Terminal(3)\", \"Terminal(4)\"];"));
+    assert!(out.contains("
+    fn nonterminal_1(&mut self) {
+        match self.peek() {
+            // Nonterminal(1) -> ε
+            3 => {
+                self.callbacks.rule(Rule(1));
+            },
+            // Nonterminal(1) -> Terminal(2)
+            2 => {
+                self.callbacks.rule(Rule(2));
+                self.expect(2);
+            },
+            t => panic!(\"Parse error! Found error entry in table for {} on {}.\", \"Nonterminal(1)\", TERMINAL_NAMES[t]),
+        }
+    }
+"));
+}
+
+#[test]
+fn can_parse_with_static_table() {
+    let mut cfg = Cfg::new();
+    let s = cfg.add_nonterminal();
+    let a = cfg.add_nonterminal();
+    let d = cfg.add_terminal();
+    let e = cfg.add_terminal();
+
+    // S -> A e
+    // A -> EPSILON | d
+
+    cfg.add_rule(s, &[a, e]);
+    cfg.add_rule(a, &[EPSILON]);
+    cfg.add_rule(a, &[d]);
+
+    let mut cfg = cfg.freeze();
+    let tab = ll1::generate_table(&mut cfg);
+    let mut out = Vec::new();
+    ll1::write_static(&tab, &mut out).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "// Generated by pct. Do not edit.
+::pct::cfg::ll1::StaticTable {
+    start: 2147483648,
+    rules: &[
+        (2147483648, &[2147483649, 3]),
+        (2147483649, &[1]),
+        (2147483649, &[2]),
+    ],
+    table: &[
+        &[None, None, Some(0), Some(0)],
+        &[None, Some(1), Some(2), Some(1)],
+    ],
+}
+");
+
+    // What the above would be included as.
+    let table = ll1::StaticTable {
+        start: 2147483648,
+        rules: &[
+            (2147483648, &[2147483649, 3]),
+            (2147483649, &[1]),
+            (2147483649, &[2]),
+        ],
+        table: &[
+            &[None, None, Some(0), Some(0)],
+            &[None, Some(1), Some(2), Some(1)],
+        ],
+    };
+    let mut stack = [0; 8];
+    let mut derivation = Vec::new();
+    ll1::parse_static(&table, &[&d, &e], &mut stack, |r| derivation.push(r));
+    assert_eq!(derivation, ll1::parse(&tab, vec![&d as &Token, &e]));
+
+    // Tokens after a complete parse are an error, as with `parse`.
+    let trailing = ::std::panic::catch_unwind(::std::panic::AssertUnwindSafe(|| {
+        ll1::parse_static(&table, &[&d, &e, &e], &mut stack, |_| ());
+    }));
+    assert!(trailing.is_err());
+}
+
+#[test]
+fn compressed_table_matches_dense_table() {
+    use cfg::ll1::ParseTable;
+
+    let mut cfg = Cfg::new();
+    let s = cfg.add_nonterminal();
+    let a = cfg.add_nonterminal();
+    let b = cfg.add_nonterminal();
+    let c = cfg.add_nonterminal();
+
+    let d = cfg.add_terminal();
+    let e = cfg.add_terminal();
+    let f = cfg.add_terminal();
+    let _g = cfg.add_terminal();
+    let _h = cfg.add_terminal();
+
+    // S -> A B C
+    // A -> EPSILON | d
+    // B -> e
+    // C -> EPSILON | f
+
+    cfg.add_rule(s, &[a, b, c]);
+    cfg.add_rule(a, &[EPSILON]);
+    cfg.add_rule(a, &[d]);
+    cfg.add_rule(b, &[e]);
+    cfg.add_rule(c, &[EPSILON]);
+    cfg.add_rule(c, &[f]);
+
+    let mut cfg = cfg.freeze();
+    let tab = ll1::generate_table(&mut cfg);
+    let small = compress::compress(&tab);
+    for nt in 0..tab.num_nonterminals() as usize {
+        for t in 0..tab.num_terminals() as usize {
+            assert_eq!(small.lookup(nt, t), tab.lookup(nt, t));
+        }
+    }
+    // The unused `g` and `h` share a class, and the 28 cells pack into fewer slots.
+    assert_eq!(small.num_classes(), 6);
+    assert_eq!(small.num_rows(), 4);
+    assert!(small.packed_len() < 28);
+    assert_eq!(ll1::parse(&small, vec![&d as &Token, &e, &f]), ll1::parse(&tab, vec![&d as &Token, &e, &f]));
+}
+
+#[test]
+fn symbols_are_named() {
+    let mut cfg = Cfg::new();
+    let expr = cfg.add_named_nonterminal("expr");
+    let atom = cfg.add_nonterminal();
+    let num = cfg.add_named_terminal("num");
+    let plus = cfg.add_named_terminal("+");
+    cfg.set_name(atom, "atom");
+
+    // expr -> atom + atom
+    // atom -> num
+
+    let r = cfg.add_rule(expr, &[atom, plus, atom]);
+    cfg.add_rule(atom, &[num]);
+
+    assert_eq!(cfg.symbol_by_name("expr"), Some(expr));
+    assert_eq!(cfg.symbol_by_name("+"), Some(plus));
+    assert_eq!(cfg.symbol_by_name("nope"), None);
+    assert_eq!(cfg.symbol_name(atom), Some("atom"));
+    assert_eq!(cfg.display_rule(r).to_string(), "expr -> atom + atom");
+    assert_eq!(cfg.display_symbol(END_OF_INPUT).to_string(), "$");
+    assert_eq!(cfg.try_add_named_terminal("atom"), Err(atom));
+    assert_eq!(cfg.try_set_name(num, "expr"), Err(expr));
+    assert_eq!(cfg.symbol_name(num), Some("num"));
+
+    let mut cfg = cfg.freeze();
+    let tab = ll1::generate_table(&mut cfg);
+    let debug = format!("{:?}", tab);
+    assert!(debug.contains("expr"));
+    assert!(debug.contains("num"));
+}
+
+#[test]
+fn ebnf_is_desugared() {
+    let input = "List ::= Item (',' Item)*
+Item -> x [ '!' ] | '(' { Item }+ ')'";
+    let c = bnf::parse(input).unwrap();
+    assert_eq!(to_string(&c), "List -> Item <List#1>
+<List#1> -> ',' Item <List#1> | ε
+Item -> 'x' <Item#1> | '(' <Item#2> ')'
+<Item#1> -> '!' | ε
+<Item#2> -> Item <Item#2> | ε");
+
+    let star = c.symbol_by_name("List#1").unwrap();
+    let origin = ebnf::origin(&c, star).unwrap();
+    assert_eq!(origin.construct, ebnf::Construct::Star);
+    assert_eq!(origin.describe(c.names()), "repetition `*` at 1:25 in the rule for List");
+
+    let c = bnf::parse("A ::= x+ (y? z?)*\nB ::= 'A#1'").unwrap();
+    assert_eq!(to_string(&c), "A -> 'x' <A#2> <A#3>
+<A#2> -> 'x' <A#2> | ε
+<A#3> -> 'y' <A#4> <A#3> | 'z' <A#3> | ε
+<A#4> -> 'z' | ε
+B -> 'A#1'");
+    assert_eq!(ebnf::origin(&c, c.symbol_by_name("A#2").unwrap()).unwrap().construct, ebnf::Construct::Plus);
+    assert!(c.symbol_by_name("A#1").unwrap().is_terminal());
+
+    // Alternatives left after removing ε from an option are spliced into the enclosing ones.
+    let c = bnf::parse("A -> ([x | z] | y)*").unwrap();
+    assert_eq!(to_string(&c), "A -> <A#1>\n<A#1> -> 'x' <A#1> | 'z' <A#1> | 'y' <A#1> | ε");
+    let c = bnf::parse("A -> (x | [y | z])+").unwrap();
+    assert_eq!(to_string(&c), "A -> <A#1>\n<A#1> -> 'x' <A#1> | 'y' <A#1> | 'z' <A#1> | ε");
+
+    let mut c = bnf::parse("List ::= Item (',' Item)*\nItem -> 'x'").unwrap().freeze();
+    let tab = ll1::generate_table(&mut c);
+    let (x, comma) = (c.symbol_by_name("x").unwrap(), c.symbol_by_name(",").unwrap());
+    assert_eq!(ll1::parse(&tab, vec![&x, &comma, &x]).len(), 5);
+}
+
+#[test]
+fn can_import_abnf() {
+    let input = "; A comma-separated list of tokens.
+list  = token *( \",\" token )
+token = 1*( ALPHA / DIGIT / %x2D-2E )
+token =/ \"#x\" %s\"Y\" 2*3SP
+        [ CRLF ]";
+    let c = abnf::parse(input).unwrap();
+    assert_eq!(to_string(&c), "list -> token <list#1>
+<list#1> -> '\",\"' token <list#1> | ε
+token -> <token#1> <token#2> | '\"#\"' '\"x\"' '%x59' 'SP' 'SP' <token#3> <token#4>
+<token#1> -> 'ALPHA' | 'DIGIT' | '%x2D-2E'
+<token#2> -> <token#1> <token#2> | ε
+<token#3> -> 'SP' | ε
+<token#4> -> CRLF | ε
+CRLF -> 'CR' 'LF'");
+
+    let mut c = c.freeze();
+    let tab = scannerless::generate_table(&mut c);
+    assert_eq!(scannerless::parse_str(&tab, "a-1,#XY  ,b").len(), 17);
+
+    use cfg::abnf::Error;
+    use cfg::bnf::Position;
+    assert_eq!(abnf::parse("a = <prose>").err(), Some(Error::Prose(Position { line: 1, column: 5 })));
+    assert_eq!(abnf::parse("a = b").err(), Some(Error::Undefined(Position { line: 1, column: 5 }, "b".to_string())));
+    assert_eq!(abnf::parse("a = 3*2\"x\"").err(), Some(Error::InvalidNumber(Position { line: 1, column: 5 })));
+    assert_eq!(abnf::parse("a = \"x\"\na = \"y\"").err(), Some(Error::Redefined(Position { line: 2, column: 1 }, "a".to_string())));
+    assert_eq!(abnf::parse("a = x\nx = %x41\nfoo\n").err(), Some(Error::Expected(Position { line: 3, column: 1 }, "`=` or `=/`")));
+    assert_eq!(abnf::parse("a = x\nx = %x41\nfoo bar\n").err(), Some(Error::Expected(Position { line: 3, column: 5 }, "`=` or `=/`")));
+}
+
+#[test]
+fn can_import_w3c_ebnf() {
+    let input = "/* Attribute values, simplified from XML 1.0. */
+AttValue  ::= '\"' ([^<&\"] | Reference)* '\"'   [ WFC: No < in Attribute Values ]
+Reference ::= '&#x' [0-9a-fA-F]+ ';' | #x3C";
+    let c = w3c::parse(input).unwrap();
+    assert_eq!(to_string(&c), "AttValue -> '\\'\"\\'' <AttValue#1> '\\'\"\\''
+<AttValue#1> -> '[^<&\"]' <AttValue#1> | Reference <AttValue#1> | ε
+Reference -> '\\'&\\'' '\\'#\\'' '\\'x\\'' '[0-9a-fA-F]' <Reference#1> '\\';\\'' | '#x3C'
+<Reference#1> -> '[0-9a-fA-F]' <Reference#1> | ε");
+
+    let mut c = c.freeze();
+    let tab = scannerless::generate_table(&mut c);
+    assert_eq!(scannerless::parse_str(&tab, "\"a&#x1F;<\"").len(), 9);
+
+    use cfg::w3c::Error;
+    use cfg::bnf::Position;
+    assert_eq!(w3c::parse("A ::= B* - 'x'\nB ::= 'x'").err(), Some(Error::Exclusion(Position { line: 1, column: 10 })));
+    assert_eq!(w3c::parse("A ::= B").err(), Some(Error::Undefined(Position { line: 1, column: 7 }, "B".to_string())));
+    assert_eq!(w3c::parse("A ::= [z-a]").err(), Some(Error::InvalidChar(Position { line: 1, column: 7 })));
+
+    let c = w3c::parse("doc-decl::=xml.Name_1 | ns:Name\nxml.Name_1 ::= 'x'\nns:Name ::= 'y'").unwrap();
+    assert_eq!(to_string(&c), "<doc-decl> -> <xml.Name_1> | <ns:Name>
+<xml.Name_1> -> '\\'x\\''
+<ns:Name> -> '\\'y\\''");
+}
+
+#[test]
+fn can_import_yacc() {
+    let input = "%{
+#include <stdio.h>
+%}
+%union { int n; }
+%token <n> NUM
+%left '+' '-'
+%left '*'
+%right UMINUS
+%start expr
+%%
+top  : { begin(); } expr { end($2); } ;
+expr : expr '+' expr   { $$ = $1 + $3; }
+     | expr '*' expr   { $$ = $1 * $3; /* } */ }
+     | '-' expr %prec UMINUS { $$ = -$2; }
+     | NUM
+%%
+int main() { return yyparse(); }";
+    let c = yacc::parse(input).unwrap();
+    assert_eq!(to_string(&c), "%start expr
+<$@1> -> ε
+top -> <$@1> expr
+expr -> expr '\\'+\\'' expr | expr '\\'*\\'' expr | '\\'-\\'' expr | 'NUM'");
+    assert_eq!(yacc::action(&c, Rule(0)), Some(" begin(); "));
+    assert_eq!(yacc::action(&c, Rule(1)), Some(" end($2); "));
+    assert_eq!(yacc::action(&c, Rule(3)), Some(" $$ = $1 * $3; /* } */ "));
+    assert_eq!(yacc::action(&c, Rule(5)), None);
+
+    let num = c.symbol_by_name("NUM").unwrap();
+    let minus = c.symbol_by_name("'-'").unwrap();
+    let times = c.symbol_by_name("'*'").unwrap();
+    let mut c = c.freeze();
+    let tab = lr::generate_table(&mut c);
+    assert!(tab.conflicts.is_empty());
+    let derivation = lr::parse(&tab, vec![&minus as &Token, &num, &times, &num]);
+    assert_eq!(derivation, vec![Rule(5), Rule(4), Rule(5), Rule(3)]);
+
+    use cfg::yacc::Error;
+    use cfg::bnf::Position;
+    assert_eq!(yacc::parse("%%\na : b ;").err(), Some(Error::Undefined(Position { line: 2, column: 5 }, "b".to_string())));
+    assert_eq!(yacc::parse("%token A\n%%\na : A %prec A ;").err(),
+               Some(Error::NoPrecedence(Position { line: 3, column: 13 }, "A".to_string())));
+    assert_eq!(yacc::parse("%%\na : { b ;").err(), Some(Error::Unterminated(Position { line: 2, column: 5 })));
+}
+
+#[test]
+fn can_export_grammars() {
+    let c = bnf::parse("List -> <the item> List | ε\n<the item> -> '+' | \"x'y\" | ident | grammar\ngrammar -> ε").unwrap();
+    assert_eq!(export::bison(&c), "%token X_Y \"x'y\"
+%token ident
+%start List
+
+%%
+
+List
+    : the_item List
+    | %empty
+    ;
+
+the_item
+    : '+'
+    | X_Y
+    | ident
+    | grammar
+    ;
+
+grammar
+    : %empty
+    ;
+
+%%
+");
+    assert_eq!(export::antlr(&c, "List"), "grammar List;
+
+tokens { Ident }
+
+list
+    : the_item list
+    | /* ε */
+    ;
+
+the_item
+    : '+'
+    | 'x\\'y'
+    | Ident
+    | grammar_
+    ;
+
+grammar_
+    : /* ε */
+    ;
+");
+    assert_eq!(export::lark(&c), "start: list
+
+list: the_item list
+    |
+
+the_item: \"+\"
+        | \"x'y\"
+        | IDENT
+        | grammar
+
+grammar:
+
+%declare IDENT
+");
+
+    // Precedence and actions survive a round trip through bison.
+    let input = "%token NUM
+%left '+'
+%right UMINUS
+%%
+expr : expr '+' expr { $$ = $1 + $3; }
+     | '-' expr %prec UMINUS
+     | NUM
+     ;";
+    let c = yacc::parse(input).unwrap();
+    let exported = export::bison(&c);
+    assert!(exported.contains("%left '+'\n%right UMINUS\n"));
+    let d = yacc::parse(&exported).unwrap();
+    assert_eq!(to_string(&d), to_string(&c));
+    assert_eq!(yacc::action(&d, Rule(0)), Some(" $$ = $1 + $3; "));
+    let uminus = d.symbol_by_name("UMINUS").unwrap();
+    let prec = d.extra().get::<prec::Precedence>().unwrap();
+    assert_eq!(prec.rule(&d, Rule(1)), prec.terminal(uminus));
+
+    let c = abnf::parse("id = ALPHA *(ALPHA / DIGIT / \"]\")").unwrap();
+    assert!(export::antlr(&c, "Id").ends_with("| ']' id_1\n    | /* ε */\n    ;\n\nALPHA : [A-Za-z] ;\n\nDIGIT : [0-9] ;\n"));
+    assert!(export::lark(&c).ends_with("ALPHA: /[A-Za-z]/\nDIGIT: /[0-9]/\n"));
+
+    // %prec names the rule's own terminal, not another one at its level.
+    let c = yacc::parse("%left '+' '-'\n%%\ne : e '+' e | '-' e %prec '-' | 'n' ;").unwrap();
+    assert!(export::bison(&c).contains("    | '-' e %prec '-'\n"));
+
+    // A nonterminal without rules derives nothing, like a token the lexer never returns.
+    let mut c = Cfg::new();
+    let s = c.add_named_nonterminal("S");
+    let a = c.add_named_nonterminal("A");
+    let x = c.add_named_terminal("x");
+    c.add_rule(s, &[a, x]);
+    assert_eq!(export::bison(&c), "%token x\n%token A\n%start S\n\n%%\n\nS\n    : A x\n    ;\n\n%%\n");
+    assert_eq!(export::antlr(&c, "S"), "grammar S;\n\ntokens { X, A }\n\ns\n    : A X\n    ;\n");
+    assert_eq!(export::lark(&c), "start: s\n\ns: A X\n\n%declare X A\n");
+
+    let c = bnf::parse("class -> if").unwrap();
+    assert_eq!(export::lark(&c), "start: class_\n\nclass_: IF\n\n%declare IF\n");
+}
+
+#[test]
+fn can_draw_railroad_diagrams() {
+    let c = bnf::parse("List -> '(' (Item (',' Item)*)? ')'\nItem -> 'a'+ | Name\nName -> 'x' Name | 'y' Name | 'z'").unwrap();
+    let html = railroad::html(&c, "Lists");
+    assert_eq!(html.matches("<h2 ").count(), 3);
+    assert!(html.contains("<h2 id=\"nt0\">List</h2>"));
+    assert!(!html.contains("List#1"));
+
+    // A loop is drawn back from right to left, under its body.
+    let loops = |svg: &str| svg.matches("h-").count();
+    let terminals = |svg: &str| svg.matches("class=\"terminal\"").count();
+    let list = railroad::svg(&c, c.symbol_by_name("List").unwrap());
+    assert_eq!((loops(&list), terminals(&list)), (1, 3));
+    assert_eq!(list.matches("<a href=\"#nt1\">").count(), 2);
+    // `'a'+` is drawn with one `a`, not as `a a*`.
+    let item = railroad::svg(&c, c.symbol_by_name("Item").unwrap());
+    assert_eq!((loops(&item), terminals(&item)), (1, 1));
+    // `Name -> x Name | y Name | z` is `(x | y)* z`.
+    let name = railroad::svg(&c, c.symbol_by_name("Name").unwrap());
+    assert_eq!((loops(&name), terminals(&name)), (1, 3));
+    assert!(!name.contains("nonterminal"));
+}
+
+#[test]
+fn can_render_ll1_tables() {
+    let mut cfg = bnf::from_str("S A B C\nA\nA d\nB e\nC\nC f").freeze();
+    let tab = ll1::generate_table(&mut cfg);
+    assert_eq!(render::ll1_table(&tab, render::Format::Text), "   $  d  e  f
+-------------
+S:    0  0
+A:    2  1
+B:       3
+C: 4        5
+");
+    assert_eq!(render::ll1_table(&tab, render::Format::Csv), ",$,d,e,f\r\nS,,0,0,\r\nA,,2,1,\r\nB,,,3,\r\nC,4,,,5\r\n");
+    assert!(format!("{:?}", tab).ends_with("S:    0  0\nA:    2  1\nB:       3\nC: 4        5\n"));
+
+    // `A -> ε | e` conflicts with `B -> e` after `A`.
+    let mut cfg = bnf::parse("S -> A B\nA -> ε | 'e'\nB -> 'e'").unwrap().freeze();
+    assert_eq!(render::ll1_grammar(&mut cfg, render::Format::Markdown), "|   | $ | e |
+|---|---|---|
+| S |   | 0 |
+| A |   | **1/2** |
+| B |   | 3 |
+");
+    let html = render::ll1_grammar(&mut cfg, render::Format::Html);
+    assert!(html.contains("<tr><th>A</th><td></td><td class=\"conflict\" style=\"background: #fcc\" title=\"1: A -&gt; ε\n2: A -&gt; e\">1/2</td></tr>"));
+}
+
+#[test]
+fn can_draw_dot_graphs() {
+    let c = bnf::parse("E -> E '+' T | T\nT -> T '*' F | F\nF -> '(' E ')' | n\nU -> n").unwrap();
+    let deps = dot::dependencies(&c);
+    // E, T and F are mutually recursive, and only E and T are left recursive.
+    assert!(deps.contains("subgraph cluster_0 {\nstyle=filled;\ncolor=\"#eeeeff\";\nn0 [label=\"E\", peripheries=2, color=red, fontcolor=red];\nn1 [label=\"T\", color=red, fontcolor=red];\nn2 [label=\"F\"];\n}\nn3 [label=\"U\"];\n"));
+    assert!(deps.contains("n0 -> n0 [color=red, penwidth=2];\nn0 -> n1;\nn1 -> n1 [color=red, penwidth=2];\nn1 -> n2;\nn2 -> n0;\n"));
+
+    let mut c = c.freeze();
+    let lr0 = dot::lr_automaton(&mut c);
+    assert_eq!(lr0.matches(" -> ").count(), 22);
+    assert!(lr0.contains("s1 [label=\"1\\nE → E • + T\\lE' → E •\\l\", peripheries=2];"));
+    assert!(!lr0.contains("color=red"));
+    let (cfg, _, _, _) = ambiguous_expression_grammar();
+    let lr0 = dot::lr_automaton(&mut cfg.freeze());
+    assert_eq!(lr0.matches("color=red").count(), 2);
+
+    let mut cfg = bnf::from_str("S A B C\nA\nA d\nB e\nC\nC f").freeze();
+    let ll = ll1::generate_table(&mut cfg);
+    let lr = lr::generate_table(&mut cfg);
+    let e = cfg.symbol_by_name("e").unwrap();
+    let t = tree::from_leftmost(&cfg, &ll1::parse(&ll, vec![&e as &Token]));
+    assert_eq!(t, tree::from_reductions(&cfg, &lr::parse(&lr, vec![&e as &Token])));
+    assert_eq!(t.leaves(), vec![e]);
+    let dot = dot::parse_tree(&cfg, &t);
+    assert!(dot.starts_with("digraph tree {\nordering=out;\nn0 [label=\"S\"];\nn1 [label=\"A\"];\nn2 [label=\"ε\", shape=plaintext];\n"));
+    assert!(dot.contains("n4 [label=\"e\", shape=box];\nn3 -> n4;\n"));
+}
+
+#[test]
+fn can_find_ambiguities() {
+    let (cfg, plus, _, n) = ambiguous_expression_grammar();
+    assert_eq!(ambiguity::find(&cfg, 4, 10), None);
+    let found = ambiguity::find(&cfg, 5, 3).unwrap();
+    assert_eq!(found.sentence, vec![n, plus, n, plus, n]);
+    assert!(found.first != found.second);
+    let (first, second) = (tree::from_leftmost(&cfg, &found.first), tree::from_leftmost(&cfg, &found.second));
+    assert!(first != second);
+    assert_eq!(first.leaves(), found.sentence);
+    assert_eq!(second.leaves(), found.sentence);
+    assert_eq!(found.first, vec![Rule(0), Rule(0), Rule(2), Rule(2), Rule(2)]);
+    assert_eq!(found.second, vec![Rule(0), Rule(2), Rule(0), Rule(2), Rule(2)]);
+    let named = bnf::parse("S -> S S | a").unwrap();
+    assert_eq!(ambiguity::find(&named, 3, 3).unwrap().describe(named.names()),
+               "`a a a` has two leftmost derivations: rules 0, 0, 1, 1, 1 and rules 0, 1, 0, 1, 1");
+
+    // Declaring precedence doesn't make the grammar unambiguous.
+    let (mut cfg, plus, times, _) = ambiguous_expression_grammar();
+    prec::declare(&mut cfg, prec::Assoc::Left, &[plus]);
+    prec::declare(&mut cfg, prec::Assoc::Left, &[times]);
+    let mut cfg = cfg.freeze();
+    assert_eq!(ambiguity::prove_unambiguous(&mut cfg), None);
+    assert!(lr::generate_table(&mut cfg).conflicts.is_empty());
+    match ambiguity::check(&mut cfg, 5, 3) {
+        ambiguity::Verdict::Ambiguous(a) => assert_eq!(a.sentence.len(), 5),
+        v => panic!("{:?}", v),
+    }
+
+    let mut ll = bnf::from_str("S A B C\nA\nA d\nB e\nC\nC f").freeze();
+    assert_eq!(ambiguity::check(&mut ll, 5, 5), ambiguity::Verdict::Unambiguous(ambiguity::Proof::Ll1));
+    let mut slr = bnf::parse("E -> E '+' T | T\nT -> T '*' F | F\nF -> '(' E ')' | n").unwrap().freeze();
+    assert_eq!(ambiguity::prove_unambiguous(&mut slr), Some(ambiguity::Proof::Slr1));
+    assert_eq!(ambiguity::find(&slr, 7, 8), None);
+
+    // Nothing reaches `A`, so its two ε-rules are never predicted.
+    let mut unused = bnf::parse("S -> a\nA -> ε | B\nB -> ε").unwrap().freeze();
+    assert_eq!(ambiguity::prove_unambiguous(&mut unused), Some(ambiguity::Proof::Ll1));
+    ll1::generate_table(&mut unused);
+}
+
+#[test]
+fn can_explain_ll1_conflicts() {
+    // After `x`, `A -> ε` is predicted for `e` because `B` follows, and so is `A -> e`.
+    let mut cfg = bnf::parse("S -> 'x' A B\nA -> ε | 'e'\nB -> C 'e'\nC -> ε").unwrap().freeze();
+    let conflicts = counterexample::ll1(&mut cfg);
+    assert_eq!(conflicts.len(), 1);
+    let (a, e) = (cfg.symbol_by_name("A").unwrap(), cfg.symbol_by_name("e").unwrap());
+    let c = &conflicts[0];
+    assert_eq!((c.nonterminal, c.terminal, c.position), (a, e, 1));
+    assert_eq!(c.context, vec![cfg.symbol_by_name("x").unwrap(), a, e]);
+    assert_eq!(c.steps, vec![(0, Rule(0)), (2, Rule(3)), (2, Rule(4))]);
+    assert_eq!(c.continuations[0].derivation, vec![Rule(1)]);
+    assert!(c.continuations[0].form.is_empty());
+    assert_eq!(c.continuations[1].form, vec![e]);
+    assert_eq!(c.describe(&cfg), "A has rules 1, 2 for e:
+S ⇒ x A B ⇒ x A C e ⇒ x A e
+rule 1: A ⇒ ε, followed by e
+rule 2: A ⇒ e
+");
+
+    // Left recursion conflicts on every terminal starting the nonterminal.
+    let mut cfg = bnf::parse("L -> L ',' I | I\nI -> 'a' | '(' L ')'").unwrap().freeze();
+    let conflicts = counterexample::ll1(&mut cfg);
+    assert_eq!(conflicts.len(), 2);
+    for c in &conflicts {
+        assert_eq!(c.context, vec![c.nonterminal]);
+        for k in &c.continuations {
+            assert_eq!(k.form[0], c.terminal);
+            assert_eq!(k.derivation[0], k.rule);
+            assert_eq!(Symbol::from(cfg.get_rule(k.rule).unwrap().0), c.nonterminal);
+        }
+    }
+    let mut ll = bnf::from_str("S A B C\nA\nA d\nB e\nC\nC f").freeze();
+    assert!(counterexample::ll1(&mut ll).is_empty());
+
+    // Two rules deriving ε conflict on what follows, not on the `EPSILON` column.
+    let mut cfg = bnf::parse("S -> A\nA -> ε | B\nB -> ε").unwrap().freeze();
+    let conflicts = counterexample::ll1(&mut cfg);
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0].terminal, END_OF_INPUT.into());
+    assert_eq!(conflicts[0].describe(&cfg), "A has rules 1, 2 for $:
+S ⇒ A
+rule 1: A ⇒ ε, followed by $
+rule 2: A ⇒ B ⇒ ε, followed by $
+");
+    let mut cfg = bnf::parse("S -> A S | ε\nA -> ε | a").unwrap().freeze();
+    let conflicts = counterexample::ll1(&mut cfg);
+    let cells: Vec<String> = conflicts.iter().map(|c| format!("{} {}", cfg.display_symbol(c.nonterminal), cfg.display_symbol(c.terminal))).collect();
+    assert_eq!(cells, vec!["S $", "A a"]);
+    assert!(conflicts.iter().all(|c| c.describe(&cfg).lines().count() == 4));
+}
+
+#[test]
+fn can_explain_lr_conflicts() {
+    let mut cfg = bnf::parse("E -> E '+' E | n").unwrap().freeze();
+    let conflicts = counterexample::lr(&mut cfg, 5, 4);
+    assert_eq!(conflicts.len(), 1);
+    let c = &conflicts[0];
+    assert!(c.unifying);
+    assert_eq!(c.first.as_ref().unwrap().form(), c.second.as_ref().unwrap().form());
+    assert!(c.describe(&cfg).ends_with("
+Example: E + E • + E
+Shift derivation:
+  E
+  ↳ 0: E + E
+           ↳ 0: E • + E
+Reduce derivation:
+  E
+  ↳ 0: E            + E
+       ↳ 0: E + E •
+"));
+
+    let mut cfg = bnf::parse("S -> 'if' c S | 'if' c S 'else' S | x").unwrap().freeze();
+    let c = &counterexample::lr(&mut cfg, 7, 6)[0];
+    assert!(c.unifying);
+    assert!(c.describe(&cfg).contains("Example: if c if c S • else S\n"));
+
+    // Not ambiguous, but `a` can't be reduced without seeing past `x`.
+    let mut cfg = bnf::parse("S -> A 'x' 'y' | B 'x' 'z'\nA -> a\nB -> a").unwrap().freeze();
+    let c = &counterexample::lr(&mut cfg, 7, 6)[0];
+    assert!(!c.unifying);
+    let a = cfg.symbol_by_name("A").unwrap();
+    let (x, y, z) = (cfg.symbol_by_name("x").unwrap(), cfg.symbol_by_name("y").unwrap(), cfg.symbol_by_name("z").unwrap());
+    assert_eq!(c.first, Some(counterexample::Derivation::Node(cfg.symbol_by_name("S").unwrap(), Rule(0), vec![
+        counterexample::Derivation::Node(a, Rule(2), vec![counterexample::Derivation::Symbol(cfg.symbol_by_name("a").unwrap()), counterexample::Derivation::Dot]),
+        counterexample::Derivation::Symbol(x),
+        counterexample::Derivation::Symbol(y),
+    ])));
+    assert_eq!(c.second.as_ref().unwrap().form()[1..].to_vec(), vec![x, z]);
+    assert!(c.describe(&cfg).ends_with("Example: a • x z
+Reduce by rule 3 derivation:
+  S
+  ↳ 1: B        x z
+       ↳ 3: a •
+"));
+}
+
+#[test]
+fn can_generate_random_sentences() {
+    // Lists of balanced parentheses around `a`.
+    let mut cfg = bnf::parse("L -> I M\nM -> ε | ',' I M\nI -> 'a' | '(' L ')'").unwrap().freeze();
+    let tab = ll1::generate_table(&mut cfg);
+    let mut generator = generate::Generator::new(&cfg, 42);
+    generator.max_depth(8);
+    let mut lengths = Vec::new();
+    for _ in 0..50 {
+        let (sentence, derivation) = generator.derivation();
+        let tokens: Vec<&Token> = sentence.iter().map(|t| t as &Token).collect();
+        assert_eq!(ll1::parse(&tab, tokens), derivation);
+        lengths.push(sentence.len());
+    }
+    assert!(lengths.iter().any(|&l| l > 1));
+    assert_eq!(generate::Generator::new(&cfg, 42).max_depth(8).sentence(), generate::Generator::new(&cfg, 42).max_depth(8).sentence());
+
+    // Past the depth budget, only the shortest sentence is left.
+    let a = cfg.symbol_by_name("a").unwrap();
+    assert_eq!(generate::Generator::new(&cfg, 7).max_depth(0).sentence(), vec![a.into()]);
+    // With `M -> , I M` weighted out, lists have one item.
+    let mut generator = generate::Generator::new(&cfg, 7);
+    generator.weight(Rule(2), 0).weight(Rule(4), 5);
+    for _ in 0..20 {
+        let sentence = generator.sentence();
+        assert!(!sentence.contains(&cfg.symbol_by_name(",").unwrap().into()));
+    }
+}
+
+#[test]
+fn can_sample_sentences_uniformly() {
+    let cfg = bnf::parse("S -> ε | a S | b S").unwrap();
+    let counts = generate::Counts::new(&cfg, 130).unwrap();
+    let s = cfg.symbol_by_name("S").unwrap();
+    assert_eq!(counts.count(s, 3), Some(8));
+    assert_eq!(counts.count(s, 70), Some(1 << 70));
+    assert_eq!(counts.count(s, 127), Some(1 << 127));
+    assert_eq!(counts.count(s, 128), None);
+    let mut rng = generate::Rng::new(1);
+    let mut seen = ::std::collections::HashMap::new();
+    for _ in 0..800 {
+        let (sentence, derivation) = counts.sample(&mut rng, 3).unwrap();
+        assert_eq!(derivation.len(), 4);
+        *seen.entry(sentence).or_insert(0) += 1;
+    }
+    assert_eq!(seen.len(), 8);
+    assert!(seen.values().all(|&n| n > 60 && n < 140));
+    // Long sentences are as likely to start with either terminal.
+    let a = cfg.symbol_by_name("a").unwrap();
+    let mut starting_with_a = 0;
+    for _ in 0..200 {
+        let (sentence, _) = counts.sample(&mut rng, 127).unwrap();
+        assert_eq!(sentence.len(), 127);
+        starting_with_a += (sentence[0] == a.into()) as u32;
+    }
+    assert!(starting_with_a > 70 && starting_with_a < 130);
+    assert!(counts.sample(&mut rng, 128).is_none());
+
+    // Balanced parentheses: a Catalan number of them, each with one derivation.
+    let mut cfg = bnf::parse("S -> ε | '(' S ')' S").unwrap().freeze();
+    let counts = generate::Counts::new(&cfg, 12).unwrap();
+    let s = cfg.symbol_by_name("S").unwrap();
+    assert_eq!((0..13).map(|n| counts.count(s, n).unwrap()).collect::<Vec<_>>(), vec![1, 0, 1, 0, 2, 0, 5, 0, 14, 0, 42, 0, 132]);
+    assert!(counts.sample(&mut rng, 5).is_none());
+    let (sentence, derivation) = counts.sample(&mut rng, 12).unwrap();
+    let tab = ll1::generate_table(&mut cfg);
+    assert_eq!(ll1::parse(&tab, sentence.iter().map(|t| t as &Token).collect()), derivation);
+}
+
+#[test]
+fn counting_cyclic_grammars_fails() {
+    let cfg = bnf::parse("S -> S A | a\nA -> ε").unwrap();
+    assert_eq!(generate::Counts::new(&cfg, 3).err(), cfg.symbol_by_name("S"));
+}
+
+#[test]
+fn can_enumerate_sentences() {
+    // Every sentence parses, and there is one per derivation.
+    let mut cfg = bnf::parse("L -> I M\nM -> ε | ',' I M\nI -> 'a' | '(' L ')'").unwrap().freeze();
+    let tab = ll1::generate_table(&mut cfg);
+    let sentences: Vec<Vec<_>> = generate::sentences(&cfg, 7).collect();
+    for sentence in &sentences {
+        ll1::parse(&tab, sentence.iter().map(|t| t as &Token).collect());
+    }
+    let counts = generate::Counts::new(&cfg, 7).unwrap();
+    let l = cfg.symbol_by_name("L").unwrap();
+    assert_eq!(sentences.len() as u128, (0..8).map(|n| counts.count(l, n).unwrap()).sum::<u128>());
+    assert!(sentences.windows(2).all(|w| (w[0].len(), &w[0]) < (w[1].len(), &w[1])));
+
+    // Ambiguous sentences come once, and ε-cycles end.
+    let cfg = bnf::parse("E -> E '+' E | E A | n\nA -> ε | A").unwrap();
+    let (n, plus) = (cfg.symbol_by_name("n").unwrap().into(), cfg.symbol_by_name("+").unwrap().into());
+    assert_eq!(generate::sentences(&cfg, 6).collect::<Vec<_>>(), vec![vec![n], vec![n, plus, n], vec![n, plus, n, plus, n]]);
+}
+
+#[test]
+fn can_find_shortest_examples() {
+    let cfg = bnf::parse("L -> '[' I M ']' | '[' ']'\nM -> ε | ',' I M\nI -> L | 'a' 'a' 'a' | X\nX -> X 'x'").unwrap();
+    let nonterminals = shortest::nonterminals(&cfg);
+    let (l, i, x) = (cfg.symbol_by_name("L").unwrap(), cfg.symbol_by_name("I").unwrap(), cfg.symbol_by_name("X").unwrap());
+    let shortest_l = nonterminals[l.to_index()].as_ref().unwrap();
+    assert_eq!(shortest_l.display(&cfg), "[ ]");
+    assert_eq!(shortest_l.derivation, vec![Rule(1)]);
+    let shortest_i = nonterminals[i.to_index()].as_ref().unwrap();
+    assert_eq!(shortest_i.display(&cfg), "[ ]");
+    assert_eq!(tree::from_leftmost(&cfg, &shortest_i.derivation).leaves(), shortest_i.sentence);
+    assert!(nonterminals[x.to_index()].is_none());
+
+    let rules = shortest::rules(&cfg);
+    let shown: Vec<Option<String>> = rules.iter().map(|e| e.as_ref().map(|e| e.display(&cfg))).collect();
+    assert_eq!(shown, vec![
+        Some("[ [ ] ]".to_string()), Some("[ ]".to_string()), Some("".to_string()), Some(", [ ]".to_string()),
+        Some("[ ]".to_string()), Some("a a a".to_string()), None, None,
+    ]);
+    assert_eq!(rules[0].as_ref().unwrap().derivation, vec![Rule(0), Rule(4), Rule(1), Rule(2)]);
+}
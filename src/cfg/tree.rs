@@ -0,0 +1,91 @@
+//! Parse trees built from the derivations the parsers return.
+//!
+//! The LL(1) parsers return the rules of a leftmost derivation, in the order they were applied,
+//! and `from_leftmost` builds the tree from them. The SLR(1) parser returns the rules it reduced
+//! by, which are a rightmost derivation in reverse, and `from_reductions` builds the tree from
+//! those.
+
+use cfg::{Cfg, Rule, Symbol, PackedSymbol, EPSILON};
+
+/// A parse tree.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Tree {
+    /// A terminal.
+    Leaf(Symbol),
+    /// A nonterminal expanded by a rule, with a child for each symbol of the rule other than ɛ.
+    Node(Symbol, Rule, Vec<Tree>),
+}
+
+impl Tree {
+    /// The symbol at the root of the tree.
+    pub fn symbol(&self) -> Symbol {
+        match *self {
+            Tree::Leaf(sym) | Tree::Node(sym, _, _) => sym,
+        }
+    }
+
+    /// The terminals at the leaves of the tree, in order.
+    pub fn leaves(&self) -> Vec<Symbol> {
+        let mut leaves = Vec::new();
+        self.collect_leaves(&mut leaves);
+        leaves
+    }
+
+    fn collect_leaves(&self, out: &mut Vec<Symbol>) {
+        match *self {
+            Tree::Leaf(sym) => out.push(sym),
+            Tree::Node(_, _, ref children) => for child in children {
+                child.collect_leaves(out);
+            },
+        }
+    }
+}
+
+fn rule<T>(cfg: &Cfg<T>, r: Rule) -> (PackedSymbol, Vec<PackedSymbol>) {
+    let (lhs, rhs) = cfg.get_rule(r).expect("The derivation uses a rule the grammar doesn't have.");
+    (lhs, rhs.iter().cloned().filter(|&s| s != EPSILON).collect())
+}
+
+/// Build the tree of a leftmost derivation, as returned by `ll1::parse`.
+///
+/// Panics if the derivation is incomplete or has rules left over.
+pub fn from_leftmost<T>(cfg: &Cfg<T>, derivation: &[Rule]) -> Tree {
+    fn build<T, I: Iterator<Item=Rule>>(cfg: &Cfg<T>, rules: &mut I) -> Tree {
+        let r = rules.next().expect("The derivation ends before the tree is complete.");
+        let (lhs, rhs) = rule(cfg, r);
+        let children = rhs.into_iter().map(|sym| {
+            if sym.is_terminal() { Tree::Leaf(sym.into()) } else { build(cfg, rules) }
+        }).collect();
+        Tree::Node(lhs.into(), r, children)
+    }
+    let mut rules = derivation.iter().cloned();
+    let tree = build(cfg, &mut rules);
+    if rules.next().is_some() {
+        panic!("The derivation has rules left over after the tree is complete.");
+    }
+    tree
+}
+
+/// Build the tree from the rules reduced by, in order, as returned by `lr::parse`.
+///
+/// Panics if the reductions don't make a single tree.
+pub fn from_reductions<T>(cfg: &Cfg<T>, reductions: &[Rule]) -> Tree {
+    let mut stack: Vec<Tree> = Vec::new();
+    for &r in reductions {
+        let (lhs, rhs) = rule(cfg, r);
+        let nonterminals = rhs.iter().filter(|s| s.is_nonterminal()).count();
+        if stack.len() < nonterminals {
+            panic!("Rule {} reduces more nonterminals than have been derived.", r.0);
+        }
+        let len = stack.len() - nonterminals;
+        let mut popped = stack.split_off(len).into_iter();
+        let children = rhs.into_iter().map(|sym| {
+            if sym.is_terminal() { Tree::Leaf(sym.into()) } else { popped.next().unwrap() }
+        }).collect();
+        stack.push(Tree::Node(lhs.into(), r, children));
+    }
+    if stack.len() != 1 {
+        panic!("The reductions make {} trees instead of one.", stack.len());
+    }
+    stack.pop().unwrap()
+}
@@ -48,61 +48,87 @@ pub fn compute_nullability<T>(cfg: &mut Cfg<T>) {
     cfg.mut_extra().insert::<Nullability>(nullable);
 }
 
-/*
 pub struct First;
-impl ::typemap::Key for First { type Value = HashMap<Vec<PackedSymbol>, HashSet<PackedSymbol>>; }
+impl ::typemap::Key for First { type Value = HashMap<PackedSymbol, HashSet<PackedSymbol>>; }
 
-pub fn compute_first_of_memoized<T>(cfg: &mut Cfg<T>, seq: Vec<PackedSymbol>) -> &HashSet<PackedSymbol> {
-    let sigh = seq.clone();
-
-    match cfg.mut_extra().entry::<First>().unwrap().entry(seq).or_insert_with(|| compute_first_of(cfg, &sigh)) {
-}
-*/
-
-// Returns true if we should keep looking in following symbols.
-fn compute_first_of_symbol<T>(cfg: &Cfg<T>, set: &mut HashSet<PackedSymbol>, sym: PackedSymbol) -> bool {
+// Adds the FIRST set of `sym` to `set` given the FIRST sets of nonterminals so far, returning true
+// if we should keep looking in following symbols.
+fn first_of_symbol(firsts: &HashMap<PackedSymbol, HashSet<PackedSymbol>>, set: &mut HashSet<PackedSymbol>, sym: PackedSymbol) -> bool {
     if sym.is_terminal() {
         set.insert(sym);
-        if sym == super::EPSILON {
-            true
-        } else {
-            false
-        }
+        sym == super::EPSILON
     } else {
-        let mut first_eps = false;
+        match firsts.get(&sym) {
+            Some(first) => {
+                set.extend(first.iter().cloned().filter(|&t| t != super::EPSILON));
+                first.contains(&super::EPSILON)
+            },
+            None => false,
+        }
+    }
+}
+
+// Computes the FIRST set of every nonterminal, with EPSILON in it if the nonterminal is nullable.
+//
+// This iterates to a fixpoint, so left recursion is fine.
+fn compute_firsts<T>(cfg: &Cfg<T>) -> HashMap<PackedSymbol, HashSet<PackedSymbol>> {
+    let mut firsts: HashMap<PackedSymbol, HashSet<PackedSymbol>> = HashMap::new();
+    let mut stable = false;
+    while !stable {
+        stable = true;
         for &(lhs, ref rhs) in cfg.rules() {
-            if lhs == sym {
-                let mut hs = HashSet::new();
-                let mut all_eps = true;
-                for sym in rhs {
-                    if !compute_first_of_symbol(cfg, &mut hs, *sym) {
-                        all_eps = false;
-                        set.extend(hs.drain());
-                        break;
-                    }
-                    set.extend(hs.drain());
-                }
-                if all_eps {
-                    set.insert(super::EPSILON);
-                    first_eps = true;
+            let mut set = HashSet::new();
+            let mut all_eps = true;
+            for &sym in rhs {
+                if !first_of_symbol(&firsts, &mut set, sym) {
+                    all_eps = false;
+                    break;
                 }
             }
+            if all_eps {
+                set.insert(super::EPSILON);
+            }
+            let first = firsts.entry(lhs).or_insert_with(HashSet::new);
+            let len = first.len();
+            first.extend(set);
+            stable = stable && first.len() == len;
         }
-        first_eps
+    }
+    firsts
+}
+
+/// Computes the FIRST set of every nonterminal, with EPSILON in it if the nonterminal is nullable,
+/// unless it was already computed.
+pub fn compute_first(cfg: &mut Cfg<super::Frozen>) {
+    if cfg.extra().get::<First>().is_none() {
+        let firsts = compute_firsts(cfg);
+        cfg.mut_extra().insert::<First>(firsts);
     }
 }
 
+/// Computes FIRST of a sequence of symbols, with EPSILON in it if the sequence is nullable and not
+/// empty.
+///
+/// This uses the sets from `compute_first` if they were computed, and otherwise computes them
+/// every time.
 pub fn compute_first_of<'a, T, R>(cfg: &Cfg<T>, seq: &'a [R]) -> HashSet<PackedSymbol> where PackedSymbol: From<&'a R> {
+    let computed;
+    let firsts = match cfg.extra().get::<First>() {
+        Some(firsts) => firsts,
+        None => { computed = compute_firsts(cfg); &computed },
+    };
     let mut first = HashSet::new();
     let mut all_nullable = true;
     for sym in seq {
-        if !compute_first_of_symbol(cfg, &mut first, sym.into()) {
+        if !first_of_symbol(firsts, &mut first, sym.into()) {
             all_nullable = false;
             break;
         }
     }
     if !all_nullable {
         first.remove(&super::EPSILON);
+    } else if !seq.is_empty() {
+        first.insert(super::EPSILON);
     }
     first
 }
@@ -112,6 +138,7 @@ impl ::typemap::Key for Follow { type Value = Vec<HashSet<PackedSymbol>>; }
 
 /// Computes the FOLLOW relation F : Nonterminal -> HashSet<Terminal>
 pub fn compute_follow(cfg: &mut Cfg<super::Frozen>) {
+    compute_first(cfg);
     let mut follow = vec![HashSet::with_capacity(cfg.max_term as usize); cfg.max_nonterm as usize];
     let mut stable_relation = Vec::new();
     {
@@ -140,3 +167,111 @@ pub fn compute_follow(cfg: &mut Cfg<super::Frozen>) {
     }
     cfg.mut_extra().insert::<Follow>(follow);
 }
+
+/// Computes the strongly connected components of a graph, given the successors of each node, in
+/// reverse topological order. Each component's nodes are in increasing order.
+pub fn strongly_connected_components(successors: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    struct Tarjan<'a> {
+        successors: &'a [Vec<usize>],
+        index: Vec<Option<usize>>,
+        low: Vec<usize>,
+        on_stack: Vec<bool>,
+        stack: Vec<usize>,
+        next: usize,
+        components: Vec<Vec<usize>>,
+    }
+
+    impl<'a> Tarjan<'a> {
+        fn visit(&mut self, v: usize) {
+            self.index[v] = Some(self.next);
+            self.low[v] = self.next;
+            self.next += 1;
+            self.stack.push(v);
+            self.on_stack[v] = true;
+            for &w in &self.successors[v] {
+                match self.index[w] {
+                    None => {
+                        self.visit(w);
+                        self.low[v] = ::std::cmp::min(self.low[v], self.low[w]);
+                    },
+                    Some(i) if self.on_stack[w] => self.low[v] = ::std::cmp::min(self.low[v], i),
+                    Some(_) => (),
+                }
+            }
+            if Some(self.low[v]) == self.index[v] {
+                let mut component = Vec::new();
+                loop {
+                    let w = self.stack.pop().unwrap();
+                    self.on_stack[w] = false;
+                    component.push(w);
+                    if w == v {
+                        break;
+                    }
+                }
+                component.sort();
+                self.components.push(component);
+            }
+        }
+    }
+
+    let n = successors.len();
+    let mut t = Tarjan {
+        successors: successors,
+        index: vec![None; n],
+        low: vec![0; n],
+        on_stack: vec![false; n],
+        stack: Vec::new(),
+        next: 0,
+        components: Vec::new(),
+    };
+    for v in 0..n {
+        if t.index[v].is_none() {
+            t.visit(v);
+        }
+    }
+    t.components
+}
+
+/// Computes the length of the shortest terminal string each nonterminal derives, and the rule to
+/// start deriving it with, or None for nonterminals that derive no terminal string.
+///
+/// This is Knuth's generalization of Dijkstra's algorithm: nonterminals are settled in order of
+/// length, each by the first rule whose nonterminals are all settled, so expanding each
+/// nonterminal by its rule always terminates, and derives a shortest string.
+pub fn compute_min_yield<T>(cfg: &Cfg<T>) -> Vec<Option<(usize, super::Rule)>> {
+    use std::cmp::Reverse;
+    use std::collections::BinaryHeap;
+
+    let mut min: Vec<Option<(usize, super::Rule)>> = vec![None; cfg.num_nonterminals() as usize];
+    // For each rule, its length so far and how many of its nonterminals aren't settled yet.
+    let mut length = Vec::new();
+    let mut pending = Vec::new();
+    let mut uses = vec![Vec::new(); cfg.num_nonterminals() as usize];
+    let mut heap = BinaryHeap::new();
+    for (i, &(_, ref rhs)) in cfg.rules().enumerate() {
+        length.push(rhs.iter().filter(|&&s| s.is_terminal() && s != super::EPSILON).count());
+        pending.push(0);
+        for &sym in rhs.iter().filter(|s| s.is_nonterminal()) {
+            uses[sym.to_index()].push(i);
+            pending[i] += 1;
+        }
+        if pending[i] == 0 {
+            heap.push(Reverse((length[i], i)));
+        }
+    }
+    while let Some(Reverse((len, i))) = heap.pop() {
+        let lhs = cfg.rules[i].0.to_index();
+        if min[lhs].is_some() {
+            continue;
+        }
+        min[lhs] = Some((len, super::Rule(i)));
+        for &user in &uses[lhs] {
+            length[user] += len;
+            pending[user] -= 1;
+            if pending[user] == 0 {
+                heap.push(Reverse((length[user], user)));
+            }
+        }
+    }
+    min
+}
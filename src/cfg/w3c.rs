@@ -0,0 +1,340 @@
+//! Importing grammars in the EBNF notation of W3C specifications, such as XML and XQuery.
+//!
+//! `parse` reads rules such as
+//!
+//! ```text
+//! /* Attribute values, simplified from XML 1.0. */
+//! AttValue  ::= '"' ([^<&"] | Reference)* '"'
+//! Reference ::= '&#x' [0-9a-fA-F]+ ';'
+//! ```
+//!
+//! A rule is a name, `::=`, and an expression of alternatives `|`, sequences, groups `( ... )` and
+//! the postfix operators `?`, `*` and `+`, which are desugared by `cfg::ebnf`. A rule ends where
+//! the next one starts. Comments are written `/* ... */`, and well-formedness and validity
+//! constraint annotations like `[ WFC: Unique Att Spec ]` are skipped.
+//!
+//! The notation describes strings down to the character, so character-level constructs become
+//! scannerless terminals (see `cfg::scannerless`): a character class `[a-zA-Z]` or `[^<&]` and a
+//! character `#x20` are terminals of their characters, and a string `'abc'` or `"abc"` is a
+//! sequence of terminals for each character. Terminals are shared between equal character sets.
+//!
+//! Names are XML names, which may also contain `-`, `.` and `:` after their first character, and
+//! every name used must have a rule. Exclusion, `A - B`, has no equivalent in a context-free
+//! grammar and is an error; without spaces around its `-`, it would be read as one name.
+
+use std::collections::HashMap;
+use std::fmt;
+use cfg::{Cfg, Mutable, Symbol};
+use cfg::bnf::Position;
+use cfg::ebnf::{self, Construct, Expr};
+use cfg::scannerless::class_terminal;
+use lex::CharSet;
+
+/// An error in a W3C grammar.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Error {
+    /// A character that can't start an expression or operator.
+    UnexpectedChar(Position, char),
+    /// Something other than what was expected, which is described.
+    Expected(Position, &'static str),
+    /// A string, character class or comment without its closing delimiter.
+    Unterminated(Position),
+    /// A `#x` character that isn't valid, or a character range with its bounds reversed.
+    InvalidChar(Position),
+    /// An exclusion `A - B`, which can't be expressed in a `Cfg`.
+    Exclusion(Position),
+    /// A name that is used but has no rule.
+    Undefined(Position, String),
+    /// The input has no rules.
+    NoRules,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::UnexpectedChar(pos, c) => write!(f, "{}: unexpected character {:?}", pos, c),
+            Error::Expected(pos, what) => write!(f, "{}: expected {}", pos, what),
+            Error::Unterminated(pos) => write!(f, "{}: unterminated string, character class or comment", pos),
+            Error::InvalidChar(pos) => write!(f, "{}: invalid character or character range", pos),
+            Error::Exclusion(pos) =>
+                write!(f, "{}: exclusion `A - B` can't be imported; rewrite the rule without it", pos),
+            Error::Undefined(pos, ref name) => write!(f, "{}: {} has no rule", pos, name),
+            Error::NoRules => write!(f, "the grammar has no rules"),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Tok {
+    Name(String),
+    Define,
+    Bar,
+    Minus,
+    Open,
+    Close,
+    /// `?`, `*` or `+`.
+    Repeat(char),
+    /// A sequence of sets of characters and their names, from a string, class or character.
+    Chars(Vec<(CharSet, String)>),
+}
+
+struct Scanner {
+    chars: Vec<char>,
+    i: usize,
+    pos: Position,
+}
+
+impl Scanner {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.i).cloned()
+    }
+
+    fn lookahead(&self, s: &str) -> bool {
+        s.chars().enumerate().all(|(k, c)| self.chars.get(self.i + k) == Some(&c))
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        match c {
+            Some('\n') => { self.pos.line += 1; self.pos.column = 1; },
+            Some(_) => self.pos.column += 1,
+            None => return None,
+        }
+        self.i += 1;
+        c
+    }
+
+    // A `#xN` character, after the `#`.
+    fn hex_char(&mut self, start: Position) -> Result<char, Error> {
+        if self.next() != Some('x') {
+            return Err(Error::Expected(start, "`x` after #"));
+        }
+        let mut digits = String::new();
+        while let Some(c) = self.peek() {
+            if !c.is_digit(16) {
+                break;
+            }
+            digits.push(c);
+            self.next();
+        }
+        u32::from_str_radix(&digits, 16).ok().and_then(::std::char::from_u32).ok_or(Error::InvalidChar(start))
+    }
+
+    // A character of a class: `#xN` or any character but `]`.
+    fn class_char(&mut self, start: Position) -> Result<char, Error> {
+        match self.peek() {
+            Some('#') if self.chars.get(self.i + 1) == Some(&'x') => { self.next(); self.hex_char(start) },
+            Some('\n') | Some(']') | None => Err(Error::Unterminated(start)),
+            Some(c) => { self.next(); Ok(c) },
+        }
+    }
+}
+
+/// Whether `c` may be in a name after its first character, as XML's `NameChar`.
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-' || c == '.' || c == ':' || c == '\u{B7}'
+        || ('\u{300}' <= c && c <= '\u{36F}') || c == '\u{203F}' || c == '\u{2040}'
+}
+
+fn tokenize(input: &str) -> Result<Vec<(Position, Tok)>, Error> {
+    let mut toks = Vec::new();
+    let mut s = Scanner { chars: input.chars().collect(), i: 0, pos: Position { line: 1, column: 1 } };
+    let show = |c: char| if c > ' ' && c <= '~' { c.to_string() } else { format!("#x{:X}", c as u32) };
+
+    while let Some(c) = s.peek() {
+        let start = s.pos;
+        let from = s.i;
+        if c.is_whitespace() {
+            s.next();
+        } else if s.lookahead("/*") {
+            while !s.lookahead("*/") {
+                if s.next().is_none() {
+                    return Err(Error::Unterminated(start));
+                }
+            }
+            s.next();
+            s.next();
+        } else if c.is_alphabetic() || c == '_' {
+            let mut name = String::new();
+            while let Some(c) = s.peek() {
+                if !is_name_char(c) || s.lookahead("::=") {
+                    break;
+                }
+                name.push(c);
+                s.next();
+            }
+            toks.push((start, Tok::Name(name)));
+        } else if s.lookahead("::=") {
+            s.next(); s.next(); s.next();
+            toks.push((start, Tok::Define));
+        } else if c == '|' || c == '-' || c == '(' || c == ')' || c == '?' || c == '*' || c == '+' {
+            s.next();
+            toks.push((start, match c {
+                '|' => Tok::Bar,
+                '-' => Tok::Minus,
+                '(' => Tok::Open,
+                ')' => Tok::Close,
+                c => Tok::Repeat(c),
+            }));
+        } else if c == '\'' || c == '"' {
+            s.next();
+            let mut chars = Vec::new();
+            loop {
+                match s.next() {
+                    Some(q) if q == c => break,
+                    Some('\n') | None => return Err(Error::Unterminated(start)),
+                    Some(ch) => chars.push((CharSet::single(ch), format!("'{}'", show(ch)))),
+                }
+            }
+            toks.push((start, Tok::Chars(chars)));
+        } else if c == '#' {
+            s.next();
+            let ch = try!(s.hex_char(start));
+            toks.push((start, Tok::Chars(vec![(CharSet::single(ch), format!("#x{:X}", ch as u32))])));
+        } else if c == '[' {
+            s.next();
+            let rest = s.chars[s.i..].iter().take_while(|&&c| c != ']').collect::<String>().trim_start().to_lowercase();
+            if rest.starts_with("wfc:") || rest.starts_with("vc:") {
+                while s.next() != Some(']') {
+                    if s.peek().is_none() {
+                        return Err(Error::Unterminated(start));
+                    }
+                }
+                continue;
+            }
+            let negated = s.peek() == Some('^');
+            if negated {
+                s.next();
+            }
+            let mut set = CharSet::new();
+            while s.peek() != Some(']') {
+                let lo = try!(s.class_char(start));
+                // A `-` between two characters is a range, and is literal anywhere else.
+                let hi = if s.peek() == Some('-') && s.chars.get(s.i + 1).map_or(false, |&c| c != ']') {
+                    s.next();
+                    try!(s.class_char(start))
+                } else {
+                    lo
+                };
+                if hi < lo {
+                    return Err(Error::InvalidChar(start));
+                }
+                set = set.union(&CharSet::range(lo, hi));
+            }
+            s.next();
+            if negated {
+                set = set.negate();
+            }
+            let name = s.chars[from..s.i].iter().cloned().collect::<String>();
+            toks.push((start, Tok::Chars(vec![(set, name)])));
+        } else {
+            return Err(Error::UnexpectedChar(start, c));
+        }
+    }
+    Ok(toks)
+}
+
+struct Parser<'a> {
+    toks: &'a [(Position, Tok)],
+    i: usize,
+    end: Position,
+    cfg: Cfg<Mutable>,
+    rules: HashMap<String, Symbol>,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a Tok> {
+        self.toks.get(self.i).map(|&(_, ref tok)| tok)
+    }
+
+    fn position(&self) -> Position {
+        self.toks.get(self.i).map_or(self.end, |&(pos, _)| pos)
+    }
+
+    // Whether the next rule starts here.
+    fn at_rule(&self) -> bool {
+        match (self.toks.get(self.i), self.toks.get(self.i + 1)) {
+            (Some(&(_, Tok::Name(_))), Some(&(_, Tok::Define))) => true,
+            _ => false,
+        }
+    }
+
+    fn alts(&mut self) -> Result<Expr, Error> {
+        let mut alts = vec![try!(self.seq())];
+        while let Some(&Tok::Bar) = self.peek() {
+            self.i += 1;
+            alts.push(try!(self.seq()));
+        }
+        Ok(if alts.len() == 1 { alts.pop().unwrap() } else { Expr::Alt(alts) })
+    }
+
+    fn seq(&mut self) -> Result<Expr, Error> {
+        let mut items = Vec::new();
+        while !self.at_rule() {
+            let pos = self.position();
+            let mut item = match self.peek() {
+                Some(&Tok::Name(ref name)) => match self.rules.get(name) {
+                    Some(&nt) => Expr::Symbol(nt),
+                    None => return Err(Error::Undefined(pos, name.clone())),
+                },
+                Some(&Tok::Chars(ref chars)) => Expr::Seq(chars.iter().map(|&(ref set, ref name)| {
+                    Expr::Symbol(class_terminal(&mut self.cfg, set.clone(), name))
+                }).collect()),
+                Some(&Tok::Open) => {
+                    self.i += 1;
+                    let inner = try!(self.alts());
+                    if self.peek() != Some(&Tok::Close) {
+                        return Err(Error::Expected(self.position(), "`)`"));
+                    }
+                    Expr::Op(Construct::Group, pos, Box::new(inner))
+                },
+                Some(&Tok::Define) | Some(&Tok::Repeat(_)) => return Err(Error::Expected(pos, "an expression")),
+                Some(&Tok::Minus) => return Err(Error::Exclusion(pos)),
+                Some(&Tok::Bar) | Some(&Tok::Close) | None => break,
+            };
+            self.i += 1;
+            while let Some(&Tok::Repeat(op)) = self.peek() {
+                let construct = match op { '*' => Construct::Star, '+' => Construct::Plus, _ => Construct::Optional };
+                item = Expr::Op(construct, self.position(), Box::new(item));
+                self.i += 1;
+            }
+            items.push(item);
+        }
+        Ok(if items.len() == 1 { items.pop().unwrap() } else { Expr::Seq(items) })
+    }
+}
+
+/// Parse a grammar in W3C EBNF notation. The first rule is the start rule.
+pub fn parse(input: &str) -> Result<Cfg<Mutable>, Error> {
+    let toks = try!(tokenize(input));
+    let end = toks.last().map_or(Position { line: 1, column: 1 }, |&(pos, _)| pos);
+    let mut p = Parser { toks: &toks, i: 0, end: end, cfg: Cfg::new(), rules: HashMap::new() };
+
+    for w in toks.windows(2) {
+        if let (&(_, Tok::Name(ref name)), &(_, Tok::Define)) = (&w[0], &w[1]) {
+            if !p.rules.contains_key(name) {
+                let nt = p.cfg.add_named_nonterminal(name);
+                p.rules.insert(name.clone(), nt);
+            }
+        }
+    }
+
+    let mut first = true;
+    while p.i < toks.len() {
+        if !p.at_rule() {
+            return Err(Error::Expected(p.position(), if first { "a rule" } else { "an expression" }));
+        }
+        let lhs = match toks[p.i].1 { Tok::Name(ref name) => p.rules[name], _ => unreachable!() };
+        p.i += 2;
+        let e = try!(p.alts());
+        let rules = ebnf::add_rules(&mut p.cfg, lhs, &e);
+        if first {
+            p.cfg.set_start(rules[0]);
+            first = false;
+        }
+    }
+    if first {
+        return Err(Error::NoRules);
+    }
+    Ok(p.cfg)
+}
@@ -0,0 +1,519 @@
+//! Importing yacc and bison grammar files.
+//!
+//! `parse` reads a `.y` file such as
+//!
+//! ```text
+//! %{ #include <stdio.h> %}
+//! %token NUM
+//! %left '+' '-'
+//! %left '*'
+//! %start expr
+//! %%
+//! expr : expr '+' expr   { $$ = $1 + $3; }
+//!      | expr '*' expr   { $$ = $1 * $3; }
+//!      | '-' expr %prec '*'
+//!      | NUM
+//!      ;
+//! %%
+//! int main() { ... }
+//! ```
+//!
+//! Tokens declared with `%token` are terminals named by their identifier, and character literals
+//! are terminals named as they are written, quotes and all, such as `'+'`. A string literal
+//! declared after a token, as in `%token PLUS "+"`, is an alias for it; other string literals are
+//! terminals of their own. Every identifier with rules is a nonterminal, and the predefined
+//! `error` token is a terminal.
+//!
+//! `%left`, `%right`, `%nonassoc` and `%precedence` declare precedence levels in `cfg::prec`, and
+//! `%prec` overrides a rule's precedence. `%precedence` is declared as non-associative. `%start`
+//! sets the start rule to the first rule of its nonterminal, which is otherwise the first rule.
+//! `%empty` is ɛ, as is an empty alternative.
+//!
+//! The prologue, epilogue, `<type>` tags and all other declarations are skipped. Semantic
+//! actions are not interpreted, but their code is kept under the `Actions` key for the rule they
+//! end. As in bison, an action in the middle of a rule becomes the rule of a fresh nonterminal
+//! `$@n` deriving ɛ.
+
+use std::collections::HashMap;
+use std::fmt;
+use cfg::{Cfg, Mutable, Rule, Symbol, EPSILON};
+use cfg::bnf::Position;
+use cfg::prec::{self, Assoc};
+
+/// An error in a yacc grammar.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Error {
+    /// A character that can't start a token.
+    UnexpectedChar(Position, char),
+    /// Something other than what was expected, which is described.
+    Expected(Position, &'static str),
+    /// A literal, comment, action or prologue without its closing delimiter.
+    Unterminated(Position),
+    /// A name that is neither a token nor has rules.
+    Undefined(Position, String),
+    /// A token on the left-hand side of a rule.
+    TokenHasRules(Position, String),
+    /// `%prec` of a terminal with no declared precedence.
+    NoPrecedence(Position, String),
+    /// The grammar has no rules.
+    NoRules,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::UnexpectedChar(pos, c) => write!(f, "{}: unexpected character {:?}", pos, c),
+            Error::Expected(pos, what) => write!(f, "{}: expected {}", pos, what),
+            Error::Unterminated(pos) => write!(f, "{}: unterminated literal, comment, action or prologue", pos),
+            Error::Undefined(pos, ref name) => write!(f, "{}: {} is neither a token nor has rules", pos, name),
+            Error::TokenHasRules(pos, ref name) => write!(f, "{}: token {} can't have rules", pos, name),
+            Error::NoPrecedence(pos, ref name) => write!(f, "{}: %prec {} has no declared precedence", pos, name),
+            Error::NoRules => write!(f, "the grammar has no rules"),
+        }
+    }
+}
+
+pub struct Actions;
+impl ::typemap::Key for Actions { type Value = HashMap<Rule, String>; }
+
+/// The code of the semantic action ending `rule`, if any.
+pub fn action<T>(cfg: &Cfg<T>, rule: Rule) -> Option<&str> {
+    cfg.extra().get::<Actions>().and_then(|a| a.get(&rule)).map(|s| &s[..])
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Tok {
+    Ident(String),
+    /// A character literal, as written.
+    Char(String),
+    /// A string literal, as written.
+    Str(String),
+    Number,
+    /// `<...>`
+    Tag,
+    /// `%` and a name, such as `%token`.
+    Directive(String),
+    /// `%%`
+    Separator,
+    /// The code inside `{ ... }`.
+    Action(String),
+    Colon,
+    Bar,
+    Semicolon,
+}
+
+struct Scanner {
+    chars: Vec<char>,
+    i: usize,
+    pos: Position,
+}
+
+impl Scanner {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.i).cloned()
+    }
+
+    fn lookahead(&self, s: &str) -> bool {
+        s.chars().enumerate().all(|(k, c)| self.chars.get(self.i + k) == Some(&c))
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        match c {
+            Some('\n') => { self.pos.line += 1; self.pos.column = 1; },
+            Some(_) => self.pos.column += 1,
+            None => return None,
+        }
+        self.i += 1;
+        c
+    }
+
+    fn text(&self, from: usize) -> String {
+        self.chars[from..self.i].iter().cloned().collect()
+    }
+
+    // Skip a comment, if there is one here.
+    fn comment(&mut self) -> Result<bool, Error> {
+        let start = self.pos;
+        if self.lookahead("//") {
+            while self.peek().map_or(false, |c| c != '\n') {
+                self.next();
+            }
+        } else if self.lookahead("/*") {
+            self.next();
+            self.next();
+            while !self.lookahead("*/") {
+                if self.next().is_none() {
+                    return Err(Error::Unterminated(start));
+                }
+            }
+            self.next();
+            self.next();
+        } else {
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    // Skip a character or string literal quoted by the next character.
+    fn literal(&mut self) -> Result<(), Error> {
+        let start = self.pos;
+        let quote = self.next();
+        loop {
+            match self.next() {
+                Some('\\') => { self.next(); },
+                Some('\n') | None => return Err(Error::Unterminated(start)),
+                c if c == quote => return Ok(()),
+                _ => (),
+            }
+        }
+    }
+
+    // Skip to the end of the code starting after the `{`, returning it.
+    fn code(&mut self, start: Position) -> Result<String, Error> {
+        let from = self.i;
+        let mut depth = 0;
+        loop {
+            if try!(self.comment()) {
+                continue;
+            }
+            match self.peek() {
+                Some('\'') | Some('"') => try!(self.literal()),
+                Some('{') => { depth += 1; self.next(); },
+                Some('}') if depth == 0 => break,
+                Some('}') => { depth -= 1; self.next(); },
+                Some(_) => { self.next(); },
+                None => return Err(Error::Unterminated(start)),
+            }
+        }
+        let code = self.text(from);
+        self.next();
+        Ok(code)
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<(Position, Tok)>, Error> {
+    let mut toks = Vec::new();
+    let mut s = Scanner { chars: input.chars().collect(), i: 0, pos: Position { line: 1, column: 1 } };
+    let mut separators = 0;
+
+    while let Some(c) = s.peek() {
+        let start = s.pos;
+        let from = s.i;
+        if c.is_whitespace() {
+            s.next();
+        } else if try!(s.comment()) {
+            continue;
+        } else if s.lookahead("%%") {
+            s.next();
+            s.next();
+            toks.push((start, Tok::Separator));
+            separators += 1;
+            if separators == 2 {
+                // The epilogue.
+                break;
+            }
+        } else if s.lookahead("%{") {
+            while !s.lookahead("%}") {
+                if s.next().is_none() {
+                    return Err(Error::Unterminated(start));
+                }
+            }
+            s.next();
+            s.next();
+        } else if c == '%' {
+            s.next();
+            while s.peek().map_or(false, |c| c.is_alphanumeric() || c == '_' || c == '-') {
+                s.next();
+            }
+            toks.push((start, Tok::Directive(s.text(from))));
+        } else if c.is_alphabetic() || c == '_' || c == '.' {
+            while s.peek().map_or(false, |c| c.is_alphanumeric() || c == '_' || c == '.') {
+                s.next();
+            }
+            toks.push((start, Tok::Ident(s.text(from))));
+        } else if c.is_digit(10) {
+            while s.peek().map_or(false, |c| c.is_alphanumeric()) {
+                s.next();
+            }
+            toks.push((start, Tok::Number));
+        } else if c == '\'' || c == '"' {
+            try!(s.literal());
+            let text = s.text(from);
+            toks.push((start, if c == '\'' { Tok::Char(text) } else { Tok::Str(text) }));
+        } else if c == '<' {
+            while s.peek() != Some('>') {
+                if s.next().is_none() {
+                    return Err(Error::Unterminated(start));
+                }
+            }
+            s.next();
+            toks.push((start, Tok::Tag));
+        } else if c == '{' {
+            s.next();
+            let code = try!(s.code(start));
+            toks.push((start, Tok::Action(code)));
+        } else if c == ':' || c == '|' || c == ';' {
+            s.next();
+            toks.push((start, match c { ':' => Tok::Colon, '|' => Tok::Bar, _ => Tok::Semicolon }));
+        } else {
+            return Err(Error::UnexpectedChar(start, c));
+        }
+    }
+    Ok(toks)
+}
+
+struct Parser<'a> {
+    toks: &'a [(Position, Tok)],
+    i: usize,
+    end: Position,
+    cfg: Cfg<Mutable>,
+    /// Terminals and nonterminals by name, and string aliases of tokens.
+    symbols: HashMap<String, Symbol>,
+    aliases: HashMap<String, Symbol>,
+    /// The number of mid-rule actions so far.
+    midrules: u32,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a Tok> {
+        self.toks.get(self.i).map(|&(_, ref tok)| tok)
+    }
+
+    fn position(&self) -> Position {
+        self.toks.get(self.i).map_or(self.end, |&(pos, _)| pos)
+    }
+
+    // Whether the next rule starts here.
+    fn at_rule(&self) -> bool {
+        match (self.toks.get(self.i), self.toks.get(self.i + 1)) {
+            (Some(&(_, Tok::Ident(_))), Some(&(_, Tok::Colon))) => true,
+            _ => false,
+        }
+    }
+
+    fn terminal(&mut self, name: &str) -> Symbol {
+        if let Some(&t) = self.symbols.get(name) {
+            return t;
+        }
+        let t = self.cfg.add_named_terminal(name);
+        self.symbols.insert(name.to_string(), t);
+        t
+    }
+
+    // A symbol of a declaration or rule.
+    fn symbol(&mut self) -> Result<Option<Symbol>, Error> {
+        let pos = self.position();
+        let sym = match self.peek() {
+            Some(&Tok::Ident(ref name)) => match self.symbols.get(name) {
+                Some(&sym) => sym,
+                None if name == "error" => self.terminal(name),
+                None => return Err(Error::Undefined(pos, name.clone())),
+            },
+            Some(&Tok::Char(ref text)) => self.terminal(text),
+            Some(&Tok::Str(ref text)) => match self.aliases.get(text) {
+                Some(&t) => t,
+                None => self.terminal(text),
+            },
+            _ => return Ok(None),
+        };
+        self.i += 1;
+        Ok(Some(sym))
+    }
+
+    // The declarations before the first `%%`, returning the name given by `%start`.
+    fn declarations(&mut self) -> Result<Option<(Position, String)>, Error> {
+        let mut start = None;
+        loop {
+            let (pos, directive) = match self.toks.get(self.i) {
+                Some(&(pos, Tok::Directive(ref d))) => (pos, d),
+                Some(&(_, Tok::Separator)) => { self.i += 1; break },
+                Some(&(pos, _)) => return Err(Error::Expected(pos, "a declaration or `%%`")),
+                None => return Err(Error::Expected(self.end, "`%%`")),
+            };
+            self.i += 1;
+            let assoc = match &directive[..] {
+                "%left" => Some(Assoc::Left),
+                "%right" => Some(Assoc::Right),
+                "%nonassoc" | "%precedence" => Some(Assoc::Nonassoc),
+                _ => None,
+            };
+            match &directive[..] {
+                "%token" => {
+                    let mut last = None;
+                    loop {
+                        match self.peek() {
+                            Some(&Tok::Ident(ref name)) => last = Some(self.terminal(name)),
+                            Some(&Tok::Char(ref text)) => last = Some(self.terminal(text)),
+                            Some(&Tok::Str(ref text)) => if let Some(t) = last {
+                                self.aliases.insert(text.clone(), t);
+                            },
+                            Some(&Tok::Tag) | Some(&Tok::Number) => (),
+                            _ => break,
+                        }
+                        self.i += 1;
+                    }
+                },
+                _ if assoc.is_some() => {
+                    let mut terminals = Vec::new();
+                    loop {
+                        match self.peek() {
+                            Some(&Tok::Ident(ref name)) => terminals.push(self.terminal(name)),
+                            Some(&Tok::Char(ref text)) => terminals.push(self.terminal(text)),
+                            Some(&Tok::Str(ref text)) => terminals.push(match self.aliases.get(text) {
+                                Some(&t) => t,
+                                None => self.terminal(text),
+                            }),
+                            Some(&Tok::Tag) | Some(&Tok::Number) => (),
+                            _ => break,
+                        }
+                        self.i += 1;
+                    }
+                    prec::declare(&mut self.cfg, assoc.unwrap(), &terminals);
+                },
+                "%start" => match self.toks.get(self.i) {
+                    Some(&(pos, Tok::Ident(ref name))) => { start = Some((pos, name.clone())); self.i += 1; },
+                    _ => return Err(Error::Expected(pos, "a nonterminal after %start")),
+                },
+                _ => while let Some(tok) = self.peek() {
+                    match *tok {
+                        Tok::Directive(_) | Tok::Separator => break,
+                        _ => self.i += 1,
+                    }
+                },
+            }
+        }
+        Ok(start)
+    }
+
+    // One alternative of the rule for `lhs`, up to `|`, `;` or the next rule.
+    fn alternative(&mut self, lhs: Symbol) -> Result<Rule, Error> {
+        let mut rhs = Vec::new();
+        let mut action: Option<String> = None;
+        let mut prec = None;
+        loop {
+            let pos = self.position();
+            match self.peek() {
+                Some(&Tok::Action(ref code)) => {
+                    // An action followed by anything but `%prec` is a mid-rule action.
+                    if let Some(code) = action.take() {
+                        rhs.push(self.midrule(code));
+                    }
+                    action = Some(code.clone());
+                    self.i += 1;
+                },
+                Some(&Tok::Directive(ref d)) if d == "%prec" => {
+                    self.i += 1;
+                    let pos = self.position();
+                    let name = match self.peek() {
+                        Some(&Tok::Ident(ref name)) | Some(&Tok::Char(ref name)) | Some(&Tok::Str(ref name)) => name.clone(),
+                        _ => return Err(Error::Expected(pos, "a terminal after %prec")),
+                    };
+                    let t = try!(self.symbol()).unwrap();
+                    prec = Some((pos, name, t));
+                },
+                Some(&Tok::Directive(ref d)) if d == "%empty" => self.i += 1,
+                Some(&Tok::Bar) | Some(&Tok::Semicolon) | Some(&Tok::Separator) | None => break,
+                Some(&Tok::Ident(_)) if self.at_rule() => break,
+                Some(&Tok::Ident(_)) | Some(&Tok::Char(_)) | Some(&Tok::Str(_)) => {
+                    if let Some(code) = action.take() {
+                        rhs.push(self.midrule(code));
+                    }
+                    rhs.push(try!(self.symbol()).unwrap());
+                },
+                Some(_) => return Err(Error::Expected(pos, "a symbol, action, `|` or `;`")),
+            }
+        }
+        let rule = if rhs.is_empty() { self.cfg.add_rule(lhs, &[EPSILON]) } else { self.cfg.add_rule(lhs, &rhs) };
+        if let Some(code) = action {
+            self.cfg.mut_extra().entry::<Actions>().or_insert_with(HashMap::new).insert(rule, code);
+        }
+        if let Some((pos, name, t)) = prec {
+            if !prec::set_rule_precedence(&mut self.cfg, rule, t) {
+                return Err(Error::NoPrecedence(pos, name));
+            }
+        }
+        Ok(rule)
+    }
+
+    // A fresh nonterminal deriving ɛ, with the mid-rule action `code`.
+    fn midrule(&mut self, code: String) -> Symbol {
+        let name = loop {
+            self.midrules += 1;
+            let name = format!("$@{}", self.midrules);
+            if self.cfg.symbol_by_name(&name).is_none() {
+                break name;
+            }
+        };
+        let nt = self.cfg.add_named_nonterminal(&name);
+        let rule = self.cfg.add_rule(nt, &[EPSILON]);
+        self.cfg.mut_extra().entry::<Actions>().or_insert_with(HashMap::new).insert(rule, code);
+        nt
+    }
+}
+
+/// Parse a yacc or bison grammar file.
+pub fn parse(input: &str) -> Result<Cfg<Mutable>, Error> {
+    let toks = try!(tokenize(input));
+    let end = toks.last().map_or(Position { line: 1, column: 1 }, |&(pos, _)| pos);
+    let mut p = Parser {
+        toks: &toks,
+        i: 0,
+        end: end,
+        cfg: Cfg::new(),
+        symbols: HashMap::new(),
+        aliases: HashMap::new(),
+        midrules: 0,
+    };
+    let start = try!(p.declarations());
+
+    let rules_from = p.i;
+    for w in toks[rules_from..].windows(2) {
+        if let (&(pos, Tok::Ident(ref name)), &(_, Tok::Colon)) = (&w[0], &w[1]) {
+            match p.symbols.get(name) {
+                Some(&sym) if sym.is_terminal() => return Err(Error::TokenHasRules(pos, name.clone())),
+                Some(_) => (),
+                None => {
+                    let nt = p.cfg.add_named_nonterminal(name);
+                    p.symbols.insert(name.clone(), nt);
+                },
+            }
+        }
+    }
+
+    // The first rule of each nonterminal, in the order they are defined.
+    let mut first = HashMap::new();
+    let mut first_rule = None;
+    loop {
+        match p.peek() {
+            Some(&Tok::Separator) | None => break,
+            _ if p.at_rule() => (),
+            _ => return Err(Error::Expected(p.position(), "a rule")),
+        }
+        let lhs = match toks[p.i].1 { Tok::Ident(ref name) => p.symbols[name], _ => unreachable!() };
+        p.i += 2;
+        loop {
+            let rule = try!(p.alternative(lhs));
+            first.entry(lhs).or_insert(rule);
+            first_rule = first_rule.or(Some(rule));
+            match p.peek() {
+                Some(&Tok::Bar) => p.i += 1,
+                Some(&Tok::Semicolon) => { p.i += 1; break },
+                _ => break,
+            }
+        }
+    }
+
+    let start = match start {
+        Some((pos, name)) => match p.symbols.get(&name).and_then(|nt| first.get(nt)) {
+            Some(&rule) => rule,
+            None => return Err(Error::Undefined(pos, name)),
+        },
+        None => match first_rule {
+            Some(rule) => rule,
+            None => return Err(Error::NoRules),
+        },
+    };
+    p.cfg.set_start(start);
+    Ok(p.cfg)
+}
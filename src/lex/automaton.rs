@@ -0,0 +1,332 @@
+//! Compilation of regular expressions to a minimal DFA.
+//!
+//! The patterns are combined into one Thompson NFA, which is determinized with the subset
+//! construction, and then minimized with Hopcroft's algorithm. Transitions are on an alphabet of
+//! disjoint character ranges, such that every character set in the patterns is a union of them.
+
+use std::collections::HashMap;
+use lex::regex::Regex;
+
+/// A partition of the characters used by a set of patterns into disjoint ranges.
+pub struct Alphabet {
+    atoms: Vec<(u32, u32)>,
+}
+
+impl Alphabet {
+    fn new(patterns: &[&Regex]) -> Alphabet {
+        fn collect(re: &Regex, bounds: &mut Vec<u32>, sets: &mut Vec<(u32, u32)>) {
+            match *re {
+                Regex::Empty => (),
+                Regex::Set(ref set) => for &(lo, hi) in set.ranges() {
+                    bounds.push(lo as u32);
+                    bounds.push(hi as u32 + 1);
+                    sets.push((lo as u32, hi as u32));
+                },
+                Regex::Concat(ref res) | Regex::Alt(ref res) => for re in res {
+                    collect(re, bounds, sets);
+                },
+                Regex::Star(ref re) | Regex::Plus(ref re) | Regex::Optional(ref re) => collect(re, bounds, sets),
+            }
+        }
+
+        let mut bounds = Vec::new();
+        let mut sets = Vec::new();
+        for re in patterns {
+            collect(re, &mut bounds, &mut sets);
+        }
+        bounds.sort();
+        bounds.dedup();
+        sets.sort();
+        // Keep the ranges between consecutive bounds that some set covers.
+        let atoms = bounds.windows(2)
+            .map(|w| (w[0], w[1] - 1))
+            .filter(|&(lo, _)| sets.iter().any(|&(a, b)| a <= lo && lo <= b))
+            .collect();
+        Alphabet { atoms: atoms }
+    }
+
+    /// The number of ranges in the alphabet.
+    pub fn len(&self) -> usize {
+        self.atoms.len()
+    }
+
+    /// The range containing `c`, if any.
+    pub fn atom_of(&self, c: char) -> Option<usize> {
+        let c = c as u32;
+        match self.atoms.binary_search_by(|&(lo, _)| lo.cmp(&c)) {
+            Ok(i) => Some(i),
+            Err(0) => None,
+            Err(i) => if self.atoms[i - 1].1 >= c { Some(i - 1) } else { None },
+        }
+    }
+
+    // The ranges making up the characters from `lo` to `hi`.
+    fn atoms_in(&self, lo: u32, hi: u32) -> ::std::ops::Range<usize> {
+        let start = match self.atoms.binary_search_by(|&(a, _)| a.cmp(&lo)) {
+            Ok(i) | Err(i) => i,
+        };
+        let mut end = start;
+        while end < self.atoms.len() && self.atoms[end].0 <= hi {
+            end += 1;
+        }
+        start..end
+    }
+}
+
+struct NfaState {
+    eps: Vec<usize>,
+    trans: Vec<(usize, usize)>,
+}
+
+/// A Thompson NFA whose transitions are on ranges of an `Alphabet`.
+struct Nfa {
+    states: Vec<NfaState>,
+}
+
+impl Nfa {
+    fn add(&mut self) -> usize {
+        self.states.push(NfaState { eps: Vec::new(), trans: Vec::new() });
+        self.states.len() - 1
+    }
+
+    // Build the fragment for `re`, returning its start and end states.
+    fn build(&mut self, re: &Regex, alphabet: &Alphabet) -> (usize, usize) {
+        let start = self.add();
+        let end = match *re {
+            Regex::Empty => start,
+            Regex::Set(ref set) => {
+                let end = self.add();
+                for &(lo, hi) in set.ranges() {
+                    for atom in alphabet.atoms_in(lo as u32, hi as u32) {
+                        self.states[start].trans.push((atom, end));
+                    }
+                }
+                end
+            },
+            Regex::Concat(ref seq) => {
+                let mut end = start;
+                for re in seq {
+                    let (s, e) = self.build(re, alphabet);
+                    self.states[end].eps.push(s);
+                    end = e;
+                }
+                end
+            },
+            Regex::Alt(ref alts) => {
+                let end = self.add();
+                for re in alts {
+                    let (s, e) = self.build(re, alphabet);
+                    self.states[start].eps.push(s);
+                    self.states[e].eps.push(end);
+                }
+                end
+            },
+            Regex::Star(ref inner) | Regex::Plus(ref inner) | Regex::Optional(ref inner) => {
+                let (s, e) = self.build(inner, alphabet);
+                let end = self.add();
+                self.states[start].eps.push(s);
+                self.states[e].eps.push(end);
+                match *re {
+                    Regex::Star(_) => { self.states[start].eps.push(end); self.states[e].eps.push(s); },
+                    Regex::Plus(_) => self.states[e].eps.push(s),
+                    _ => self.states[start].eps.push(end),
+                }
+                end
+            },
+        };
+        (start, end)
+    }
+
+    fn closure(&self, set: &mut Vec<usize>) {
+        let mut seen = vec![false; self.states.len()];
+        for &s in set.iter() {
+            seen[s] = true;
+        }
+        let mut i = 0;
+        while i < set.len() {
+            for &t in &self.states[set[i]].eps {
+                if !seen[t] {
+                    seen[t] = true;
+                    set.push(t);
+                }
+            }
+            i += 1;
+        }
+        set.sort();
+    }
+}
+
+/// A DFA over the ranges of an `Alphabet`.
+///
+/// A state accepts the patterns listed in `accept`, in increasing order, so the first is the one
+/// with priority.
+pub struct Dfa {
+    pub alphabet: Alphabet,
+    pub start: usize,
+    pub trans: Vec<Vec<Option<usize>>>,
+    pub accept: Vec<Vec<usize>>,
+}
+
+impl Dfa {
+    /// Build the minimal DFA recognizing each of `patterns`.
+    pub fn new(patterns: &[&Regex]) -> Dfa {
+        minimize(determinize(patterns))
+    }
+
+    /// The state reached from `state` on `c`, if any.
+    pub fn step(&self, state: usize, c: char) -> Option<usize> {
+        self.alphabet.atom_of(c).and_then(|a| self.trans[state][a])
+    }
+}
+
+fn determinize(patterns: &[&Regex]) -> Dfa {
+    let alphabet = Alphabet::new(patterns);
+    let mut nfa = Nfa { states: Vec::new() };
+    let start = nfa.add();
+    let mut accepting = HashMap::new();
+    for (i, re) in patterns.iter().enumerate() {
+        let (s, e) = nfa.build(re, &alphabet);
+        nfa.states[start].eps.push(s);
+        accepting.insert(e, i);
+    }
+
+    let mut initial = vec![start];
+    nfa.closure(&mut initial);
+    let mut seen = HashMap::new();
+    seen.insert(initial.clone(), 0);
+    let mut subsets = vec![initial];
+    let mut trans = Vec::new();
+    let mut accept = Vec::new();
+
+    let mut d = 0;
+    while d < subsets.len() {
+        let mut moves = vec![Vec::new(); alphabet.len()];
+        let mut accepts = Vec::new();
+        for &s in &subsets[d] {
+            for &(atom, t) in &nfa.states[s].trans {
+                moves[atom].push(t);
+            }
+            if let Some(&i) = accepting.get(&s) {
+                accepts.push(i);
+            }
+        }
+        accepts.sort();
+        accepts.dedup();
+        accept.push(accepts);
+
+        let mut row = Vec::with_capacity(alphabet.len());
+        for mut next in moves {
+            if next.is_empty() {
+                row.push(None);
+                continue;
+            }
+            next.sort();
+            next.dedup();
+            nfa.closure(&mut next);
+            let target = match seen.get(&next) {
+                Some(&t) => t,
+                None => {
+                    let t = subsets.len();
+                    seen.insert(next.clone(), t);
+                    subsets.push(next);
+                    t
+                }
+            };
+            row.push(Some(target));
+        }
+        trans.push(row);
+        d += 1;
+    }
+
+    Dfa { alphabet: alphabet, start: 0, trans: trans, accept: accept }
+}
+
+fn minimize(dfa: Dfa) -> Dfa {
+    // Complete the DFA with a dead state, so every state has a transition on every range.
+    let dead = dfa.trans.len();
+    let n = dead + 1;
+    let atoms = dfa.alphabet.len();
+    let target = |s: usize, a: usize| if s == dead { dead } else { dfa.trans[s][a].unwrap_or(dead) };
+
+    let mut inverse = vec![vec![Vec::new(); n]; atoms];
+    for s in 0..n {
+        for a in 0..atoms {
+            inverse[a][target(s, a)].push(s);
+        }
+    }
+
+    // Start with the states partitioned by what they accept.
+    let mut blocks: Vec<Vec<usize>> = Vec::new();
+    let mut block_of = vec![0; n];
+    {
+        let mut by_accept = HashMap::new();
+        for s in 0..n {
+            let key: &[usize] = if s == dead { &[] } else { &dfa.accept[s] };
+            let b = *by_accept.entry(key).or_insert_with(|| { blocks.push(Vec::new()); blocks.len() - 1 });
+            blocks[b].push(s);
+            block_of[s] = b;
+        }
+    }
+
+    let mut work: Vec<usize> = (0..blocks.len()).collect();
+    let mut in_work = vec![true; blocks.len()];
+    while let Some(splitter) = work.pop() {
+        in_work[splitter] = false;
+        let members = blocks[splitter].clone();
+        for a in 0..atoms {
+            let mut marked: HashMap<usize, Vec<usize>> = HashMap::new();
+            for &t in &members {
+                for &s in &inverse[a][t] {
+                    marked.entry(block_of[s]).or_insert_with(Vec::new).push(s);
+                }
+            }
+            let mut touched = marked.into_iter().collect::<Vec<_>>();
+            touched.sort();
+            for (b, mut inside) in touched {
+                if inside.len() == blocks[b].len() {
+                    continue;
+                }
+                inside.sort();
+                let outside = blocks[b].iter().cloned().filter(|s| inside.binary_search(s).is_err()).collect::<Vec<_>>();
+                let new = blocks.len();
+                let (keep, moved) = (outside, inside);
+                for &s in &moved {
+                    block_of[s] = new;
+                }
+                let smaller = if moved.len() <= keep.len() { new } else { b };
+                blocks[b] = keep;
+                blocks.push(moved);
+                in_work.push(false);
+                if in_work[b] {
+                    in_work[new] = true;
+                    work.push(new);
+                } else {
+                    in_work[smaller] = true;
+                    work.push(smaller);
+                }
+            }
+        }
+    }
+
+    // Number the blocks in order of their first state, dropping the dead one.
+    let dead_block = block_of[dead];
+    if block_of[dfa.start] == dead_block {
+        return Dfa { alphabet: dfa.alphabet, start: 0, trans: vec![vec![None; atoms]], accept: vec![Vec::new()] };
+    }
+    let mut number = vec![None; blocks.len()];
+    let mut reps = Vec::new();
+    for s in 0..dead {
+        let b = block_of[s];
+        if b != dead_block && number[b].is_none() {
+            number[b] = Some(reps.len());
+            reps.push(s);
+        }
+    }
+    let trans = reps.iter().map(|&s| {
+        (0..atoms).map(|a| number[block_of[target(s, a)]]).collect()
+    }).collect();
+    let accept = reps.iter().map(|&s| dfa.accept[s].clone()).collect();
+    let start = number[block_of[dfa.start]].unwrap();
+
+    Dfa { alphabet: dfa.alphabet, start: start, trans: trans, accept: accept }
+}
@@ -0,0 +1,104 @@
+//! Sets of characters, as sorted lists of disjoint ranges.
+
+use std::char;
+
+/// A set of Unicode scalar values.
+///
+/// The ranges are inclusive, sorted, non-overlapping and non-adjacent, so equal sets have equal
+/// representations.
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct CharSet {
+    ranges: Vec<(char, char)>,
+}
+
+const MAX: u32 = 0x10FFFF;
+
+impl CharSet {
+    /// The empty set.
+    pub fn new() -> CharSet {
+        CharSet { ranges: Vec::new() }
+    }
+
+    /// The set containing only `c`.
+    pub fn single(c: char) -> CharSet {
+        CharSet { ranges: vec![(c, c)] }
+    }
+
+    /// The set of characters from `lo` to `hi`, inclusive.
+    pub fn range(lo: char, hi: char) -> CharSet {
+        if lo <= hi { CharSet { ranges: vec![(lo, hi)] } } else { CharSet::new() }
+    }
+
+    /// The set of all characters.
+    pub fn any() -> CharSet {
+        CharSet::new().negate()
+    }
+
+    /// Construct a set from arbitrary, possibly overlapping, ranges.
+    pub fn from_ranges(ranges: &[(char, char)]) -> CharSet {
+        let mut ranges = ranges.iter().cloned().filter(|&(lo, hi)| lo <= hi).collect::<Vec<_>>();
+        ranges.sort();
+        let mut merged: Vec<(char, char)> = Vec::with_capacity(ranges.len());
+        for (lo, hi) in ranges {
+            if let Some(last) = merged.last_mut() {
+                if lo as u32 <= last.1 as u32 + 1 || (last.1 == '\u{D7FF}' && lo == '\u{E000}') {
+                    if hi > last.1 {
+                        last.1 = hi;
+                    }
+                    continue;
+                }
+            }
+            merged.push((lo, hi));
+        }
+        CharSet { ranges: merged }
+    }
+
+    /// The ranges in this set, in order.
+    pub fn ranges(&self) -> &[(char, char)] {
+        &self.ranges
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    pub fn contains(&self, c: char) -> bool {
+        match self.ranges.binary_search_by(|&(lo, _)| lo.cmp(&c)) {
+            Ok(_) => true,
+            Err(0) => false,
+            Err(i) => self.ranges[i - 1].1 >= c,
+        }
+    }
+
+    pub fn union(&self, other: &CharSet) -> CharSet {
+        let mut ranges = self.ranges.clone();
+        ranges.extend(other.ranges.iter().cloned());
+        CharSet::from_ranges(&ranges)
+    }
+
+    /// The complement of this set.
+    pub fn negate(&self) -> CharSet {
+        let mut ranges = Vec::new();
+        let mut next = 0;
+        for &(lo, hi) in &self.ranges {
+            if (lo as u32) > next {
+                push_scalar_range(&mut ranges, next, lo as u32 - 1);
+            }
+            next = hi as u32 + 1;
+        }
+        if next <= MAX {
+            push_scalar_range(&mut ranges, next, MAX);
+        }
+        CharSet::from_ranges(&ranges)
+    }
+}
+
+// Push the scalar values between `lo` and `hi`, skipping the surrogates.
+fn push_scalar_range(ranges: &mut Vec<(char, char)>, lo: u32, hi: u32) {
+    let pieces = [(lo, if hi < 0xD800 { hi } else { 0xD7FF }), (if lo > 0xDFFF { lo } else { 0xE000 }, hi)];
+    for &(lo, hi) in &pieces {
+        if lo <= hi {
+            ranges.push((char::from_u32(lo).unwrap(), char::from_u32(hi).unwrap()));
+        }
+    }
+}
@@ -0,0 +1,209 @@
+//! Lexer generator.
+//!
+//! Each terminal of a grammar is given a regular expression (see `lex::regex` for the syntax).
+//! The expressions are compiled into a single minimal DFA, which tokenizes input by longest match:
+//! at each position the longest possible token is taken, and if several terminals match that
+//! token, the one whose pattern was added first wins.
+//!
+//! Patterns added with `skip`, such as whitespace and comments, are matched like any other but no
+//! token is produced for them.
+//!
+//! When the same characters form different tokens depending on syntactic context, the lexer can
+//! instead be driven by the parser: `Tokens::next_allowed` only tries the patterns of the
+//! terminals it is given, and `parse_contextual` feeds it those an LL(1) parser expects next.
+
+use std::fmt;
+use cfg::{ll1, Rule, Symbol, Token, END_OF_INPUT};
+
+pub use self::charset::CharSet;
+
+pub mod charset;
+pub mod regex;
+mod automaton;
+mod test;
+
+/// An error from building or running a lexer.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Error {
+    /// A pattern is malformed at the given character offset.
+    Syntax(usize, &'static str),
+    /// No pattern matches the input at the given byte offset.
+    NoMatch(usize),
+    /// The token at the given byte offset, or the end of input there, can't be parsed.
+    Parse(usize, ll1::Error),
+    /// The input goes on at the given byte offset after the parser has derived all of it.
+    TrailingInput(usize),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Syntax(pos, msg) => write!(f, "invalid pattern at character {}: {}", pos, msg),
+            Error::NoMatch(pos) => write!(f, "no token matches the input at byte {}", pos),
+            Error::Parse(pos, _) => write!(f, "unexpected token at byte {}", pos),
+            Error::TrailingInput(pos) => write!(f, "unexpected input after the end at byte {}", pos),
+        }
+    }
+}
+
+/// A range of bytes in the input.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A token produced by a `Lexer`.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Lexeme<'a> {
+    pub terminal: Symbol,
+    pub text: &'a str,
+    pub span: Span,
+}
+
+impl<'a> Token for Lexeme<'a> {
+    fn to_terminal(&self) -> Symbol {
+        self.terminal
+    }
+}
+
+/// Collects the patterns of a `Lexer`.
+pub struct Builder {
+    patterns: Vec<(Option<Symbol>, regex::Regex)>,
+}
+
+impl Builder {
+    pub fn new() -> Builder {
+        Builder { patterns: Vec::new() }
+    }
+
+    /// Produce `terminal` for input matching `pattern`.
+    pub fn add(&mut self, terminal: Symbol, pattern: &str) -> Result<(), Error> {
+        let re = try!(regex::parse(pattern));
+        self.patterns.push((Some(terminal), re));
+        Ok(())
+    }
+
+    /// Skip input matching `pattern`.
+    pub fn skip(&mut self, pattern: &str) -> Result<(), Error> {
+        let re = try!(regex::parse(pattern));
+        self.patterns.push((None, re));
+        Ok(())
+    }
+
+    pub fn build(&self) -> Lexer {
+        let res = self.patterns.iter().map(|&(_, ref re)| re).collect::<Vec<_>>();
+        Lexer {
+            dfa: automaton::Dfa::new(&res),
+            terminals: self.patterns.iter().map(|&(t, _)| t).collect(),
+        }
+    }
+}
+
+/// A tokenizer for a set of terminals.
+pub struct Lexer {
+    dfa: automaton::Dfa,
+    terminals: Vec<Option<Symbol>>,
+}
+
+impl Lexer {
+    /// Tokenize `input`.
+    pub fn tokenize<'a>(&'a self, input: &'a str) -> Tokens<'a> {
+        Tokens { lexer: self, input: input, pos: 0 }
+    }
+
+    /// Number of states in the lexer's DFA.
+    pub fn num_states(&self) -> usize {
+        self.dfa.trans.len()
+    }
+
+    // Find the longest match at the start of `input` of the patterns for which `allowed` holds,
+    // returning its length and pattern.
+    fn longest_match<F: Fn(usize) -> bool>(&self, input: &str, allowed: F) -> Option<(usize, usize)> {
+        let mut state = self.dfa.start;
+        let mut last = None;
+        for (i, c) in input.char_indices() {
+            state = match self.dfa.step(state, c) {
+                Some(s) => s,
+                None => break,
+            };
+            if let Some(&pattern) = self.dfa.accept[state].iter().find(|&&p| allowed(p)) {
+                last = Some((i + c.len_utf8(), pattern));
+            }
+        }
+        last
+    }
+}
+
+/// An iterator over the tokens of some input. After an error, it produces nothing more.
+pub struct Tokens<'a> {
+    lexer: &'a Lexer,
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Tokens<'a> {
+    /// The next token, trying only the patterns of `allowed` terminals and skipped patterns.
+    ///
+    /// `END_OF_INPUT` may be among the allowed terminals; it is never produced, as the end of input
+    /// is signaled by `None` as usual.
+    pub fn next_allowed(&mut self, allowed: &[Symbol]) -> Option<Result<Lexeme<'a>, Error>> {
+        let terminals = &self.lexer.terminals;
+        self.next_where(|p| terminals[p].map_or(true, |t| allowed.contains(&t)))
+    }
+
+    fn next_where<F: Fn(usize) -> bool>(&mut self, allowed: F) -> Option<Result<Lexeme<'a>, Error>> {
+        while self.pos < self.input.len() {
+            let start = self.pos;
+            match self.lexer.longest_match(&self.input[start..], &allowed) {
+                Some((len, pattern)) => {
+                    self.pos += len;
+                    if let Some(terminal) = self.lexer.terminals[pattern] {
+                        let span = Span { start: start, end: self.pos };
+                        return Some(Ok(Lexeme { terminal: terminal, text: &self.input[start..self.pos], span: span }));
+                    }
+                },
+                None => {
+                    self.pos = self.input.len();
+                    return Some(Err(Error::NoMatch(start)));
+                },
+            }
+        }
+        None
+    }
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Result<Lexeme<'a>, Error>;
+
+    fn next(&mut self) -> Option<Result<Lexeme<'a>, Error>> {
+        self.next_where(|_| true)
+    }
+}
+
+/// Tokenize and parse `input` together, returning the rules applied to derive it.
+///
+/// Before each token, the parser is asked which terminals it expects, and only their patterns are
+/// tried. A token that can't be derived is an `Error::Parse` at its start, whose `ll1::Error` can
+/// be described with the grammar's names.
+pub fn parse_contextual(lexer: &Lexer, tab: &ll1::Table, input: &str) -> Result<Vec<Rule>, Error> {
+    let mut parser = ll1::Parser::new(tab);
+    let mut tokens = lexer.tokenize(input);
+    loop {
+        let expected = parser.expected();
+        match tokens.next_allowed(&expected) {
+            Some(Ok(lexeme)) => match parser.try_feed(lexeme.terminal) {
+                Ok(true) => (),
+                Ok(false) => return Err(Error::TrailingInput(lexeme.span.start)),
+                Err(e) => return Err(Error::Parse(lexeme.span.start, e)),
+            },
+            Some(Err(e)) => return Err(e),
+            None => break,
+        }
+    }
+    match parser.try_feed(END_OF_INPUT.into()) {
+        Ok(true) => Ok(parser.into_derivation()),
+        Ok(false) => Err(Error::TrailingInput(input.len())),
+        Err(e) => Err(Error::Parse(input.len(), e)),
+    }
+}
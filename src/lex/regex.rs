@@ -0,0 +1,177 @@
+//! Regular expression syntax.
+//!
+//! The supported syntax is the common subset of most regex dialects:
+//!
+//! - `a|b` alternation, `ab` concatenation and `(a)` grouping.
+//! - `a*`, `a+` and `a?` repetition.
+//! - `.`, matching any character except a newline.
+//! - `[a-z_]` and `[^"\\]` character classes.
+//! - `\d`, `\w`, `\s` and their negations `\D`, `\W`, `\S`; `\n`, `\r`, `\t` and `\0`. Any other
+//!   escaped character stands for itself.
+
+use lex::charset::CharSet;
+use lex::Error;
+
+/// The syntax tree of a regular expression.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Regex {
+    /// Matches the empty string.
+    Empty,
+    Set(CharSet),
+    Concat(Vec<Regex>),
+    Alt(Vec<Regex>),
+    Star(Box<Regex>),
+    Plus(Box<Regex>),
+    Optional(Box<Regex>),
+}
+
+/// Parse a regular expression.
+pub fn parse(pattern: &str) -> Result<Regex, Error> {
+    let mut p = Parser { chars: pattern.chars().collect(), pos: 0 };
+    let re = try!(p.alt());
+    match p.peek() {
+        None => Ok(re),
+        Some(_) => Err(Error::Syntax(p.pos, "unmatched `)`")),
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).cloned()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek();
+        self.pos += 1;
+        c
+    }
+
+    fn alt(&mut self) -> Result<Regex, Error> {
+        let mut alts = vec![try!(self.concat())];
+        while self.peek() == Some('|') {
+            self.pos += 1;
+            alts.push(try!(self.concat()));
+        }
+        Ok(if alts.len() == 1 { alts.pop().unwrap() } else { Regex::Alt(alts) })
+    }
+
+    fn concat(&mut self) -> Result<Regex, Error> {
+        let mut seq = Vec::new();
+        loop {
+            match self.peek() {
+                None | Some('|') | Some(')') => break,
+                _ => seq.push(try!(self.repeat())),
+            }
+        }
+        Ok(match seq.len() {
+            0 => Regex::Empty,
+            1 => seq.pop().unwrap(),
+            _ => Regex::Concat(seq),
+        })
+    }
+
+    fn repeat(&mut self) -> Result<Regex, Error> {
+        let mut re = try!(self.atom());
+        loop {
+            re = match self.peek() {
+                Some('*') => Regex::Star(Box::new(re)),
+                Some('+') => Regex::Plus(Box::new(re)),
+                Some('?') => Regex::Optional(Box::new(re)),
+                _ => return Ok(re),
+            };
+            self.pos += 1;
+        }
+    }
+
+    fn atom(&mut self) -> Result<Regex, Error> {
+        let start = self.pos;
+        match self.next() {
+            Some('(') => {
+                let re = try!(self.alt());
+                match self.next() {
+                    Some(')') => Ok(re),
+                    _ => Err(Error::Syntax(start, "unclosed `(`")),
+                }
+            },
+            Some('[') => self.class().map(Regex::Set),
+            Some('.') => Ok(Regex::Set(CharSet::single('\n').negate())),
+            Some('\\') => self.escape().map(Regex::Set),
+            Some('*') | Some('+') | Some('?') => Err(Error::Syntax(start, "repetition of nothing")),
+            Some(c) => Ok(Regex::Set(CharSet::single(c))),
+            None => unreachable!(),
+        }
+    }
+
+    fn escape(&mut self) -> Result<CharSet, Error> {
+        let digit = CharSet::range('0', '9');
+        let word = CharSet::from_ranges(&[('0', '9'), ('A', 'Z'), ('_', '_'), ('a', 'z')]);
+        let space = CharSet::from_ranges(&[('\t', '\r'), (' ', ' ')]);
+        Ok(match self.next() {
+            Some('d') => digit,
+            Some('D') => digit.negate(),
+            Some('w') => word,
+            Some('W') => word.negate(),
+            Some('s') => space,
+            Some('S') => space.negate(),
+            Some('n') => CharSet::single('\n'),
+            Some('r') => CharSet::single('\r'),
+            Some('t') => CharSet::single('\t'),
+            Some('0') => CharSet::single('\0'),
+            Some(c) => CharSet::single(c),
+            None => return Err(Error::Syntax(self.pos - 1, "trailing `\\`")),
+        })
+    }
+
+    fn class(&mut self) -> Result<CharSet, Error> {
+        let start = self.pos - 1;
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.pos += 1;
+        }
+        let mut set = CharSet::new();
+        let mut first = true;
+        loop {
+            let lo = match self.next() {
+                None => return Err(Error::Syntax(start, "unclosed `[`")),
+                Some(']') if !first => break,
+                Some('\\') => {
+                    let escaped = try!(self.escape());
+                    if escaped.ranges().len() != 1 || escaped.ranges()[0].0 != escaped.ranges()[0].1 {
+                        set = set.union(&escaped);
+                        first = false;
+                        continue;
+                    }
+                    escaped.ranges()[0].0
+                },
+                Some(c) => c,
+            };
+            first = false;
+            if self.peek() == Some('-') && self.chars.get(self.pos + 1).map_or(false, |&c| c != ']') {
+                self.pos += 1;
+                let hi = match self.next() {
+                    Some('\\') => {
+                        let escaped = try!(self.escape());
+                        if escaped.ranges().len() != 1 || escaped.ranges()[0].0 != escaped.ranges()[0].1 {
+                            return Err(Error::Syntax(self.pos - 2, "class escape as range bound"));
+                        }
+                        escaped.ranges()[0].0
+                    },
+                    Some(c) => c,
+                    None => return Err(Error::Syntax(start, "unclosed `[`")),
+                };
+                if hi < lo {
+                    return Err(Error::Syntax(self.pos - 1, "range out of order"));
+                }
+                set = set.union(&CharSet::range(lo, hi));
+            } else {
+                set = set.union(&CharSet::single(lo));
+            }
+        }
+        Ok(if negated { set.negate() } else { set })
+    }
+}
@@ -0,0 +1,102 @@
+#![cfg(test)]
+
+use cfg::{ll1, Cfg, Token, Rule, END_OF_INPUT};
+use lex::{self, Builder, Error, Span};
+
+#[test]
+fn longest_match_and_priority() {
+    let mut cfg = Cfg::new();
+    let kw_if = cfg.add_terminal();
+    let ident = cfg.add_terminal();
+    let num = cfg.add_terminal();
+    let eq = cfg.add_terminal();
+    let eqeq = cfg.add_terminal();
+
+    let mut b = Builder::new();
+    b.add(kw_if, "if").unwrap();
+    b.add(ident, "[a-zA-Z_][a-zA-Z0-9_]*").unwrap();
+    b.add(num, r"\d+").unwrap();
+    b.add(eq, "=").unwrap();
+    b.add(eqeq, "==").unwrap();
+    b.skip(r"\s+").unwrap();
+    let lexer = b.build();
+
+    let tokens = lexer.tokenize("if iffy == 42=x").collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(tokens.iter().map(|t| t.terminal).collect::<Vec<_>>(), vec![kw_if, ident, eqeq, num, eq, ident]);
+    assert_eq!(tokens[1].text, "iffy");
+    assert_eq!(tokens[3].span, Span { start: 11, end: 13 });
+
+    let errors = lexer.tokenize("x $").collect::<Vec<_>>();
+    assert_eq!(errors.last(), Some(&Err(Error::NoMatch(2))));
+}
+
+#[test]
+fn dfa_is_minimal() {
+    let mut cfg = Cfg::new();
+    let a = cfg.add_terminal();
+
+    // (a|b)*abb has a four state minimal DFA.
+    let mut b = Builder::new();
+    b.add(a, "(a|b)*abb").unwrap();
+    assert_eq!(b.build().num_states(), 4);
+
+    // a*|(a+)+ is just a*, which needs a single state.
+    let mut b = Builder::new();
+    b.add(a, "a*|(a+)+").unwrap();
+    assert_eq!(b.build().num_states(), 1);
+
+    assert_eq!(Builder::new().add(a, "(ab"), Err(Error::Syntax(0, "unclosed `(`")));
+}
+
+#[test]
+fn lexemes_feed_ll1_parse() {
+    let mut cfg = Cfg::new();
+    let s = cfg.add_nonterminal();
+    let open = cfg.add_terminal();
+    let word = cfg.add_terminal();
+    let close = cfg.add_terminal();
+
+    // S -> ( w )
+
+    cfg.add_rule(s, &[open, word, close]);
+
+    let mut b = Builder::new();
+    b.add(open, r"\(").unwrap();
+    b.add(close, r"\)").unwrap();
+    b.add(word, r"[^()\s]+").unwrap();
+    b.skip(r"\s+").unwrap();
+    let lexer = b.build();
+
+    let mut cfg = cfg.freeze();
+    let tab = ll1::generate_table(&mut cfg);
+    let tokens = lexer.tokenize("( héllo )").collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(ll1::parse(&tab, tokens.iter().map(|t| t as &Token).collect()), vec![Rule(0)]);
+}
+
+#[test]
+fn parser_state_restricts_patterns() {
+    let mut cfg = Cfg::new();
+    let s = cfg.add_nonterminal();
+    let gt = cfg.add_terminal();
+    let shr = cfg.add_terminal();
+
+    // S -> > >
+
+    cfg.add_rule(s, &[gt, gt]);
+
+    let mut b = Builder::new();
+    b.add(shr, ">>").unwrap();
+    b.add(gt, ">").unwrap();
+    let lexer = b.build();
+
+    // Out of context, `>>` is a shift.
+    assert_eq!(lexer.tokenize(">>").map(|t| t.unwrap().terminal).collect::<Vec<_>>(), vec![shr]);
+
+    let mut cfg = cfg.freeze();
+    let tab = ll1::generate_table(&mut cfg);
+    let parser = ll1::Parser::new(&tab);
+    assert_eq!(parser.expected(), vec![gt]);
+    assert_eq!(lex::parse_contextual(&lexer, &tab, ">>"), Ok(vec![Rule(0)]));
+    let error = ll1::Error::Expected { expected: gt, found: END_OF_INPUT.into() };
+    assert_eq!(lex::parse_contextual(&lexer, &tab, ">"), Err(Error::Parse(1, error)));
+}
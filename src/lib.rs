@@ -2,3 +2,4 @@
 extern crate typemap;
 
 pub mod cfg;
+pub mod lex;
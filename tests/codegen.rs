@@ -0,0 +1,97 @@
+//! Runs the parser `codegen::generate_parser` writes for a small grammar against `ll1::parse` on
+//! the same table.
+//!
+//! The generated source is checked in as `generated/parser.rs`. If the generator's output
+//! changes, `generated_parser_is_up_to_date` fails; run the tests with `PCT_REGENERATE` set in
+//! the environment to write it out again.
+
+extern crate pct;
+
+use std::env;
+use std::fs::File;
+use std::io::Write;
+use std::panic;
+use pct::cfg::{codegen, ll1, Cfg, Frozen, Rule, Symbol, Token, EPSILON};
+
+#[path = "generated/parser.rs"]
+mod parser;
+
+fn grammar() -> (Cfg<Frozen>, Vec<Symbol>) {
+    let mut cfg = Cfg::new();
+    let s = cfg.add_named_nonterminal("S");
+    let a = cfg.add_named_nonterminal("A");
+    let b = cfg.add_named_nonterminal("B");
+    let c = cfg.add_named_nonterminal("C");
+
+    let d = cfg.add_named_terminal("d");
+    let e = cfg.add_named_terminal("e");
+    let f = cfg.add_named_terminal("f");
+
+    // S -> A B C
+    // A -> EPSILON | d
+    // B -> e
+    // C -> EPSILON | f
+
+    cfg.add_rule(s, &[a, b, c]);
+    cfg.add_rule(a, &[EPSILON]);
+    cfg.add_rule(a, &[d]);
+    cfg.add_rule(b, &[e]);
+    cfg.add_rule(c, &[EPSILON]);
+    cfg.add_rule(c, &[f]);
+    (cfg.freeze(), vec![d, e, f])
+}
+
+/// The derivation of `s`, or the message it panicked with.
+fn run<F: FnOnce() -> Vec<Rule> + panic::UnwindSafe>(parse: F) -> Result<Vec<Rule>, String> {
+    panic::catch_unwind(parse).map_err(|e| match e.downcast::<String>() {
+        Ok(message) => *message,
+        Err(_) => "a panic without a message".to_string(),
+    })
+}
+
+#[test]
+fn generated_parser_is_up_to_date() {
+    let (mut cfg, _) = grammar();
+    let tab = ll1::generate_table(&mut cfg);
+    let mut out = Vec::new();
+    codegen::generate_parser(&cfg, &tab, &mut out).unwrap();
+    if env::var_os("PCT_REGENERATE").is_some() {
+        File::create("tests/generated/parser.rs").unwrap().write_all(&out).unwrap();
+    } else {
+        assert!(String::from_utf8(out).unwrap() == include_str!("generated/parser.rs"),
+                "tests/generated/parser.rs is out of date; regenerate it with PCT_REGENERATE=1");
+    }
+}
+
+#[test]
+fn generated_parser_matches_ll1_parse() {
+    let (mut cfg, terminals) = grammar();
+    let tab = ll1::generate_table(&mut cfg);
+
+    // Every string of up to four terminals.
+    let mut strings: Vec<Vec<Symbol>> = vec![vec![]];
+    let mut i = 0;
+    while i < strings.len() {
+        if strings[i].len() < 4 {
+            for &t in &terminals {
+                let mut longer = strings[i].clone();
+                longer.push(t);
+                strings.push(longer);
+            }
+        }
+        i += 1;
+    }
+
+    let mut accepted = 0;
+    for s in &strings {
+        let tokens = || s.iter().map(|t| t as &Token).collect::<Vec<_>>();
+        let expected = run(|| ll1::parse(&tab, tokens()));
+        let generated = run(|| parser::parse(tokens()));
+        assert_eq!(generated, expected, "on {:?}", s);
+        if expected.is_ok() {
+            accepted += 1;
+        }
+    }
+    // `e`, `d e`, `e f` and `d e f`.
+    assert_eq!(accepted, 4);
+}
@@ -0,0 +1,110 @@
+// Generated by pct. Do not edit.
+
+use pct::cfg::{Rule, Token};
+
+/// Receives the rules and tokens of a derivation as they are recognized.
+pub trait Callbacks {
+    fn rule(&mut self, rule: Rule);
+    fn token(&mut self, _token: &Token) { }
+}
+
+impl Callbacks for Vec<Rule> {
+    fn rule(&mut self, rule: Rule) {
+        self.push(rule);
+    }
+}
+
+struct Parser<'a, 'b: 'a, C: 'a> {
+    s: &'a [&'b Token],
+    idx: usize,
+    callbacks: &'a mut C,
+}
+
+impl<'a, 'b, C: Callbacks> Parser<'a, 'b, C> {
+    fn peek(&self) -> usize {
+        self.s.get(self.idx).map(|t| t.to_terminal().to_index()).unwrap_or(0)
+    }
+
+    fn expect(&mut self, t: usize) {
+        if self.peek() != t {
+            panic!("Parse error! Expected {}, found {}.", TERMINAL_NAMES[t], TERMINAL_NAMES[self.peek()]);
+        }
+        if let Some(token) = self.s.get(self.idx) {
+            self.callbacks.token(*token);
+        }
+        self.idx += 1;
+    }
+}
+
+const TERMINAL_NAMES: &'static [&'static str] = &["$", "ε", "d", "e", "f"];
+
+/// Parse a string, returning the rules applied to derive the string.
+pub fn parse(s: Vec<&Token>) -> Vec<Rule> {
+    let mut derivation = Vec::new();
+    parse_with(&s, &mut derivation);
+    derivation
+}
+
+/// Parse a string, reporting each rule and token to `callbacks` as it is recognized.
+pub fn parse_with<C: Callbacks>(s: &[&Token], callbacks: &mut C) {
+    let mut p = Parser { s: s, idx: 0, callbacks: callbacks };
+    p.nonterminal_0();
+    p.expect(0);
+}
+
+impl<'a, 'b, C: Callbacks> Parser<'a, 'b, C> {
+    fn nonterminal_0(&mut self) {
+        match self.peek() {
+            // S -> A B C
+            2 | 3 => {
+                self.callbacks.rule(Rule(0));
+                self.nonterminal_1();
+                self.nonterminal_2();
+                self.nonterminal_3();
+            },
+            t => panic!("Parse error! Found error entry in table for {} on {}.", "S", TERMINAL_NAMES[t]),
+        }
+    }
+
+    fn nonterminal_1(&mut self) {
+        match self.peek() {
+            // A -> ε
+            3 => {
+                self.callbacks.rule(Rule(1));
+            },
+            // A -> d
+            2 => {
+                self.callbacks.rule(Rule(2));
+                self.expect(2);
+            },
+            t => panic!("Parse error! Found error entry in table for {} on {}.", "A", TERMINAL_NAMES[t]),
+        }
+    }
+
+    fn nonterminal_2(&mut self) {
+        match self.peek() {
+            // B -> e
+            3 => {
+                self.callbacks.rule(Rule(3));
+                self.expect(3);
+            },
+            t => panic!("Parse error! Found error entry in table for {} on {}.", "B", TERMINAL_NAMES[t]),
+        }
+    }
+
+    fn nonterminal_3(&mut self) {
+        match self.peek() {
+            // C -> ε
+            0 => {
+                self.callbacks.rule(Rule(4));
+            },
+            // C -> f
+            4 => {
+                self.callbacks.rule(Rule(5));
+                self.expect(4);
+            },
+            t => panic!("Parse error! Found error entry in table for {} on {}.", "C", TERMINAL_NAMES[t]),
+        }
+    }
+
+}